@@ -1,13 +1,12 @@
-use std::{
-    collections::hash_map::DefaultHasher,
-    fmt::Debug,
-    hash::{Hash, Hasher},
-};
+use std::{fmt::Debug, hash::Hash};
 
 #[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
 pub struct Delta<T> {
     pub start: Option<String>,
     pub end: Option<T>,
+    /// The value was erased as personal data, see [`Delta::redacted`]
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub redacted: bool,
 }
 
 impl<T> std::default::Default for Delta<T> {
@@ -15,6 +14,7 @@ impl<T> std::default::Default for Delta<T> {
         Self {
             start: None,
             end: None,
+            redacted: false,
         }
     }
 }
@@ -24,8 +24,20 @@ impl<T> Delta<T> {
         init_value.map(|inner| Delta {
             start: None,
             end: Some(inner),
+            redacted: false,
         })
     }
+
+    /// The marker left in place of a `#[personal_data]` field once erased.
+    ///
+    /// It changes nothing when replayed, so the field rolls up as if never set.
+    pub fn redacted() -> Self {
+        Self {
+            start: None,
+            end: None,
+            redacted: true,
+        }
+    }
 }
 
 pub trait Del<S>: From<S> + Into<S>
@@ -33,40 +45,266 @@ where
     S: serde::Serialize,
 {
     fn ty() -> String;
+    /// Apply every field of the delta whose `start` hash matches, skipping the rest.
+    ///
+    /// Used when replaying deltas that have already been accepted.
     fn apply(&mut self, del: S);
+    /// Apply the delta only if every supplied `start` hash matches the current value.
+    ///
+    /// Nothing is applied when any field conflicts.
+    fn try_apply(&mut self, del: S) -> Result<(), DeltaConflict>;
 }
 
 pub trait Store {
     fn ty() -> String;
+    /// The uuid the tables key the entity on, the value of its primary Yoda identifier
     fn identifier(&self) -> String;
+    /// The values no other entity of the type may have: its identifiers and `#[unique]` fields
+    fn claims(&self) -> Vec<Claim>;
+    /// The lifecycle this delta moves the entity to, if it is a tombstone or restores one
+    fn lifecycle(&self) -> Option<Lifecycle>;
+    /// A delta changing nothing but the entity's lifecycle
+    fn tombstone(lifecycle: Lifecycle) -> Self
+    where
+        Self: Sized;
+
+    /// The text to index for full-text search, if any fields are `#[searchable(text)]`
+    fn document(&self) -> Option<crate::search::Document> {
+        None
+    }
+
+    /// The `#[personal_data]` fields, which erasing an entity redacts
+    fn personal_data() -> Vec<String>
+    where
+        Self: Sized,
+    {
+        vec![]
+    }
+
+    /// Every reference the entity holds, by field, see [`crate::Referrer`]
+    fn references(&self) -> Vec<crate::Referral> {
+        vec![]
+    }
+
+    /// The references held by each `#[relation]` field the delta sets
+    fn relations(&self) -> Vec<Relation> {
+        vec![]
+    }
+
+    /// The indexes searches over this type rely on, see `src/bin/indexes.rs`
+    fn indexes() -> Vec<crate::search::Index>
+    where
+        Self: Sized,
+    {
+        vec![]
+    }
+}
+
+/// A field whose `start` hash did not match the value currently stored
+#[derive(serde::Serialize, Debug, Clone)]
+pub struct FieldConflict {
+    pub field: String,
+    pub hash: String,
+    pub value: serde_json::Value,
 }
 
-pub fn check_delta<T: Debug, S: Hash + PartialEq + Debug>(
+/// The set of fields that rejected a delta
+#[derive(serde::Serialize, Debug, Clone, Default)]
+pub struct DeltaConflict {
+    pub conflicts: Vec<FieldConflict>,
+}
+
+impl std::fmt::Display for DeltaConflict {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let fields: Vec<_> = self
+            .conflicts
+            .iter()
+            .map(|conflict| conflict.field.as_str())
+            .collect();
+
+        write!(f, "Stale start hash supplied for: {}", fields.join(", "))
+    }
+}
+
+impl async_graphql::ErrorExtensions for DeltaConflict {
+    fn extend(&self) -> async_graphql::Error {
+        let conflicts = serde_json::to_value(&self.conflicts)
+            .ok()
+            .and_then(|json| async_graphql::Value::from_json(json).ok())
+            .unwrap_or_default();
+
+        async_graphql::Error::new(self.to_string()).extend_with(|_, e| {
+            e.set("code", "CONFLICT");
+            e.set("conflicts", conflicts);
+        })
+    }
+}
+
+/// Whether an entity can be found, kept in the delta log under the `lifecycle` key
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Lifecycle {
+    Active,
+    /// Hidden from finds and searches until restored
+    Deleted,
+    /// Erased: the projection is gone and every delta body redacted
+    Purged,
+}
+
+impl Lifecycle {
+    /// The lifecycle of an entity after its deltas, which begin active
+    pub fn of<'a, S: Store + 'a>(dels: impl IntoIterator<Item = &'a S>) -> Self {
+        dels.into_iter()
+            .filter_map(Store::lifecycle)
+            .last()
+            .unwrap_or(Self::Active)
+    }
+}
+
+/// A value identifying at most one entity of a type
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Claim {
+    /// The system of an identifier, or the name of a `#[unique]` field
+    pub key: String,
+    pub value: String,
+}
+
+impl Claim {
+    pub fn identifier(identifier: &crate::Identifier) -> Self {
+        Self {
+            key: identifier.system.to_string(),
+            value: identifier.value.clone(),
+        }
+    }
+
+    pub fn field<T: serde::Serialize>(field: &str, value: &T) -> Self {
+        Self {
+            key: field.into(),
+            value: crate::search::text(&serde_json::to_value(value).unwrap_or_default()),
+        }
+    }
+}
+
+/// A write refused because another entity of the type already claims one of its values
+#[derive(serde::Serialize, Debug, Clone, PartialEq)]
+pub struct Duplicate {
+    pub ty: String,
+    pub key: String,
+    pub value: String,
+    /// The entity holding the value
+    pub id: String,
+}
+
+impl Duplicate {
+    /// The global id of the entity holding the value, as the API names it
+    pub fn global_id(&self) -> async_graphql::ID {
+        crate::node::GlobalId::new(&self.ty, &self.id).encode()
+    }
+}
+
+impl std::fmt::Display for Duplicate {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} {} already has {} {}",
+            self.ty,
+            self.global_id().as_str(),
+            self.key,
+            self.value
+        )
+    }
+}
+
+impl async_graphql::ErrorExtensions for Duplicate {
+    fn extend(&self) -> async_graphql::Error {
+        async_graphql::Error::new(self.to_string()).extend_with(|_, e| {
+            e.set("code", "DUPLICATE");
+            e.set("key", self.key.as_str());
+            e.set("value", self.value.as_str());
+            e.set("id", self.global_id().as_str());
+        })
+    }
+}
+
+/// How a `#[relation]` field's references are checked as they are written
+#[derive(serde::Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Integrity {
+    /// Refuse the write
+    Strict,
+    /// Log the dangling reference and accept the write
+    Warn,
+    Off,
+}
+
+/// The references a `#[relation]` field holds, and the type they must point at
+#[derive(Debug, Clone)]
+pub struct Relation {
+    pub field: String,
+    /// The type of the entities referred to
+    pub ty: String,
+    pub integrity: Integrity,
+    pub references: Vec<crate::Reference>,
+}
+
+/// A reference to no entity of the type its field relates to
+#[derive(serde::Serialize, Debug, Clone, PartialEq)]
+pub struct Dangling {
+    pub ty: String,
+    /// The entity holding the reference
+    pub id: String,
+    pub field: String,
+    /// The type the reference should point at
+    pub target: String,
+    pub reference: crate::Reference,
+}
+
+impl std::fmt::Display for Dangling {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} {} {} refers to no {} with identifier {}",
+            self.ty,
+            self.id,
+            self.field,
+            self.target,
+            self.reference.identifier()
+        )
+    }
+}
+
+impl async_graphql::ErrorExtensions for Dangling {
+    fn extend(&self) -> async_graphql::Error {
+        async_graphql::Error::new(self.to_string()).extend_with(|_, e| {
+            e.set("code", "DANGLING_REFERENCE");
+            e.set("field", self.field.as_str());
+            e.set("target", self.target.as_str());
+            e.set("identifier", self.reference.identifier().to_string());
+        })
+    }
+}
+
+pub fn check_delta<T: Debug, S: Hash + PartialEq + Debug + serde::Serialize>(
+    field: &str,
     curr: Option<&S>,
     del: Delta<T>,
-) -> Option<T> {
-    let Delta { start, end } = del;
-
-    if let Some(curr) = curr {
-        let mut hasher = DefaultHasher::new();
-        curr.hash(&mut hasher);
-        let hash = hasher.finish().to_string();
-
-        if let Some(start) = start {
-            if hash == start {
-                end
-            } else {
-                None
-            }
-        } else {
-            end
+) -> Result<Option<T>, FieldConflict> {
+    let Delta { start, end, .. } = del;
+
+    if let (Some(curr), Some(start)) = (curr, start) {
+        if !crate::hash::matches(curr, &start) {
+            return Err(FieldConflict {
+                field: field.into(),
+                hash: crate::hash::hash(curr),
+                value: serde_json::to_value(curr).unwrap_or_default(),
+            });
         }
-    } else {
-        end
     }
+
+    Ok(end)
 }
 pub trait Rollup<T> {
     fn rollup(self) -> T;
+    /// Apply the deltas on top of an already rolled-up value, such as a snapshot
+    fn rollup_onto(self, start: T) -> T;
 }
 impl<S, B> Rollup<B> for std::vec::IntoIter<S>
 where
@@ -74,7 +312,10 @@ where
     B: Del<S> + Default,
 {
     fn rollup(self) -> B {
-        let mut res = B::default();
+        self.rollup_onto(B::default())
+    }
+    fn rollup_onto(self, start: B) -> B {
+        let mut res = start;
         self.for_each(|del| res.apply(del));
         res
     }
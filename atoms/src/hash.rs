@@ -0,0 +1,74 @@
+use std::{
+    collections::hash_map::DefaultHasher,
+    hash::{Hash, Hasher},
+};
+
+/// Version tag prefixed to every hash handed to clients, as `<version>:<digest>`.
+///
+/// `v1` is the lowercase hex SHA-256 digest of the value's canonical JSON:
+/// object keys sorted bytewise and no insignificant whitespace.
+pub const HASH_VERSION: &str = "v1";
+
+/// Hash a value for use as a `Delta.start` precondition
+pub fn hash<S: serde::Serialize + ?Sized>(value: &S) -> String {
+    use sha2::{Digest, Sha256};
+
+    let json = serde_json::to_value(value).unwrap_or_default();
+
+    let mut canonical = String::new();
+    write_canonical(&json, &mut canonical);
+
+    format!(
+        "{}:{:x}",
+        HASH_VERSION,
+        Sha256::digest(canonical.as_bytes())
+    )
+}
+
+/// Check a `Delta.start` hash against the current value.
+///
+/// Untagged hashes were produced by `DefaultHasher` before hashes were versioned.
+/// They are still compared the old way so deltas already in the log keep replaying,
+/// but they are only stable on the toolchain that produced them.
+pub fn matches<S: Hash + serde::Serialize + ?Sized>(value: &S, start: &str) -> bool {
+    match start.split_once(':') {
+        Some((HASH_VERSION, _)) => hash(value) == start,
+        Some(_) => false,
+        None => {
+            let mut hasher = DefaultHasher::new();
+            value.hash(&mut hasher);
+            hasher.finish().to_string() == start
+        }
+    }
+}
+
+fn write_canonical(value: &serde_json::Value, out: &mut String) {
+    match value {
+        serde_json::Value::Object(map) => {
+            let mut entries: Vec<_> = map.iter().collect();
+            entries.sort_by_key(|(key, _)| *key);
+
+            out.push('{');
+            for (i, (key, value)) in entries.into_iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                out.push_str(&serde_json::Value::String(key.clone()).to_string());
+                out.push(':');
+                write_canonical(value, out);
+            }
+            out.push('}');
+        }
+        serde_json::Value::Array(values) => {
+            out.push('[');
+            for (i, value) in values.iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                write_canonical(value, out);
+            }
+            out.push(']');
+        }
+        other => out.push_str(&other.to_string()),
+    }
+}
@@ -0,0 +1,120 @@
+use async_graphql::{Json, SimpleObject};
+use sqlx::types::chrono::{DateTime, Utc};
+
+use crate::delta::{Del, Lifecycle};
+
+/// A delta as stored in the log, with who wrote it and when
+#[derive(Debug, Clone)]
+pub struct DeltaRecord<S> {
+    pub body: S,
+    pub author: String,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(SimpleObject, Clone, Debug)]
+pub struct FieldChange {
+    pub field: String,
+    pub before: Json<serde_json::Value>,
+    pub after: Json<serde_json::Value>,
+    /// The values were erased as personal data
+    pub redacted: bool,
+}
+
+#[derive(SimpleObject, Clone, Debug)]
+pub struct HistoryEntry {
+    /// Number of deltas applied once this one is, starting at 1
+    pub version: usize,
+    pub author: String,
+    pub created_at: DateTime<Utc>,
+    pub changes: Vec<FieldChange>,
+}
+
+/// Replay `records` in order, reporting the fields each delta changed
+pub fn entries<B, S>(records: Vec<DeltaRecord<S>>) -> Vec<HistoryEntry>
+where
+    S: serde::Serialize,
+    B: Del<S> + Default + Clone,
+{
+    entries_onto(&mut B::default(), &mut Lifecycle::Active, 0, records)
+}
+
+/// Replay `records` on top of an entity already rolled up to `version`, in its lifecycle,
+/// reporting the fields each delta changed and leaving both as of the last
+pub fn entries_onto<B, S>(
+    current: &mut B,
+    lifecycle: &mut Lifecycle,
+    version: usize,
+    records: Vec<DeltaRecord<S>>,
+) -> Vec<HistoryEntry>
+where
+    S: serde::Serialize,
+    B: Del<S> + Clone,
+{
+    records
+        .into_iter()
+        .enumerate()
+        .map(|(i, record)| {
+            let DeltaRecord {
+                body,
+                author,
+                created_at,
+            } = record;
+
+            let supplied = serde_json::to_value(&body).unwrap_or_default();
+            let mut before = values(current);
+            before["lifecycle"] = serde_json::json!(lifecycle);
+            current.apply(body);
+            if let Ok(Some(next)) = serde_json::from_value(supplied["lifecycle"]["end"].clone()) {
+                *lifecycle = next;
+            }
+            let mut after = values(current);
+            after["lifecycle"] = serde_json::json!(lifecycle);
+
+            let changes = supplied
+                .as_object()
+                .map(|fields| {
+                    fields
+                        .iter()
+                        .map(|(field, delta)| (field, delta, delta["redacted"] == true))
+                        .filter(|(_, delta, redacted)| !delta["end"].is_null() || *redacted)
+                        .map(|(field, _, redacted)| {
+                            (field, &before[field], &after[field], redacted)
+                        })
+                        // An erased value shows where it was, though it no longer changes anything
+                        .filter(|(_, before, after, redacted)| before != after || *redacted)
+                        .map(|(field, before, after, redacted)| FieldChange {
+                            field: field.clone(),
+                            before: Json(before.clone()),
+                            after: Json(after.clone()),
+                            redacted,
+                        })
+                        .collect()
+                })
+                .unwrap_or_default();
+
+            HistoryEntry {
+                version: version + i + 1,
+                author,
+                created_at,
+                changes,
+            }
+        })
+        .collect()
+}
+
+/// The value of every field of `doc`, keyed by field name
+fn values<B, S>(doc: &B) -> serde_json::Value
+where
+    S: serde::Serialize,
+    B: Del<S> + Clone,
+{
+    let store: S = doc.clone().into();
+
+    match serde_json::to_value(store).unwrap_or_default() {
+        serde_json::Value::Object(fields) => fields
+            .into_iter()
+            .map(|(field, delta)| (field, delta.get("end").cloned().unwrap_or_default()))
+            .collect(),
+        _ => Default::default(),
+    }
+}
@@ -3,6 +3,9 @@ use derive_more::Display;
 use serde::{Deserialize, Serialize};
 
 pub mod delta;
+pub mod hash;
+pub mod history;
+pub mod node;
 pub mod pagination;
 pub mod search;
 
@@ -27,9 +30,9 @@ impl Identifier {
     }
 }
 
-impl ToString for Identifier {
-    fn to_string(&self) -> String {
-        format!("{}/{}", self.system, self.value)
+impl std::fmt::Display for Identifier {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}/{}", self.system, self.value)
     }
 }
 
@@ -63,10 +66,7 @@ impl From<IdentifierInput> for String {
 impl From<IdentifierInput> for Identifier {
     fn from(input: IdentifierInput) -> Self {
         Self {
-            system: match input.system.to_string().as_str() {
-                "Yoda" => IdentifierSystem::Yoda,
-                _ => IdentifierSystem::Other,
-            },
+            system: input.system,
             value: input.value,
             tier: input.tier,
         }
@@ -87,10 +87,52 @@ pub struct Reference {
     value: Identifier,
 }
 
-#[derive(Enum, Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize, Hash)]
+impl Reference {
+    pub fn reference_type(&self) -> ReferenceType {
+        self.ty
+    }
+
+    /// The identifier of the entity referred to
+    pub fn identifier(&self) -> &Identifier {
+        &self.value
+    }
+}
+
+#[derive(Enum, Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize, Hash, Display)]
 pub enum ReferenceType {
     StripeTransaction,
     StripePaymentMethod,
     User,
     Organization,
 }
+
+/// A reference held in a field of an entity, indexed so the entity it points to can find it
+#[derive(Clone, Debug, PartialEq)]
+pub struct Referral {
+    pub field: String,
+    pub reference: Reference,
+}
+
+/// An entity referring to another through one of its fields
+#[derive(SimpleObject, Clone, Debug, PartialEq, Eq)]
+#[graphql(complex)]
+pub struct Referrer {
+    /// The type of the referring entity
+    pub ty: String,
+    /// The uuid of the referring entity, given to clients as its global id
+    #[graphql(skip)]
+    pub id: String,
+    pub field: String,
+    pub reference_type: ReferenceType,
+}
+
+#[async_graphql::ComplexObject]
+impl Referrer {
+    /// The global id of the referring entity
+    async fn id(&self) -> async_graphql::ID {
+        node::GlobalId::new(&self.ty, &self.id).encode()
+    }
+}
+
+#[cfg(test)]
+mod test;
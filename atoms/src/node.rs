@@ -0,0 +1,50 @@
+use async_graphql::ID;
+
+/// The id of an entity across the API: its type and the uuid the tables key it on.
+///
+/// Encoded as an opaque [`ID`], so clients pass back exactly what they were given.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct GlobalId {
+    pub ty: String,
+    pub id: String,
+}
+
+impl GlobalId {
+    pub fn new(ty: impl Into<String>, id: impl Into<String>) -> Self {
+        Self {
+            ty: ty.into(),
+            id: id.into(),
+        }
+    }
+
+    pub fn encode(&self) -> ID {
+        base64::encode_config(format!("{}:{}", self.ty, self.id), base64::URL_SAFE_NO_PAD).into()
+    }
+
+    pub fn decode(id: &ID) -> async_graphql::Result<Self> {
+        let invalid = || async_graphql::Error::new(format!("Invalid id: {}", id.as_str()));
+
+        let bytes =
+            base64::decode_config(id.as_str(), base64::URL_SAFE_NO_PAD).map_err(|_| invalid())?;
+        let decoded = String::from_utf8(bytes).map_err(|_| invalid())?;
+
+        match decoded.split_once(':') {
+            Some((ty, id)) if !ty.is_empty() && !id.is_empty() => Ok(Self::new(ty, id)),
+            _ => Err(invalid()),
+        }
+    }
+
+    /// The uuid within the id of an entity of type `ty`, refusing the ids of other types
+    pub fn of(id: &ID, ty: &str) -> async_graphql::Result<String> {
+        let global = Self::decode(id)?;
+
+        if global.ty != ty {
+            return Err(async_graphql::Error::new(format!(
+                "Expected the id of type {}, not {}",
+                ty, global.ty
+            )));
+        }
+
+        Ok(global.id)
+    }
+}
@@ -1,7 +1,182 @@
-use async_graphql::InputObject;
+use std::cmp::Ordering;
+
+use async_graphql::{connection::CursorType, Enum, InputObject};
+use serde::{Deserialize, Serialize};
+
+use crate::search::{self, Kind};
 
 #[derive(Default, InputObject, Copy, Clone)]
 pub struct PaginationOption {
     pub limit: usize,
     pub skip: usize,
 }
+
+impl PaginationOption {
+    /// The page after `cursor`, the index an edge of the previous page was given,
+    /// or the first page without one
+    pub fn after(cursor: Option<&str>, limit: usize) -> Result<Self, std::num::ParseIntError> {
+        let skip = match cursor {
+            Some(cursor) => cursor.parse::<usize>()? + 1,
+            None => 0,
+        };
+
+        Ok(Self { limit, skip })
+    }
+}
+
+#[derive(Enum, Copy, Clone, PartialEq, Eq, Debug, Default)]
+pub enum Direction {
+    #[default]
+    Asc,
+    Desc,
+}
+
+/// What search results are ordered by
+#[derive(Clone, Debug, PartialEq)]
+pub enum SortBy {
+    /// When the projection was created
+    LastUpdated,
+    Field(String),
+    /// How well the projection matches a full-text search, missing without one
+    Relevance,
+    /// How close the projection is to a fuzzy search, missing without one
+    Similarity,
+}
+
+/// The order of search results.
+///
+/// Results missing the value come last in either direction, and ties are broken by id.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Sort {
+    pub by: SortBy,
+    pub kind: Kind,
+    pub direction: Direction,
+}
+
+impl Default for Sort {
+    fn default() -> Self {
+        Self {
+            by: SortBy::LastUpdated,
+            kind: Kind::DateTime,
+            direction: Direction::Asc,
+        }
+    }
+}
+
+impl Sort {
+    /// The best matches of a full-text search first
+    pub fn relevance() -> Self {
+        Self {
+            by: SortBy::Relevance,
+            kind: Kind::Number,
+            direction: Direction::Desc,
+        }
+    }
+
+    /// The closest matches of a fuzzy search first
+    pub fn similarity() -> Self {
+        Self {
+            by: SortBy::Similarity,
+            kind: Kind::Number,
+            direction: Direction::Desc,
+        }
+    }
+
+    pub fn ascending(&self) -> bool {
+        self.direction == Direction::Asc
+    }
+
+    /// Where `a` falls relative to `b` in this order
+    pub fn compare(&self, a: &Key, b: &Key) -> Ordering {
+        let ordering = match (a.value.is_null(), b.value.is_null()) {
+            (true, false) => return Ordering::Greater,
+            (false, true) => return Ordering::Less,
+            (true, true) => a.id.cmp(&b.id),
+            (false, false) => search::compare(self.kind, &a.value, &b.value)
+                .unwrap_or(Ordering::Equal)
+                .then_with(|| a.id.cmp(&b.id)),
+        };
+
+        match self.direction {
+            Direction::Asc => ordering,
+            Direction::Desc => ordering.reverse(),
+        }
+    }
+}
+
+/// Where a search result falls in its order: the value sorted by and the id breaking ties.
+///
+/// Used as an opaque cursor, so pages stay put when results are written in between.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Key {
+    pub value: serde_json::Value,
+    pub id: String,
+}
+
+impl Key {
+    /// The value as the text it is compared against
+    pub fn text(&self) -> String {
+        search::text(&self.value)
+    }
+}
+
+impl CursorType for Key {
+    type Error = String;
+
+    fn decode_cursor(s: &str) -> Result<Self, Self::Error> {
+        let bytes = base64::decode_config(s, base64::URL_SAFE_NO_PAD)
+            .map_err(|_| "Invalid cursor".to_string())?;
+
+        serde_json::from_slice(&bytes).map_err(|_| "Invalid cursor".to_string())
+    }
+
+    fn encode_cursor(&self) -> String {
+        base64::encode_config(
+            serde_json::to_vec(self).unwrap_or_default(),
+            base64::URL_SAFE_NO_PAD,
+        )
+    }
+}
+
+/// A page of sorted search results between two keys
+#[derive(Clone, Debug, Default)]
+pub struct Page {
+    pub sort: Sort,
+    /// Only results after this key
+    pub after: Option<Key>,
+    /// Only results before this key
+    pub before: Option<Key>,
+    pub limit: usize,
+    /// Take the last `limit` results rather than the first
+    pub backward: bool,
+}
+
+impl Page {
+    /// The page for the arguments of a connection field, 100 results by default
+    pub fn new(
+        sort: Sort,
+        after: Option<Key>,
+        before: Option<Key>,
+        first: Option<usize>,
+        last: Option<usize>,
+    ) -> Self {
+        Self {
+            sort,
+            after,
+            before,
+            limit: first.or(last).unwrap_or(100),
+            backward: first.is_none() && last.is_some(),
+        }
+    }
+
+    /// Whether `key` falls between the page's bounds
+    pub fn contains(&self, key: &Key) -> bool {
+        self.after
+            .as_ref()
+            .is_none_or(|after| self.sort.compare(key, after) == Ordering::Greater)
+            && self
+                .before
+                .as_ref()
+                .is_none_or(|before| self.sort.compare(key, before) == Ordering::Less)
+    }
+}
@@ -1,7 +1,540 @@
-pub type SqlQuery<'a> = sqlx::query::Query<'a, sqlx::Postgres, sqlx::postgres::PgArguments>;
+use std::cmp::Ordering;
+
+use async_graphql::InputObject;
+use serde::Serialize;
+use sqlx::types::chrono::{DateTime, Utc};
+
+/// A backend independent search over projection bodies
 pub trait Search<S> {
     fn ty() -> String;
-    fn array_splits(&self) -> Vec<String>;
-    fn paths(&self) -> Vec<String>;
-    fn bind_args<'a>(&self, query: SqlQuery<'a>) -> SqlQuery<'a>;
+    fn conditions(&self) -> Vec<Condition>;
+
+    /// Words to find in the text of each projection, see [`Document`]
+    fn text(&self) -> Option<TextQuery> {
+        None
+    }
+
+    /// A value to find in `#[searchable(fuzzy)]` fields despite typos
+    fn fuzzy(&self) -> Option<FuzzyQuery> {
+        None
+    }
+
+    fn criteria(&self) -> Criteria {
+        Criteria {
+            conditions: self.conditions(),
+            text: self.text(),
+            fuzzy: self.fuzzy(),
+        }
+    }
 }
+
+/// Everything a projection has to match to be found
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Criteria {
+    pub conditions: Vec<Condition>,
+    pub text: Option<TextQuery>,
+    pub fuzzy: Option<FuzzyQuery>,
+}
+
+impl From<Vec<Condition>> for Criteria {
+    fn from(conditions: Vec<Condition>) -> Self {
+        Self {
+            conditions,
+            ..Default::default()
+        }
+    }
+}
+
+impl Criteria {
+    /// Whether a projection body, and its document, matches
+    pub fn matches(&self, body: &serde_json::Value, document: Option<&str>) -> bool {
+        self.conditions
+            .iter()
+            .all(|condition| condition.matches(body))
+            && self
+                .text
+                .as_ref()
+                .is_none_or(|text| document.is_some_and(|document| text.matches(document)))
+            && self
+                .fuzzy
+                .as_ref()
+                .is_none_or(|fuzzy| fuzzy.score(body).is_some())
+    }
+}
+
+/// Operators a searchable field accepts, chosen from the field's type
+pub trait Filter {
+    fn conditions(&self, field: &str) -> Vec<Condition>;
+}
+
+/// A filter on the current value of one field of a projection body
+#[derive(Debug, Clone, PartialEq)]
+pub struct Condition {
+    /// The field, followed by a dotted path into its value for nested structs
+    pub field: String,
+    /// The field is a list, matching when any element does
+    pub any: bool,
+    pub op: Operator,
+    pub kind: Kind,
+    /// An array of values for [`Operator::In`]
+    pub value: serde_json::Value,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Operator {
+    Eq,
+    /// Equal to any of the values
+    In,
+    Gt,
+    Gte,
+    Lt,
+    Lte,
+    /// The text starts with the value
+    Prefix,
+    /// The text contains the value, ignoring case
+    Contains,
+}
+
+/// How field values are compared
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Kind {
+    Text,
+    Number,
+    Boolean,
+    DateTime,
+}
+
+impl Condition {
+    pub fn new(field: &str, op: Operator, kind: Kind, value: impl Serialize) -> Self {
+        Self {
+            field: field.into(),
+            any: false,
+            op,
+            kind,
+            value: serde_json::to_value(value).unwrap_or_default(),
+        }
+    }
+
+    /// Match when any element of the list held by the field does
+    pub fn any(self) -> Self {
+        Self { any: true, ..self }
+    }
+
+    /// Move the condition inside `field`, for the filters of nested structs
+    pub fn within(self, field: &str) -> Self {
+        Self {
+            field: format!("{}.{}", field, self.field),
+            ..self
+        }
+    }
+
+    /// The top level field and the keys below it
+    pub fn path(&self) -> (&str, Vec<&str>) {
+        let mut keys = self.field.split('.');
+        let field = keys.next().unwrap_or_default();
+
+        (field, keys.collect())
+    }
+
+    /// Whether values have to be found through lists or nested structs,
+    /// rather than compared directly with the field
+    pub fn is_nested(&self) -> bool {
+        self.any || self.field.contains('.')
+    }
+
+    /// The value as the text it is compared against
+    pub fn text(&self) -> String {
+        text(&self.value)
+    }
+
+    /// Every value of an [`Operator::In`] as text
+    pub fn texts(&self) -> Vec<String> {
+        self.value
+            .as_array()
+            .map(|values| values.iter().map(text).collect())
+            .unwrap_or_default()
+    }
+
+    /// Evaluate the condition against a projection body.
+    ///
+    /// Lists along the path are searched element by element.
+    pub fn matches(&self, body: &serde_json::Value) -> bool {
+        let (field, keys) = self.path();
+
+        let mut values = vec![&body[field]["end"]];
+        for key in keys {
+            values = flatten(values).map(|value| &value[key]).collect();
+        }
+
+        flatten(values).any(|current| self.matches_value(current))
+    }
+
+    fn matches_value(&self, current: &serde_json::Value) -> bool {
+        if current.is_null() {
+            return false;
+        }
+
+        let ordering = || compare(self.kind, current, &self.value);
+
+        match self.op {
+            Operator::Eq => ordering() == Some(Ordering::Equal),
+            Operator::In => self.value.as_array().is_some_and(|values| {
+                values
+                    .iter()
+                    .any(|value| compare(self.kind, current, value) == Some(Ordering::Equal))
+            }),
+            Operator::Gt => ordering() == Some(Ordering::Greater),
+            Operator::Gte => matches!(ordering(), Some(Ordering::Greater) | Some(Ordering::Equal)),
+            Operator::Lt => ordering() == Some(Ordering::Less),
+            Operator::Lte => matches!(ordering(), Some(Ordering::Less) | Some(Ordering::Equal)),
+            Operator::Prefix => text(current).starts_with(&self.text()),
+            Operator::Contains => text(current)
+                .to_lowercase()
+                .contains(&self.text().to_lowercase()),
+        }
+    }
+}
+
+/// The text of a projection's `#[searchable(text)]` fields, kept for full-text search
+#[derive(Debug, Clone, PartialEq)]
+pub struct Document {
+    /// The text search configuration stemming its words, such as `english`
+    pub language: String,
+    pub text: String,
+}
+
+/// A full-text search, matching documents that contain every word
+#[derive(Debug, Clone, PartialEq)]
+pub struct TextQuery {
+    pub language: String,
+    pub query: String,
+}
+
+impl TextQuery {
+    /// The lowercase words searched for.
+    ///
+    /// Backends without a text search engine look for these as they are, without stemming.
+    pub fn words(&self) -> Vec<String> {
+        self.query
+            .split_whitespace()
+            .map(|word| word.to_lowercase())
+            .collect()
+    }
+
+    pub fn matches(&self, text: &str) -> bool {
+        let text = text.to_lowercase();
+        self.words().iter().all(|word| text.contains(word.as_str()))
+    }
+
+    /// How often the words occur in `text`
+    pub fn rank(&self, text: &str) -> f64 {
+        let text = text.to_lowercase();
+        self.words()
+            .iter()
+            .map(|word| text.matches(word.as_str()).count() as f64)
+            .sum()
+    }
+
+    /// `text` with each word found wrapped in `<b>` tags
+    pub fn highlight(&self, text: &str) -> String {
+        // Only ASCII is lowercased, keeping the offsets of both the same
+        let lower = text.to_ascii_lowercase();
+        let words = self.words();
+
+        let mut res = String::new();
+        let mut i = 0;
+        while i < text.len() {
+            let found = words
+                .iter()
+                .filter(|word| !word.is_empty() && lower[i..].starts_with(word.as_str()))
+                .map(|word| word.len())
+                .max();
+
+            match found {
+                Some(len) => {
+                    res.push_str("<b>");
+                    res.push_str(&text[i..i + len]);
+                    res.push_str("</b>");
+                    i += len;
+                }
+                None => {
+                    let c = text[i..].chars().next().unwrap_or_default();
+                    res.push(c);
+                    i += c.len_utf8();
+                }
+            }
+        }
+
+        res
+    }
+}
+
+/// A search for values close to `query` in any of a few text fields
+#[derive(Debug, Clone, PartialEq)]
+pub struct FuzzyQuery {
+    pub query: String,
+    pub fields: Vec<FuzzyField>,
+}
+
+/// A text field searched by a [`FuzzyQuery`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct FuzzyField {
+    pub field: String,
+    /// The [`similarity`] a value needs to match, from 0 to 1
+    pub threshold: f64,
+}
+
+impl FuzzyQuery {
+    /// The similarity of the closest field, when any is close enough to match
+    pub fn score(&self, body: &serde_json::Value) -> Option<f64> {
+        let similarities: Vec<_> = self
+            .fields
+            .iter()
+            .filter_map(|field| {
+                let value = body[&field.field]["end"].as_str()?;
+                Some((similarity(value, &self.query), field.threshold))
+            })
+            .collect();
+
+        if !similarities
+            .iter()
+            .any(|(similarity, threshold)| similarity >= threshold)
+        {
+            return None;
+        }
+
+        similarities
+            .into_iter()
+            .map(|(similarity, _)| similarity)
+            .reduce(f64::max)
+    }
+}
+
+/// How alike two texts are, from 0 to 1, as the share of trigrams they have in common.
+///
+/// Follows `pg_trgm`: each lowercase word is padded with two spaces in front and one behind,
+/// and anything but letters and digits separates words.
+pub fn similarity(a: &str, b: &str) -> f64 {
+    let a = trigrams(a);
+    let b = trigrams(b);
+
+    let shared = a.intersection(&b).count();
+    let all = a.len() + b.len() - shared;
+
+    if all == 0 {
+        0.0
+    } else {
+        shared as f64 / all as f64
+    }
+}
+
+fn trigrams(text: &str) -> std::collections::BTreeSet<[char; 3]> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .flat_map(|word| {
+            let padded: Vec<_> = "  "
+                .chars()
+                .chain(word.chars().flat_map(char::to_lowercase))
+                .chain(std::iter::once(' '))
+                .collect();
+
+            padded
+                .windows(3)
+                .map(|trigram| [trigram[0], trigram[1], trigram[2]])
+                .collect::<Vec<_>>()
+        })
+        .collect()
+}
+
+/// An index on projections that searches over one type rely on
+#[derive(Debug, Clone, PartialEq)]
+pub struct Index {
+    pub ty: String,
+    pub field: String,
+    pub method: IndexMethod,
+}
+
+/// What an [`Index`] speeds up
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IndexMethod {
+    /// Comparing and sorting the field's value as the kind
+    Value(Kind),
+    /// Finding substrings of the field's text, and fuzzy matches
+    Trigram,
+}
+
+impl Index {
+    pub fn value(ty: &str, field: &str, kind: Kind) -> Self {
+        Self {
+            ty: ty.into(),
+            field: field.into(),
+            method: IndexMethod::Value(kind),
+        }
+    }
+
+    pub fn trigram(ty: &str, field: &str) -> Self {
+        Self {
+            ty: ty.into(),
+            field: field.into(),
+            method: IndexMethod::Trigram,
+        }
+    }
+
+    /// The name the index is created with, the same for every backend
+    pub fn name(&self) -> String {
+        let suffix = match self.method {
+            IndexMethod::Value(_) => "",
+            IndexMethod::Trigram => "_trgm",
+        };
+
+        format!(
+            "projection_{}_{}{}",
+            self.ty.to_lowercase(),
+            self.field,
+            suffix
+        )
+    }
+}
+
+fn flatten<'a>(
+    values: Vec<&'a serde_json::Value>,
+) -> impl Iterator<Item = &'a serde_json::Value> + 'a {
+    values.into_iter().flat_map(|value| match value {
+        serde_json::Value::Array(values) => values.iter().collect::<Vec<_>>(),
+        value => vec![value],
+    })
+}
+
+/// Compare two values of a field, `None` when either isn't of the kind
+pub(crate) fn compare(
+    kind: Kind,
+    current: &serde_json::Value,
+    value: &serde_json::Value,
+) -> Option<Ordering> {
+    match kind {
+        Kind::Text => Some(text(current).cmp(&text(value))),
+        Kind::Number => current.as_f64()?.partial_cmp(&value.as_f64()?),
+        Kind::Boolean => Some(current.as_bool()?.cmp(&value.as_bool()?)),
+        Kind::DateTime => {
+            let parse =
+                |value: &serde_json::Value| DateTime::parse_from_rfc3339(value.as_str()?).ok();
+            Some(parse(current)?.cmp(&parse(value)?))
+        }
+    }
+}
+
+pub(crate) fn text(value: &serde_json::Value) -> String {
+    match value {
+        serde_json::Value::String(text) => text.clone(),
+        other => other.to_string(),
+    }
+}
+
+fn push<T: Serialize>(
+    res: &mut Vec<Condition>,
+    field: &str,
+    op: Operator,
+    kind: Kind,
+    value: &Option<T>,
+) {
+    if let Some(value) = value {
+        res.push(Condition::new(field, op, kind, value))
+    }
+}
+
+/// Operators for text fields
+#[derive(InputObject, Default, Clone, Debug)]
+pub struct StringFilter {
+    pub eq: Option<String>,
+    #[graphql(name = "in")]
+    pub in_: Option<Vec<String>>,
+    /// Starts with, case sensitive
+    pub prefix: Option<String>,
+    /// Contains, ignoring case
+    pub contains: Option<String>,
+}
+
+impl Filter for StringFilter {
+    fn conditions(&self, field: &str) -> Vec<Condition> {
+        let mut res = vec![];
+
+        push(&mut res, field, Operator::Eq, Kind::Text, &self.eq);
+        push(&mut res, field, Operator::In, Kind::Text, &self.in_);
+        push(&mut res, field, Operator::Prefix, Kind::Text, &self.prefix);
+        push(
+            &mut res,
+            field,
+            Operator::Contains,
+            Kind::Text,
+            &self.contains,
+        );
+
+        res
+    }
+}
+
+/// Operators for boolean fields
+#[derive(InputObject, Default, Clone, Debug)]
+pub struct BooleanFilter {
+    pub eq: Option<bool>,
+}
+
+impl Filter for BooleanFilter {
+    fn conditions(&self, field: &str) -> Vec<Condition> {
+        let mut res = vec![];
+
+        push(&mut res, field, Operator::Eq, Kind::Boolean, &self.eq);
+
+        res
+    }
+}
+
+macro_rules! range_filter {
+    ($(#[$attr:meta])* $name:ident, $ty:ty, $kind:expr) => {
+        $(#[$attr])*
+        #[derive(InputObject, Default, Clone, Debug)]
+        pub struct $name {
+            pub eq: Option<$ty>,
+            #[graphql(name = "in")]
+            pub in_: Option<Vec<$ty>>,
+            pub gt: Option<$ty>,
+            pub gte: Option<$ty>,
+            pub lt: Option<$ty>,
+            pub lte: Option<$ty>,
+        }
+
+        impl Filter for $name {
+            fn conditions(&self, field: &str) -> Vec<Condition> {
+                let mut res = vec![];
+
+                push(&mut res, field, Operator::Eq, $kind, &self.eq);
+                push(&mut res, field, Operator::In, $kind, &self.in_);
+                push(&mut res, field, Operator::Gt, $kind, &self.gt);
+                push(&mut res, field, Operator::Gte, $kind, &self.gte);
+                push(&mut res, field, Operator::Lt, $kind, &self.lt);
+                push(&mut res, field, Operator::Lte, $kind, &self.lte);
+
+                res
+            }
+        }
+    };
+}
+
+range_filter!(
+    /// Operators for integer fields
+    IntFilter,
+    i64,
+    Kind::Number
+);
+range_filter!(
+    /// Operators for floating point fields
+    FloatFilter,
+    f64,
+    Kind::Number
+);
+range_filter!(
+    /// Operators for `DateTime` fields
+    DateTimeFilter,
+    DateTime<Utc>,
+    Kind::DateTime
+);
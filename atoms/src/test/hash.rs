@@ -0,0 +1,40 @@
+use crate::hash::{hash, matches};
+
+#[derive(serde::Serialize, Hash)]
+struct Unordered {
+    b: u32,
+    a: Vec<Option<String>>,
+}
+
+#[test]
+fn hash_known_value() {
+    assert_eq!(
+        hash(&"Boston"),
+        "v1:82932ddce217e2199c3cd9ba126ab94c5a0bca7f820ad501ad2f39d4dd9d90cd"
+    );
+}
+
+#[test]
+fn hash_sorts_keys() {
+    let value = Unordered {
+        b: 1,
+        a: vec![None, Some("x".into())],
+    };
+
+    assert_eq!(
+        hash(&value),
+        "v1:6566c16d5797373869cdb3d2515191ebb5cdfe414b840af9cbdafbd6750d9fb2"
+    );
+}
+
+#[test]
+fn hash_option_matches_inner() {
+    assert_eq!(hash(&Some("Boston")), hash(&"Boston"));
+}
+
+#[test]
+fn matches_versioned() {
+    assert!(matches(&"Boston", &hash(&"Boston")));
+    assert!(!matches(&"Boston", &hash(&"Cambridge")));
+    assert!(!matches(&"Boston", "v0:abc"));
+}
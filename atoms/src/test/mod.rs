@@ -0,0 +1,4 @@
+mod hash;
+mod node;
+mod pagination;
+mod search;
@@ -0,0 +1,47 @@
+use async_graphql::{ErrorExtensions, ID};
+
+use crate::{delta::Duplicate, node::GlobalId};
+
+#[test]
+fn global_id_round_trips() {
+    let global = GlobalId::new("Account", "00000000-0000-0000-0000-000000000001");
+
+    let id = global.encode();
+
+    assert_eq!(GlobalId::decode(&id).unwrap(), global);
+    assert_eq!(
+        GlobalId::of(&id, "Account").unwrap(),
+        "00000000-0000-0000-0000-000000000001"
+    );
+}
+
+#[test]
+fn global_id_of_another_type() {
+    let id = GlobalId::new("Account", "00000000-0000-0000-0000-000000000001").encode();
+
+    assert!(GlobalId::of(&id, "Transaction").is_err());
+}
+
+#[test]
+fn global_id_invalid() {
+    assert!(GlobalId::decode(&ID::from("00000000-0000-0000-0000-000000000001")).is_err());
+    assert!(GlobalId::decode(&GlobalId::new("Account", "").encode()).is_err());
+}
+
+#[test]
+fn duplicates_name_the_holder_by_global_id() {
+    let duplicate = Duplicate {
+        ty: "Account".into(),
+        key: "email".into(),
+        value: "leia@yoda.dev".into(),
+        id: "00000000-0000-0000-0000-000000000001".into(),
+    };
+
+    let error = serde_json::to_value(duplicate.extend()).unwrap();
+    let id = ID::from(error["extensions"]["id"].as_str().unwrap());
+
+    assert_eq!(
+        GlobalId::of(&id, "Account").unwrap(),
+        "00000000-0000-0000-0000-000000000001"
+    );
+}
@@ -0,0 +1,128 @@
+use std::cmp::Ordering;
+
+use async_graphql::connection::CursorType;
+use serde_json::json;
+
+use crate::{
+    pagination::{Direction, Key, Page, PaginationOption, Sort, SortBy},
+    search::Kind,
+};
+
+fn key(value: serde_json::Value, id: &str) -> Key {
+    Key {
+        value,
+        id: id.into(),
+    }
+}
+
+fn by_amount(direction: Direction) -> Sort {
+    Sort {
+        by: SortBy::Field("amount".into()),
+        kind: Kind::Number,
+        direction,
+    }
+}
+
+#[test]
+fn cursor_round_trips() {
+    let key = key(json!("2021-06-01T12:00:00Z"), "a");
+
+    let cursor = key.encode_cursor();
+
+    assert!(cursor
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_'));
+    assert_eq!(Key::decode_cursor(&cursor), Ok(key));
+    assert!(Key::decode_cursor("not a cursor").is_err());
+}
+
+#[test]
+fn ties_broken_by_id() {
+    let sort = by_amount(Direction::Asc);
+
+    assert_eq!(
+        sort.compare(&key(json!(5), "b"), &key(json!(15), "a")),
+        Ordering::Less
+    );
+    assert_eq!(
+        sort.compare(&key(json!(5), "b"), &key(json!(5), "a")),
+        Ordering::Greater
+    );
+
+    let sort = by_amount(Direction::Desc);
+
+    assert_eq!(
+        sort.compare(&key(json!(5), "b"), &key(json!(15), "a")),
+        Ordering::Greater
+    );
+    assert_eq!(
+        sort.compare(&key(json!(5), "b"), &key(json!(5), "a")),
+        Ordering::Less
+    );
+}
+
+#[test]
+fn missing_values_last() {
+    for &direction in &[Direction::Asc, Direction::Desc] {
+        let sort = by_amount(direction);
+
+        assert_eq!(
+            sort.compare(&key(json!(null), "a"), &key(json!(5), "b")),
+            Ordering::Greater
+        );
+    }
+}
+
+#[test]
+fn page_bounds_exclusive() {
+    let page = Page {
+        sort: by_amount(Direction::Asc),
+        after: Some(key(json!(5), "a")),
+        before: Some(key(json!(15), "a")),
+        ..Default::default()
+    };
+
+    assert!(!page.contains(&key(json!(5), "a")));
+    assert!(page.contains(&key(json!(5), "b")));
+    assert!(page.contains(&key(json!(10), "a")));
+    assert!(!page.contains(&key(json!(15), "a")));
+    assert!(!page.contains(&key(json!(null), "a")));
+}
+
+#[test]
+fn page_from_connection_arguments() {
+    let page = Page::new(Sort::default(), None, None, None, Some(10));
+    assert!(page.backward);
+    assert_eq!(page.limit, 10);
+
+    let page = Page::new(Sort::default(), None, None, None, None);
+    assert!(!page.backward);
+    assert_eq!(page.limit, 100);
+}
+
+#[test]
+fn pages_after_a_cursor_follow_on() {
+    let entries: Vec<usize> = (0..5).collect();
+    let mut seen = vec![];
+    let mut cursor: Option<String> = None;
+
+    loop {
+        let pagination = PaginationOption::after(cursor.as_deref(), 2).unwrap();
+        let page: Vec<_> = entries
+            .iter()
+            .enumerate()
+            .skip(pagination.skip)
+            .take(pagination.limit)
+            .map(|(i, _)| i)
+            .collect();
+
+        match page.last() {
+            Some(last) => cursor = Some(last.to_string()),
+            None => break,
+        }
+        seen.extend(page);
+    }
+
+    assert_eq!(seen, entries);
+    assert!(PaginationOption::after(Some("not a cursor"), 2).is_err());
+}
@@ -0,0 +1,201 @@
+use serde_json::json;
+
+use crate::search::{
+    similarity, Condition, Filter, FuzzyField, FuzzyQuery, Index, IntFilter, Kind, Operator,
+    StringFilter, TextQuery,
+};
+
+fn body(value: serde_json::Value) -> serde_json::Value {
+    json!({ "field": { "start": null, "end": value } })
+}
+
+#[test]
+fn filter_conditions() {
+    let filter = IntFilter {
+        gte: Some(5),
+        lt: Some(50),
+        ..Default::default()
+    };
+
+    assert_eq!(
+        filter.conditions("amount"),
+        vec![
+            Condition::new("amount", Operator::Gte, Kind::Number, 5),
+            Condition::new("amount", Operator::Lt, Kind::Number, 50),
+        ]
+    );
+}
+
+#[test]
+fn number_equality_is_exact() {
+    let condition = Condition::new("field", Operator::Eq, Kind::Number, 5);
+
+    assert!(condition.matches(&body(json!(5))));
+    assert!(!condition.matches(&body(json!(15))));
+    assert!(!condition.matches(&body(json!(50))));
+}
+
+#[test]
+fn number_ranges() {
+    let gt = Condition::new("field", Operator::Gt, Kind::Number, 5);
+    let lte = Condition::new("field", Operator::Lte, Kind::Number, 5);
+
+    assert!(gt.matches(&body(json!(15))));
+    assert!(!gt.matches(&body(json!(5))));
+    assert!(lte.matches(&body(json!(5))));
+    assert!(!lte.matches(&body(json!(15))));
+}
+
+#[test]
+fn in_any_value() {
+    let condition = Condition::new("field", Operator::In, Kind::Text, vec!["MA", "NH"]);
+
+    assert!(condition.matches(&body(json!("NH"))));
+    assert!(!condition.matches(&body(json!("VT"))));
+}
+
+#[test]
+fn datetime_compares_instants() {
+    let condition = Condition::new(
+        "field",
+        Operator::Lt,
+        Kind::DateTime,
+        "2021-06-01T12:00:00.5Z",
+    );
+
+    assert!(condition.matches(&body(json!("2021-06-01T12:00:00Z"))));
+    assert!(condition.matches(&body(json!("2021-06-01T13:00:00+02:00"))));
+    assert!(!condition.matches(&body(json!("2021-06-01T12:00:01Z"))));
+}
+
+#[test]
+fn string_operators() {
+    let filter = StringFilter {
+        prefix: Some("Bos".into()),
+        contains: Some("HARBOR".into()),
+        ..Default::default()
+    };
+
+    let matches = |value| {
+        filter
+            .conditions("field")
+            .iter()
+            .all(|condition| condition.matches(&body(json!(value))))
+    };
+
+    assert!(matches("Boston Harbor"));
+    assert!(!matches("boston harbor"));
+    assert!(!matches("Boston"));
+}
+
+#[test]
+fn missing_value_never_matches() {
+    let condition = Condition::new("field", Operator::Lt, Kind::Number, 5);
+
+    assert!(!condition.matches(&body(json!(null))));
+    assert!(!condition.matches(&json!({})));
+}
+
+#[test]
+fn any_element_of_a_list() {
+    let condition = Condition::new("field", Operator::Eq, Kind::Text, "Education").any();
+
+    assert!(condition.matches(&body(json!(["Religious", "Education"]))));
+    assert!(!condition.matches(&body(json!(["Politics"]))));
+    assert!(!condition.matches(&body(json!([]))));
+}
+
+#[test]
+fn nested_within_list() {
+    let condition = Condition::new("city", Operator::Eq, Kind::Text, "Boston").within("field");
+
+    assert_eq!(condition.path(), ("field", vec!["city"]));
+    assert!(condition.is_nested());
+    assert!(condition.matches(&body(json!([
+        { "city": "Cambridge" },
+        { "city": "Boston" }
+    ]))));
+    assert!(condition.matches(&body(json!({ "city": "Boston" }))));
+    assert!(!condition.matches(&body(json!([{ "city": "Cambridge" }]))));
+}
+
+#[test]
+fn text_query_matches_every_word() {
+    let query = TextQuery {
+        language: "english".into(),
+        query: "boston  FOOD".into(),
+    };
+
+    assert!(query.matches("Food bank of Boston"));
+    assert!(!query.matches("Boston library"));
+    assert_eq!(query.rank("Boston food, Boston families"), 3.0);
+}
+
+#[test]
+fn text_query_highlights_words() {
+    let query = TextQuery {
+        language: "english".into(),
+        query: "boston".into(),
+    };
+
+    assert_eq!(
+        query.highlight("Café in BOSTON, boston"),
+        "Café in <b>BOSTON</b>, <b>boston</b>"
+    );
+}
+
+#[test]
+fn similarity_of_trigrams() {
+    assert_eq!(similarity("Boston", "boston"), 1.0);
+    // 4 of the 9 trigrams of either are shared
+    assert!((similarity("Boston", "Bostn") - 4.0 / 9.0).abs() < 1e-9);
+    assert_eq!(similarity("Boston", "Paris"), 0.0);
+    assert_eq!(similarity("", ""), 0.0);
+}
+
+#[test]
+fn fuzzy_query_scores_the_closest_field() {
+    let query = FuzzyQuery {
+        query: "Bostn".into(),
+        fields: vec![
+            FuzzyField {
+                field: "field".into(),
+                threshold: 0.3,
+            },
+            FuzzyField {
+                field: "other".into(),
+                threshold: 0.3,
+            },
+        ],
+    };
+
+    let close = json!({
+        "field": { "start": null, "end": "Boston" },
+        "other": { "start": null, "end": "Paris" },
+    });
+    assert!((query.score(&close).unwrap() - 4.0 / 9.0).abs() < 1e-9);
+
+    assert_eq!(query.score(&body(json!("Paris"))), None);
+    assert_eq!(query.score(&body(json!(null))), None);
+
+    let strict = FuzzyQuery {
+        fields: vec![FuzzyField {
+            field: "field".into(),
+            threshold: 0.5,
+        }],
+        ..query
+    };
+    assert_eq!(strict.score(&close), None);
+}
+
+#[test]
+fn index_names() {
+    assert_eq!(
+        Index::value("Organization", "established", Kind::DateTime).name(),
+        "projection_organization_established"
+    );
+    assert_eq!(
+        Index::trigram("Organization", "name").name(),
+        "projection_organization_name_trgm"
+    );
+}
@@ -12,6 +12,8 @@ pub enum Action<'a> {
     Query,
     Mutate(&'a str),
     Create,
+    /// Irreversibly erase an entity or its personal data, which only the roles listed may do
+    Purge,
     All,
 }
 
@@ -87,19 +89,14 @@ impl Identity {
         let permitted = self.roles.iter().any(|role| match role {
             Role::Admin | Role::Service | Role::Organization => permissive_roles.contains(role),
             Role::User => match action {
-                Action::Mutate(id) => {
-                    if permissive_roles.contains(&Role::Own) {
-                        id == self.user_id
-                    } else {
-                        true
-                    }
-                }
+                Action::Mutate(id) if permissive_roles.contains(&Role::Own) => id == self.user_id,
+                Action::Purge => permissive_roles.contains(&Role::User),
                 _ => true,
             },
             Role::Own => false,
         });
 
-        permitted.then(|| ()).ok_or_else(|| {
+        permitted.then_some(()).ok_or_else(|| {
             async_graphql::Error::new("Your identity is not authorized to perform that action")
         })
     }
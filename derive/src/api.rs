@@ -5,6 +5,7 @@ mod mutate;
 mod output;
 mod query;
 mod store;
+mod subscribe;
 mod update;
 
 pub(crate) fn derive(input: DeriveData) -> TokenStream {
@@ -18,6 +19,8 @@ pub(crate) fn derive(input: DeriveData) -> TokenStream {
 
     let update = update::derive(&input);
 
+    let subscribe = subscribe::derive(&input);
+
     TokenStream::from(quote! {
         #store
 
@@ -28,5 +31,17 @@ pub(crate) fn derive(input: DeriveData) -> TokenStream {
         #mutate
 
         #update
+
+        #subscribe
     })
 }
+
+/// Turn the global `id` argument of a resolver into the uuid of an entity of the type,
+/// refusing the ids of other types
+fn decode_id(input: &DeriveData) -> proc_macro2::TokenStream {
+    let ty = input.ident.to_string();
+
+    quote! {
+        let id = atoms::node::GlobalId::of(&id, #ty)?;
+    }
+}
@@ -8,12 +8,18 @@ use heck::{CamelCase, SnakeCase};
 pub(crate) fn derive(input: &DeriveData) -> TokenStream2 {
     let base = &input.ident;
 
-    let ident = Ident::new(format!("{}Mutate", base.to_string()).as_str(), base.span());
+    let ident = Ident::new(format!("{}Mutate", base).as_str(), base.span());
 
     let new = derive_new(input);
 
     let update = derive_update(input);
 
+    let upsert = derive_upsert(input);
+
+    let lifecycle = derive_lifecycle(input);
+
+    let erase = derive_erase(input);
+
     quote! {
         #[derive(Default)]
         pub struct #ident;
@@ -23,6 +29,12 @@ pub(crate) fn derive(input: &DeriveData) -> TokenStream2 {
             #new
 
             #update
+
+            #upsert
+
+            #lifecycle
+
+            #erase
         }
     }
 }
@@ -34,7 +46,7 @@ fn derive_new(input: &DeriveData) -> TokenStream2 {
         base.span(),
     );
 
-    let store = Ident::new(format!("{}Store", base.to_string()).as_str(), base.span());
+    let store = Ident::new(format!("{}Store", base).as_str(), base.span());
 
     let params = input.fields.iter().map(|field| {
         let Field {
@@ -91,15 +103,32 @@ fn derive_new(input: &DeriveData) -> TokenStream2 {
 
     let mutate_permitted = input.auth_attribute().mutate;
 
+    let snapshot = input.snapshot_attribute().map(|snapshot| {
+        let every = snapshot.every;
+        quote! {
+            store::Driver::snapshot::<#store>(
+                &mut *transaction,
+                &new_identifier.value,
+                #every,
+                doc.into(),
+            )
+            .await?;
+        }
+    });
+
+    let ty = base.to_string();
+    let new_comment = format!("Create a {}, returning its global id", ty);
+
     quote! {
+        #[doc = #new_comment]
         #[allow(clippy::too_many_arguments)]
         async fn #func_name(
             &self,
             ctx: &Context<'_>,
             #(#params,)*
-        ) -> Result<atoms::Identifier> {
+        ) -> Result<async_graphql::ID> {
             let identity = ctx.data::<auth::Identity>()?;
-            let pool = ctx.data::<sqlx::PgPool>()?;
+            let db = ctx.data::<store::Database>()?;
             identity.is_authorized(auth::Action::Create, vec![#(#mutate_permitted),*])?;
 
             let mut identifier: Vec<_> = identifier
@@ -119,38 +148,51 @@ fn derive_new(input: &DeriveData) -> TokenStream2 {
                 #(#fields,)*
             };
 
-            let mut transaction = pool.begin().await?;
+            let projected: #store = doc.clone().into();
+
+            let mut transaction = db.begin().await?;
+
+            if let Some(duplicate) =
+                store::Driver::claim(&mut *transaction, &new_identifier.value, &projected).await?
+            {
+                return Err(async_graphql::ErrorExtensions::extend(&duplicate));
+            }
 
-            store::sql::Driver::delta::<#store>(
-                &mut transaction,
+            if let Some(dangling) =
+                store::Driver::check_references(&mut *transaction, &new_identifier.value, &projected).await?
+            {
+                return Err(async_graphql::ErrorExtensions::extend(&dangling));
+            }
+
+            store::Driver::delta::<#store>(
+                &mut *transaction,
                 &new_identifier.value,
                 doc.clone().into(),
                 identity,
             )
             .await?;
 
-            store::sql::Driver::project::<#store>(
-                &mut transaction,
-                &new_identifier.value,
-                doc.into(),
-            )
-            .await?;
+            store::Driver::project(&mut *transaction, &new_identifier.value, projected).await?;
+
+            #snapshot
 
             transaction.commit().await?;
 
-            Ok(new_identifier)
+            Ok(atoms::node::GlobalId::new(#ty, new_identifier.value).encode())
         }
     }
 }
 
 fn derive_update(input: &DeriveData) -> TokenStream2 {
+    let decode_id = decode_id(input);
+
     let base = &input.ident;
     let func_name = Ident::new(
         format!("update_{}", base.to_string().to_snake_case()).as_str(),
         base.span(),
     );
 
-    let store = Ident::new(format!("{}Store", base.to_string()).as_str(), base.span());
+    let store = Ident::new(format!("{}Store", base).as_str(), base.span());
 
     let params = input.fields.iter().map(|field| {
         let Field { ident, .. } = field;
@@ -176,6 +218,19 @@ fn derive_update(input: &DeriveData) -> TokenStream2 {
 
     let mutate_permitted = input.auth_attribute().mutate;
 
+    let snapshot = input.snapshot_attribute().map(|snapshot| {
+        let every = snapshot.every;
+        quote! {
+            store::Driver::snapshot::<#store>(
+                &mut *transaction,
+                id.as_str(),
+                #every,
+                current_doc.clone().into(),
+            )
+            .await?;
+        }
+    });
+
     quote! {
         #[allow(clippy::too_many_arguments)]
         async fn #func_name(
@@ -185,28 +240,191 @@ fn derive_update(input: &DeriveData) -> TokenStream2 {
             #(#params,)*
         ) -> Result<#base> {
             let identity = ctx.data::<auth::Identity>()?;
-            let pool = ctx.data::<sqlx::PgPool>()?;
+            let db = ctx.data::<store::Database>()?;
+            #decode_id
             identity.is_authorized(auth::Action::Mutate(&id), vec![#(#mutate_permitted),*])?;
 
             let delta = #store {
                 #(#fields,)*
+                lifecycle: None,
             };
 
-            let mut transaction = pool.begin().await?;
-
-            store::sql::Driver::delta(&mut transaction, id.as_str(), delta.clone(), identity).await?;
+            let mut transaction = db.begin().await?;
 
             let mut current_doc: #base =
-                store::sql::Driver::query_proj(&mut transaction, id.as_str()).await?;
+                store::Driver::query_proj(&mut *transaction, id.as_str()).await?;
 
-            current_doc.apply(delta);
+            current_doc
+                .try_apply(delta.clone())
+                .map_err(|conflict| async_graphql::ErrorExtensions::extend(&conflict))?;
 
-            store::sql::Driver::project::<#store>(
-                &mut transaction,
-                id.as_str(),
+            let projected: #store = current_doc.clone().into();
+
+            if let Some(duplicate) =
+                store::Driver::claim(&mut *transaction, id.as_str(), &projected).await?
+            {
+                return Err(async_graphql::ErrorExtensions::extend(&duplicate));
+            }
+
+            if let Some(dangling) =
+                store::Driver::check_references(&mut *transaction, id.as_str(), &delta).await?
+            {
+                return Err(async_graphql::ErrorExtensions::extend(&dangling));
+            }
+
+            store::Driver::delta(&mut *transaction, id.as_str(), delta, identity).await?;
+
+            store::Driver::project(&mut *transaction, id.as_str(), projected).await?;
+
+            #snapshot
+
+            transaction.commit().await?;
+
+            Ok(current_doc)
+        }
+    }
+}
+
+fn derive_upsert(input: &DeriveData) -> TokenStream2 {
+    let base = &input.ident;
+    let func_name = Ident::new(
+        format!("upsert_{}", base.to_string().to_snake_case()).as_str(),
+        base.span(),
+    );
+
+    let store = Ident::new(format!("{}Store", base).as_str(), base.span());
+
+    let delta_fields: Vec<_> = input
+        .fields
+        .iter()
+        .filter(|field| !field.is_identifier())
+        .collect();
+
+    let params = delta_fields.iter().map(|field| {
+        let Field { ident, .. } = field;
+        let delta = Ident::new(
+            format!(
+                "Delta{}{}",
+                base.to_string().to_camel_case(),
+                ident.to_string().to_camel_case()
+            )
+            .as_str(),
+            ident.span(),
+        );
+        quote! {
+            #ident: Option<#delta>
+        }
+    });
+
+    let fields = delta_fields.iter().map(|field| {
+        let name = &field.ident;
+
+        quote! { #name: #name.map(|del| del.into()) }
+    });
+
+    let mutate_permitted = input.auth_attribute().mutate;
+
+    let snapshot = input.snapshot_attribute().map(|snapshot| {
+        let every = snapshot.every;
+        quote! {
+            store::Driver::snapshot::<#store>(
+                &mut *transaction,
+                &id,
+                #every,
                 current_doc.clone().into(),
             )
             .await?;
+        }
+    });
+
+    let deleted = format!(
+        "The {} with this identifier is deleted, restore it first",
+        base
+    );
+    let not_found = format!("No {} has this Yoda identifier", base);
+
+    quote! {
+        #[allow(clippy::too_many_arguments)]
+        async fn #func_name(
+            &self,
+            ctx: &Context<'_>,
+            identifier: atoms::IdentifierInput,
+            #(#params,)*
+        ) -> Result<#base> {
+            let identity = ctx.data::<auth::Identity>()?;
+            let db = ctx.data::<store::Database>()?;
+
+            let identifier: atoms::Identifier = identifier.into();
+
+            let delta = #store {
+                identifier: None,
+                #(#fields,)*
+                lifecycle: None,
+            };
+
+            let mut transaction = db.begin().await?;
+
+            let existing =
+                store::Driver::identified::<#store>(&mut *transaction, &identifier).await?;
+
+            let (id, delta, current_doc) = match existing {
+                Some(id) => {
+                    identity.is_authorized(auth::Action::Mutate(&id), vec![#(#mutate_permitted),*])?;
+
+                    // Deleted entities keep their claims, but not their projection
+                    let mut current_doc: #base = store::Driver::query_proj(&mut *transaction, &id)
+                        .await
+                        .map_err(|e| match e {
+                            sqlx::Error::RowNotFound => async_graphql::Error::new(#deleted),
+                            e => async_graphql::Error::new(e.to_string()),
+                        })?;
+
+                    current_doc
+                        .try_apply(delta.clone())
+                        .map_err(|conflict| async_graphql::ErrorExtensions::extend(&conflict))?;
+
+                    (id, delta, current_doc)
+                }
+                None => {
+                    identity.is_authorized(auth::Action::Create, vec![#(#mutate_permitted),*])?;
+
+                    // Yoda identifiers are only issued on creation, so one found nowhere is wrong
+                    if identifier.system == atoms::IdentifierSystem::Yoda {
+                        return Err(async_graphql::Error::new(#not_found));
+                    }
+
+                    let new_identifier = atoms::Identifier::new(
+                        atoms::IdentifierSystem::Yoda,
+                        atoms::IdentifierTier::Primary,
+                    );
+
+                    let mut current_doc = #base::default();
+                    current_doc
+                        .try_apply(delta)
+                        .map_err(|conflict| async_graphql::ErrorExtensions::extend(&conflict))?;
+                    current_doc.identifier = vec![new_identifier.clone(), identifier];
+
+                    (new_identifier.value, current_doc.clone().into(), current_doc)
+                }
+            };
+
+            let projected: #store = current_doc.clone().into();
+
+            if let Some(duplicate) = store::Driver::claim(&mut *transaction, &id, &projected).await? {
+                return Err(async_graphql::ErrorExtensions::extend(&duplicate));
+            }
+
+            if let Some(dangling) =
+                store::Driver::check_references(&mut *transaction, &id, &delta).await?
+            {
+                return Err(async_graphql::ErrorExtensions::extend(&dangling));
+            }
+
+            store::Driver::delta(&mut *transaction, &id, delta, identity).await?;
+
+            store::Driver::project(&mut *transaction, &id, projected).await?;
+
+            #snapshot
 
             transaction.commit().await?;
 
@@ -214,3 +432,183 @@ fn derive_update(input: &DeriveData) -> TokenStream2 {
         }
     }
 }
+
+fn derive_lifecycle(input: &DeriveData) -> TokenStream2 {
+    let decode_id = decode_id(input);
+
+    let base = &input.ident;
+    let name = base.to_string().to_snake_case();
+    let func_name = |verb: &str| Ident::new(format!("{}_{}", verb, name).as_str(), base.span());
+    let (delete, restore, purge) = (
+        func_name("delete"),
+        func_name("restore"),
+        func_name("purge"),
+    );
+
+    let store = Ident::new(format!("{}Store", base).as_str(), base.span());
+
+    let mutate_permitted = input.auth_attribute().mutate;
+
+    let delete_comment = format!(
+        "Delete a {}, hiding it from finds and searches until restored",
+        base
+    );
+    let restore_comment = format!("Restore a deleted {}", base);
+    let purge_comment = format!(
+        "Erase a {} for good, redacting its history. Only admins may purge",
+        base
+    );
+    let not_found = format!("No {} has this id", base);
+    let not_deleted = format!("This {} is not deleted", base);
+    let purged = format!("This {} has been purged", base);
+
+    quote! {
+        #[doc = #delete_comment]
+        async fn #delete(&self, ctx: &Context<'_>, id: async_graphql::ID) -> Result<bool> {
+            let identity = ctx.data::<auth::Identity>()?;
+            let db = ctx.data::<store::Database>()?;
+            #decode_id
+            identity.is_authorized(auth::Action::Mutate(&id), vec![#(#mutate_permitted),*])?;
+
+            let mut transaction = db.begin().await?;
+
+            // Held before the projection, as restoring does
+            store::Driver::lock::<#store>(&mut *transaction, id.as_str()).await?;
+
+            // Locks the projection, which a deleted entity no longer has
+            store::Driver::query_proj_body::<#store>(&mut *transaction, id.as_str())
+                .await?
+                .ok_or_else(|| async_graphql::Error::new(#not_found))?;
+
+            store::Driver::tombstone::<#store>(
+                &mut *transaction,
+                id.as_str(),
+                atoms::delta::Lifecycle::Deleted,
+                identity,
+            )
+            .await?;
+
+            transaction.commit().await?;
+
+            Ok(true)
+        }
+
+        #[doc = #restore_comment]
+        async fn #restore(&self, ctx: &Context<'_>, id: async_graphql::ID) -> Result<#base> {
+            let identity = ctx.data::<auth::Identity>()?;
+            let db = ctx.data::<store::Database>()?;
+            #decode_id
+            identity.is_authorized(auth::Action::Mutate(&id), vec![#(#mutate_permitted),*])?;
+
+            let mut transaction = db.begin().await?;
+
+            // Locks the entity, so it can't be deleted or purged again until committed
+            let (doc, lifecycle) = store::Driver::rollup_with_lifecycle::<#base, #store>(
+                &mut *transaction,
+                id.as_str(),
+            )
+            .await?;
+
+            match lifecycle {
+                atoms::delta::Lifecycle::Deleted => {}
+                atoms::delta::Lifecycle::Active => return Err(async_graphql::Error::new(#not_deleted)),
+                atoms::delta::Lifecycle::Purged => return Err(async_graphql::Error::new(#purged)),
+            }
+
+            let projected: #store = doc.clone().into();
+
+            if let Some(duplicate) =
+                store::Driver::claim(&mut *transaction, id.as_str(), &projected).await?
+            {
+                return Err(async_graphql::ErrorExtensions::extend(&duplicate));
+            }
+
+            store::Driver::tombstone::<#store>(
+                &mut *transaction,
+                id.as_str(),
+                atoms::delta::Lifecycle::Active,
+                identity,
+            )
+            .await?;
+
+            store::Driver::project(&mut *transaction, id.as_str(), projected).await?;
+
+            transaction.commit().await?;
+
+            Ok(doc)
+        }
+
+        #[doc = #purge_comment]
+        async fn #purge(&self, ctx: &Context<'_>, id: async_graphql::ID) -> Result<bool> {
+            let identity = ctx.data::<auth::Identity>()?;
+            let db = ctx.data::<store::Database>()?;
+            #decode_id
+            identity.is_authorized(auth::Action::Purge, vec![auth::Role::Admin])?;
+
+            if store::Driver::ids::<#store>(&**db, Some(id.as_str())).await?.is_empty() {
+                return Err(async_graphql::Error::new(#not_found));
+            }
+
+            let mut transaction = db.begin().await?;
+
+            store::Driver::tombstone::<#store>(
+                &mut *transaction,
+                id.as_str(),
+                atoms::delta::Lifecycle::Purged,
+                identity,
+            )
+            .await?;
+
+            transaction.commit().await?;
+
+            Ok(true)
+        }
+    }
+}
+
+fn derive_erase(input: &DeriveData) -> TokenStream2 {
+    let fields = input.personal_data();
+
+    if fields.is_empty() {
+        return Default::default();
+    }
+
+    let decode_id = decode_id(input);
+
+    let base = &input.ident;
+    let func_name = Ident::new(
+        format!("erase_{}", base.to_string().to_snake_case()).as_str(),
+        base.span(),
+    );
+
+    let store = Ident::new(format!("{}Store", base).as_str(), base.span());
+
+    let erase_comment = format!(
+        "Erase the personal data of a {} from its whole history: {}. Only admins may erase",
+        base,
+        fields.join(", ")
+    );
+    let not_found = format!("No {} has this id", base);
+
+    quote! {
+        #[doc = #erase_comment]
+        async fn #func_name(&self, ctx: &Context<'_>, id: async_graphql::ID) -> Result<bool> {
+            let identity = ctx.data::<auth::Identity>()?;
+            let db = ctx.data::<store::Database>()?;
+            #decode_id
+            identity.is_authorized(auth::Action::Purge, vec![auth::Role::Admin])?;
+
+            if store::Driver::ids::<#store>(&**db, Some(id.as_str())).await?.is_empty() {
+                return Err(async_graphql::Error::new(#not_found));
+            }
+
+            let mut transaction = db.begin().await?;
+
+            store::Driver::erase::<#base, #store>(&mut *transaction, id.as_str(), identity).await?;
+
+            transaction.commit().await?;
+
+            Ok(true)
+        }
+    }
+}
@@ -8,16 +8,48 @@ use crate::DeriveData;
 pub(crate) fn derive(input: &DeriveData) -> TokenStream2 {
     let base = &input.ident;
 
-    let field_hash_structs = derive_field_hash_structs(&input);
+    let field_hash_structs = derive_field_hash_structs(input);
 
-    let resolvers = derive_resolvers(&input);
+    let resolvers = derive_resolvers(input);
+
+    let relations = derive_relations(input);
+
+    let referenced_by = derive_referenced_by(input);
+
+    let global_id = derive_global_id(input);
+
+    let query_permitted = input.auth_attribute().query;
+
+    let store = Ident::new(format!("{}Store", base).as_str(), base.span());
 
     quote! {
         #(#field_hash_structs)*
 
         #[async_graphql::Object]
         impl #base {
+            #global_id
+
             #(#resolvers)*
+
+            #(#relations)*
+
+            #referenced_by
+        }
+
+        impl #base {
+            /// Whether an identity may read this type, such as through a relation
+            pub(crate) fn authorize_query(identity: &auth::Identity) -> Result<()> {
+                identity.is_authorized(auth::Action::Query, vec![#(#query_permitted),*])
+            }
+
+            /// The entity with a uuid, if it is projected, read through the batching loader
+            pub(crate) async fn node(ctx: &Context<'_>, id: String) -> Result<Option<Self>> {
+                let identity = ctx.data::<auth::Identity>()?;
+                let loader = ctx.data::<store::ProjectionLoader>()?;
+                Self::authorize_query(identity)?;
+
+                Ok(store::Driver::load::<Self, #store>(loader, vec![id]).await?.pop())
+            }
         }
     }
 }
@@ -62,38 +94,136 @@ fn derive_resolvers(input: &DeriveData) -> Vec<TokenStream2> {
         .fields
         .iter()
         .map(|field| {
-            let Field { ident, ty, .. } = field;
+            let Field { ident, .. } = field;
 
             let output_ty = with_hash_ident(base, field);
 
-            let hashing = match ty.wrapper {
-                Wrapper::Vec | Wrapper::None => quote! {
-                    value.hash(&mut hasher);
-                },
-                Wrapper::Option => {
-                    quote! {
-                        if let Some(val) = value {
-                            val.hash(&mut hasher)
-                        }
-                    }
-                }
-            };
             quote! {
                 async fn #ident(&self, ctx: &Context<'_>,) -> Result<#output_ty> {
-                    use std::hash::{Hasher, Hash};
-
                     let value = &self.#ident;
 
-                    let mut hasher = std::collections::hash_map::DefaultHasher::new();
-                    #hashing
-                    let hash = hasher.finish().to_string();
-
                     Ok(#output_ty {
                         value: value.clone(),
-                        hash
+                        hash: atoms::hash::hash(value),
                     })
                 }
             }
         })
         .collect()
 }
+
+/// A resolver for each `#[relation]` field, loading the entities its references point to
+fn derive_relations(input: &DeriveData) -> Vec<TokenStream2> {
+    input
+        .fields
+        .iter()
+        .filter_map(|field| field.relation().map(|relation| (field, relation)))
+        .map(|(field, relation)| {
+            let Field { ident, ty, .. } = field;
+
+            if ty.wrapper != Wrapper::Vec || ty.ty_str() != "Reference" {
+                panic!(
+                    "`{}` can't be a relation, it must be a Vec<Reference>",
+                    ident
+                )
+            }
+
+            let func_name = Ident::new(format!("resolved_{}", ident).as_str(), ident.span());
+            let target = relation.ty();
+            let store = relation.store();
+
+            let comment = format!(
+                "The entities `{}` refers to, skipping any that no longer exist",
+                ident
+            );
+
+            quote! {
+                #[doc = #comment]
+                async fn #func_name(&self, ctx: &Context<'_>) -> Result<Vec<#target>> {
+                    let identity = ctx.data::<auth::Identity>()?;
+                    let loader = ctx.data::<store::ProjectionLoader>()?;
+                    #target::authorize_query(identity)?;
+
+                    let ids = store::Driver::referenced::<#store>(loader, &self.#ident).await?;
+
+                    Ok(store::Driver::load::<#target, #store>(loader, ids).await?)
+                }
+            }
+        })
+        .collect()
+}
+
+/// The `id` of the `Node` interface, encoding the type and the uuid of the entity's primary
+/// Yoda identifier, whatever other systems call primary
+fn derive_global_id(input: &DeriveData) -> TokenStream2 {
+    let ty = input.ident.to_string();
+
+    let identifier = &input
+        .fields
+        .iter()
+        .find(|field| field.is_identifier())
+        .expect("Every struct must have an identifier field")
+        .ident;
+
+    let comment = format!(
+        "The global id of this {}, which every query and mutation taking an id accepts",
+        ty
+    );
+
+    quote! {
+        #[doc = #comment]
+        pub(crate) async fn id(&self, ctx: &Context<'_>) -> Result<async_graphql::ID> {
+            let id = self
+                .#identifier
+                .iter()
+                .find(|id| id.is_primary() && id.system == atoms::IdentifierSystem::Yoda)
+                .map(|id| id.value.clone())
+                .unwrap_or_default();
+
+            Ok(atoms::node::GlobalId::new(#ty, id).encode())
+        }
+    }
+}
+
+/// A resolver listing the entities that hold a reference to this one, under any of its identifiers.
+///
+/// Only generated with `#[referrers]`, whose authorizer each referrer's type is checked with,
+/// leaving out those the identity may not read.
+fn derive_referenced_by(input: &DeriveData) -> TokenStream2 {
+    let authorize = match input.referrers_attribute() {
+        Some(referrers) => referrers.authorize(),
+        None => return Default::default(),
+    };
+
+    let identifier = &input
+        .fields
+        .iter()
+        .find(|field| field.is_identifier())
+        .expect("Every struct must have an identifier field")
+        .ident;
+
+    let comment = format!(
+        "The entities referring to this {}, through references of `referenceType` if given",
+        input.ident
+    );
+
+    quote! {
+        #[doc = #comment]
+        async fn referenced_by(
+            &self,
+            ctx: &Context<'_>,
+            reference_type: Option<atoms::ReferenceType>,
+        ) -> Result<Vec<atoms::Referrer>> {
+            let identity = ctx.data::<auth::Identity>()?;
+            let db = ctx.data::<store::Database>()?;
+
+            let referrers =
+                store::Driver::referrers(&**db, &self.#identifier, reference_type).await?;
+
+            Ok(referrers
+                .into_iter()
+                .filter(|referrer| #authorize(&referrer.ty, identity).is_ok())
+                .collect())
+        }
+    }
+}
@@ -3,25 +3,32 @@ use quote::quote;
 
 use super::*;
 use crate::DeriveData;
-use heck::SnakeCase;
+use heck::{CamelCase, SnakeCase};
 
 pub(crate) fn derive(input: &DeriveData) -> TokenStream2 {
     let base = &input.ident;
 
-    let ident = Ident::new(format!("{}Query", base.to_string()).as_str(), base.span());
+    let ident = Ident::new(format!("{}Query", base).as_str(), base.span());
 
     let find = derive_find(input);
 
-    let (search_struct, search) = if input
-        .fields
-        .iter()
-        .any(|field| field.attributes.contains(&Attribute::Search))
-    {
+    let find_by_identifier = derive_find_by_identifier(input);
+
+    let history = derive_history(input);
+
+    let (search_struct, search) = if has_search(input) {
         let search_struct = derive_search_struct(input);
 
+        let order_by = derive_order_by(input);
+
+        let connection_fields = derive_connection_fields(input);
+
         let search = derive_search_func(input);
 
-        (search_struct, search)
+        (
+            quote! { #search_struct #order_by #connection_fields },
+            search,
+        )
     } else {
         Default::default()
     };
@@ -37,38 +44,486 @@ pub(crate) fn derive(input: &DeriveData) -> TokenStream2 {
         impl #ident {
             #find
 
+            #find_by_identifier
+
+            #history
+
             #search
         }
     }
 }
 
-fn search_struct_ident(input: &DeriveData) -> Ident {
+/// Whether any field can be searched or sorted by, giving the type a search
+pub(super) fn has_search(input: &DeriveData) -> bool {
+    input.fields.iter().any(|field| {
+        field.is_searchable()
+            || field.is_text_searchable()
+            || field.fuzzy_threshold().is_some()
+            || field.attributes.contains(&Attribute::Sort)
+    })
+}
+
+/// Whether any field is `#[searchable(text)]`, giving the search a `query`
+pub(super) fn has_text(input: &DeriveData) -> bool {
+    input.fields.iter().any(|field| field.is_text_searchable())
+}
+
+/// Fields marked `#[searchable(fuzzy)]` with their thresholds, giving the search a `fuzzy` value
+fn fuzzy_fields(input: &DeriveData) -> Vec<(&Field, f64)> {
+    input
+        .fields
+        .iter()
+        .filter_map(|field| {
+            let threshold = field.fuzzy_threshold()?;
+            if field.ty.ty_str() != "String" || field.ty.wrapper == Wrapper::Vec {
+                panic!(
+                    "`{}` can't be searchable(fuzzy), it must hold a single text",
+                    field.ident
+                )
+            }
+            Some((field, threshold))
+        })
+        .collect()
+}
+
+pub(super) fn has_fuzzy(input: &DeriveData) -> bool {
+    !fuzzy_fields(input).is_empty()
+}
+
+pub(super) fn search_struct_ident(input: &DeriveData) -> Ident {
+    let base = &input.ident;
+    Ident::new(format!("{}Search", base).as_str(), base.span())
+}
+
+/// The search input for a field: the scalar filters, the filter derived for a `#[construct]`
+/// struct, or one derived here for anything else, such as enums
+pub(super) fn search_filter(input: &DeriveData, field: &Field) -> TokenStream2 {
+    let Field {
+        ident,
+        ty,
+        attributes,
+        ..
+    } = field;
+
+    if let Some(filter) = ty.filter() {
+        return filter;
+    }
+
+    let filter = if attributes.contains(&Attribute::Struct) {
+        Ident::new(
+            format!("{}Filter", ty.ty_str()).to_camel_case().as_str(),
+            ident.span(),
+        )
+    } else {
+        value_filter_ident(input, field)
+    };
+
+    quote! { #filter }
+}
+
+fn value_filter_ident(input: &DeriveData, field: &Field) -> Ident {
+    Ident::new(
+        format!(
+            "{}{}Filter",
+            input.ident,
+            field.ident.to_string().to_camel_case()
+        )
+        .as_str(),
+        field.ident.span(),
+    )
+}
+
+/// Equality filters for searchable fields that are neither scalars nor structs
+fn derive_value_filters(input: &DeriveData) -> TokenStream2 {
+    let filters = input
+        .fields
+        .iter()
+        .filter(|field| field.is_searchable())
+        .filter(|field| {
+            field.ty.filter().is_none() && !field.attributes.contains(&Attribute::Struct)
+        })
+        .map(|field| {
+            let ident = value_filter_ident(input, field);
+            let ty = &field.ty.ty;
+
+            quote! {
+                #[derive(async_graphql::InputObject, Default, Clone, Debug)]
+                pub struct #ident {
+                    eq: Option<#ty>,
+                    #[graphql(name = "in")]
+                    in_: Option<Vec<#ty>>,
+                }
+
+                impl atoms::search::Filter for #ident {
+                    fn conditions(&self, field: &str) -> Vec<atoms::search::Condition> {
+                        use atoms::search::{Condition, Kind, Operator};
+
+                        let mut res = vec![];
+
+                        if let Some(eq) = self.eq.as_ref() {
+                            res.push(Condition::new(field, Operator::Eq, Kind::Text, eq))
+                        }
+                        if let Some(in_) = self.in_.as_ref() {
+                            res.push(Condition::new(field, Operator::In, Kind::Text, in_))
+                        }
+
+                        res
+                    }
+                }
+            }
+        });
+
+    quote! {
+        #(#filters)*
+    }
+}
+
+fn connection_fields_ident(input: &DeriveData) -> Ident {
+    let base = &input.ident;
+    Ident::new(format!("{}SearchFields", base).as_str(), base.span())
+}
+
+fn edge_fields_ident(input: &DeriveData) -> Ident {
     let base = &input.ident;
-    Ident::new(format!("{}Search", base.to_string()).as_str(), base.span())
+    Ident::new(format!("{}SearchEdgeFields", base).as_str(), base.span())
+}
+
+/// Fields of the search connection, and of its edges when searching text or fuzzy values,
+/// beyond the edges, nodes and page info
+fn derive_connection_fields(input: &DeriveData) -> TokenStream2 {
+    let search = search_struct_ident(input);
+    let fields = connection_fields_ident(input);
+
+    let edge_fields = if has_text(input) || has_fuzzy(input) {
+        let edge_fields = edge_fields_ident(input);
+
+        let snippet = if has_text(input) {
+            quote! {
+                /// The text searched, with the words matching `query` in `<b>` tags
+                snippet: Option<String>,
+            }
+        } else {
+            Default::default()
+        };
+
+        let score = if has_fuzzy(input) {
+            quote! {
+                /// How similar the closest fuzzy field is to `fuzzy`, from 0 to 1
+                score: Option<f64>,
+            }
+        } else {
+            Default::default()
+        };
+
+        quote! {
+            #[derive(async_graphql::SimpleObject)]
+            pub struct #edge_fields {
+                #snippet
+                #score
+            }
+        }
+    } else {
+        Default::default()
+    };
+
+    quote! {
+        #edge_fields
+
+        pub struct #fields {
+            search: #search,
+        }
+
+        #[Object]
+        impl #fields {
+            /// How many results match the search across every page
+            async fn total_count(&self, ctx: &Context<'_>) -> Result<usize> {
+                let db = ctx.data::<store::Database>()?;
+
+                Ok(store::Driver::count(&**db, &self.search).await?)
+            }
+        }
+    }
+}
+
+fn order_by_ident(input: &DeriveData) -> Ident {
+    let base = &input.ident;
+    Ident::new(format!("{}OrderBy", base).as_str(), base.span())
+}
+
+/// Fields search results can be ordered by: those marked `#[sortable]`, and any
+/// `#[searchable]` field holding a single scalar
+fn sort_fields(input: &DeriveData) -> Vec<&Field> {
+    input
+        .fields
+        .iter()
+        .filter(|field| {
+            let scalar = field.ty.kind().is_some() && field.ty.wrapper != Wrapper::Vec;
+
+            if field.attributes.contains(&Attribute::Sort) {
+                if !scalar {
+                    panic!(
+                        "`{}` can't be sortable, it must hold a single scalar",
+                        field.ident
+                    )
+                }
+                return true;
+            }
+
+            scalar && field.is_searchable()
+        })
+        .collect()
+}
+
+/// The `orderBy` input of the search, choosing a field and direction
+fn derive_order_by(input: &DeriveData) -> TokenStream2 {
+    let base = &input.ident;
+
+    let order_by = order_by_ident(input);
+    let order_field = Ident::new(format!("{}OrderField", base).as_str(), base.span());
+
+    let fields = sort_fields(input);
+
+    let variants: Vec<_> = fields
+        .iter()
+        .map(|field| {
+            Ident::new(
+                field.ident.to_string().to_camel_case().as_str(),
+                field.ident.span(),
+            )
+        })
+        .collect();
+
+    let sorts = fields.iter().zip(variants.iter()).map(|(field, variant)| {
+        let name = field.ident.to_string();
+        let kind = field.ty.kind();
+
+        quote! {
+            #order_field::#variant => (atoms::pagination::SortBy::Field(#name.into()), #kind)
+        }
+    });
+
+    let (relevance, relevance_sort) = if has_text(input) {
+        (
+            quote! {
+                /// How well it matches `query`, missing without one
+                Relevance,
+            },
+            quote! {
+                #order_field::Relevance => {
+                    (atoms::pagination::SortBy::Relevance, atoms::search::Kind::Number)
+                }
+            },
+        )
+    } else {
+        Default::default()
+    };
+
+    let (similarity, similarity_sort) = if has_fuzzy(input) {
+        (
+            quote! {
+                /// How close it is to `fuzzy`, missing without one
+                Similarity,
+            },
+            quote! {
+                #order_field::Similarity => {
+                    (atoms::pagination::SortBy::Similarity, atoms::search::Kind::Number)
+                }
+            },
+        )
+    } else {
+        Default::default()
+    };
+
+    let order_field_comment = format!("What {} search results can be ordered by", base);
+
+    quote! {
+        #[doc = #order_field_comment]
+        #[derive(async_graphql::Enum, Copy, Clone, PartialEq, Eq, Debug)]
+        pub enum #order_field {
+            /// When it was created
+            LastUpdated,
+            #relevance
+            #similarity
+            #(#variants,)*
+        }
+
+        #[derive(async_graphql::InputObject, Clone, Debug)]
+        pub struct #order_by {
+            field: #order_field,
+            #[graphql(default)]
+            direction: atoms::pagination::Direction,
+        }
+
+        impl From<#order_by> for atoms::pagination::Sort {
+            fn from(order_by: #order_by) -> Self {
+                let (by, kind) = match order_by.field {
+                    #order_field::LastUpdated => {
+                        (atoms::pagination::SortBy::LastUpdated, atoms::search::Kind::DateTime)
+                    }
+                    #relevance_sort
+                    #similarity_sort
+                    #(#sorts,)*
+                };
+
+                Self {
+                    by,
+                    kind,
+                    direction: order_by.direction,
+                }
+            }
+        }
+    }
 }
 
 fn derive_find(input: &DeriveData) -> TokenStream2 {
+    let decode_id = decode_id(input);
+
     let base = &input.ident;
     let func_name = Ident::new(
         format!("find_{}", base.to_string().to_snake_case()).as_str(),
         base.span(),
     );
 
-    let store = Ident::new(format!("{}Store", base.to_string()).as_str(), base.span());
+    let store = Ident::new(format!("{}Store", base).as_str(), base.span());
 
     let query_permitted = input.auth_attribute().query;
 
+    let find_comment = format!("Find {} by its global id", base);
+    let not_found = format!("No {} has this id", base);
+
     quote! {
-        async fn #func_name(&self, ctx: &Context<'_>, id: String) -> Result<#base> {
+        #[doc = #find_comment]
+        ///
+        /// `asOf` and `asOfVersion` replay the delta log to reconstruct the entity
+        /// as it was at that time, or after that many deltas.
+        async fn #func_name(
+            &self,
+            ctx: &Context<'_>,
+            id: async_graphql::ID,
+            as_of: Option<sqlx::types::chrono::DateTime<sqlx::types::chrono::Utc>>,
+            as_of_version: Option<usize>,
+        ) -> Result<#base> {
             let identity = ctx.data::<auth::Identity>()?;
-            let pool = ctx.data::<sqlx::PgPool>()?;
+            let db = ctx.data::<store::Database>()?;
+            #decode_id
             identity.is_authorized(auth::Action::Query, vec![#(#query_permitted),*])?;
 
-            let dels = store::sql::Driver::query::<#base, #store>(pool, &id)
+            // Deleted and purged entities can't be found
+            store::Driver::rollup::<#base, #store>(&**db, &id, as_of, as_of_version)
                 .await
-                .map_err(|e| async_graphql::Error::new(e.to_string()))?;
+                .map_err(|e| match e {
+                    sqlx::Error::RowNotFound => async_graphql::Error::new(#not_found),
+                    e => async_graphql::Error::new(e.to_string()),
+                })
+        }
+    }
+}
+
+fn derive_find_by_identifier(input: &DeriveData) -> TokenStream2 {
+    let base = &input.ident;
+    let func_name = Ident::new(
+        format!("find_{}_by_identifier", base.to_string().to_snake_case()).as_str(),
+        base.span(),
+    );
+
+    let store = Ident::new(format!("{}Store", base).as_str(), base.span());
+
+    let query_permitted = input.auth_attribute().query;
+
+    let find_comment = format!(
+        "Find {} by an identifier from any system, such as a Stripe id",
+        base
+    );
+    let not_found = format!("No {} has this identifier", base);
+
+    quote! {
+        #[doc = #find_comment]
+        async fn #func_name(
+            &self,
+            ctx: &Context<'_>,
+            system: atoms::IdentifierSystem,
+            value: String,
+        ) -> Result<#base> {
+            let identity = ctx.data::<auth::Identity>()?;
+            let db = ctx.data::<store::Database>()?;
+            identity.is_authorized(auth::Action::Query, vec![#(#query_permitted),*])?;
+
+            let id = store::Driver::find_by_identifier::<#store>(&**db, system, &value)
+                .await?
+                .ok_or_else(|| async_graphql::Error::new(#not_found))?;
 
-            Ok(dels.into_iter().rollup())
+            store::Driver::rollup::<#base, #store>(&**db, &id, None, None)
+                .await
+                .map_err(|e| match e {
+                    sqlx::Error::RowNotFound => async_graphql::Error::new(#not_found),
+                    e => async_graphql::Error::new(e.to_string()),
+                })
+        }
+    }
+}
+
+fn derive_history(input: &DeriveData) -> TokenStream2 {
+    let decode_id = decode_id(input);
+
+    let base = &input.ident;
+    let func_name = Ident::new(
+        format!("{}_history", base.to_string().to_snake_case()).as_str(),
+        base.span(),
+    );
+
+    let store = Ident::new(format!("{}Store", base).as_str(), base.span());
+
+    let query_permitted = input.auth_attribute().query;
+
+    let history_comment = format!("Changes made to a {}, oldest first", base);
+
+    quote! {
+        #[doc = #history_comment]
+        ///
+        /// Pass the `endCursor` of a page as `cursor` for the changes after it.
+        ///
+        /// ### Defaults
+        /// Cursor: none, starting from the first change
+        ///
+        /// Limit: 100
+        async fn #func_name(
+            &self,
+            ctx: &Context<'_>,
+            id: async_graphql::ID,
+            cursor: Option<String>,
+            limit: Option<usize>,
+        ) -> Result<Connection<usize, atoms::history::HistoryEntry>> {
+            let identity = ctx.data::<auth::Identity>()?;
+            let db = ctx.data::<store::Database>()?;
+            #decode_id
+            identity.is_authorized(auth::Action::Query, vec![#(#query_permitted),*])?;
+
+            let pagination = PaginationOption::after(cursor.as_deref(), limit.unwrap_or(100))?;
+
+            // Every earlier delta is needed to know the values before each change
+            let records = store::Driver::history::<#base, #store>(
+                &**db,
+                &id,
+                pagination.skip + pagination.limit + 1,
+            )
+            .await?;
+
+            let has_next = records.len() > pagination.skip + pagination.limit;
+
+            let edges: Vec<Edge<usize, atoms::history::HistoryEntry, EmptyFields>> =
+                atoms::history::entries::<#base, _>(records)
+                    .into_iter()
+                    .enumerate()
+                    .skip(pagination.skip)
+                    .take(pagination.limit)
+                    .map(|(i, entry)| Edge::new(i, entry))
+                    .collect();
+
+            let mut connection = Connection::new(pagination.skip > 0, has_next);
+
+            connection.append(edges);
+
+            Ok(connection)
         }
     }
 }
@@ -87,66 +542,155 @@ fn derive_search_func(input: &DeriveData) -> TokenStream2 {
     let search_params = input
         .fields
         .iter()
-        .filter(|field| field.attributes.contains(&Attribute::Search))
+        .filter(|field| field.is_searchable())
         .map(|field| {
-            let Field { ident, ty, .. } = field;
-            let ty = &ty.ty;
+            let ident = &field.ident;
+            let filter = search_filter(input, field);
             quote! {
-                #ident: Option<#ty>
+                #ident: Option<#filter>
             }
         });
 
-    let search_idents = input
+    let mut search_idents: Vec<_> = input
         .fields
         .iter()
-        .filter(|field| field.attributes.contains(&Attribute::Search))
-        .map(|field| &field.ident);
+        .filter(|field| field.is_searchable())
+        .map(|field| {
+            let ident = &field.ident;
+            quote! { #ident }
+        })
+        .collect();
+
+    let order_by = order_by_ident(input);
+    let fields = connection_fields_ident(input);
+
+    // A text query adds its argument, orders by relevance unless told otherwise,
+    // and gives each edge a snippet of the matching text
+    let mut default_sort = quote! { Default::default() };
+
+    let (query_param, snippet) = if has_text(input) {
+        search_idents.push(quote! { query });
+
+        default_sort = quote! {
+            if doc.query.is_some() {
+                atoms::pagination::Sort::relevance()
+            } else {
+                #default_sort
+            }
+        };
+
+        (
+            quote! { query: Option<String>, },
+            quote! { snippet: found.snippet, },
+        )
+    } else {
+        Default::default()
+    };
+
+    // As does a fuzzy value, ordering by similarity first and scoring each edge
+    let (fuzzy_param, score) = if has_fuzzy(input) {
+        search_idents.push(quote! { fuzzy });
+
+        default_sort = quote! {
+            if doc.fuzzy.is_some() {
+                atoms::pagination::Sort::similarity()
+            } else {
+                #default_sort
+            }
+        };
+
+        (
+            quote! { fuzzy: Option<String>, },
+            quote! { score: found.score, },
+        )
+    } else {
+        Default::default()
+    };
+
+    let (edge_fields, edge) = if has_text(input) || has_fuzzy(input) {
+        let edge_fields = edge_fields_ident(input);
+
+        (
+            quote! { #edge_fields },
+            quote! {
+                Edge::with_additional_fields(
+                    found.key,
+                    found.body.into(),
+                    #edge_fields {
+                        #snippet
+                        #score
+                    },
+                )
+            },
+        )
+    } else {
+        (
+            quote! { EmptyFields },
+            quote! { Edge::new(found.key, found.body.into()) },
+        )
+    };
 
-    let search_for_comment = format!("Search for {}", base.to_string());
+    let search_for_comment = format!("Search for {}", base);
 
     quote! {
         #[doc = #search_for_comment]
-        /// ### Defaults
-        /// Cursor: 0
         ///
-        /// Limit: 100
+        /// Results are ordered by `orderBy`: oldest first by default, most relevant first
+        /// given a text `query`, or closest first given a `fuzzy` value. They are paged with
+        /// the cursors of the results: `first` after `after`, or `last` before `before`.
+        ///
+        /// ### Defaults
+        /// First: 100
         #[allow(clippy::too_many_arguments)]
         async fn #func_name(
             &self,
             ctx: &Context<'_>,
             #(#search_params,)*
-            cursor: Option<String>,
-            limit: Option<usize>,
-        ) -> Result<Connection<usize, #base>> {
+            #query_param
+            #fuzzy_param
+            order_by: Option<#order_by>,
+            after: Option<String>,
+            before: Option<String>,
+            first: Option<i32>,
+            last: Option<i32>,
+        ) -> Result<Connection<atoms::pagination::Key, #base, #fields, #edge_fields>> {
             let identity = ctx.data::<auth::Identity>()?;
-            let pool = ctx.data::<sqlx::PgPool>()?;
+            let db = ctx.data::<store::Database>()?;
             identity.is_authorized(auth::Action::All, vec![#(#query_permitted),*])?;
 
             let doc = #search { #(#search_idents,)* };
 
-            let pagination = PaginationOption {
-                skip: cursor.unwrap_or("0".into()).parse()?,
-                limit: limit.unwrap_or(100),
-            };
+            let sort = order_by.map(Into::into).unwrap_or_else(|| #default_sort);
 
-            let edges: Vec<Edge<usize, #base, EmptyFields>> =
-                store::sql::Driver::search(pool, doc, pagination)
-                    .await?
-                    .into_iter()
-                    .map(|org| org.into())
-                    .enumerate()
-                    .map(|(i, org)| Edge::new(i + pagination.skip, org))
-                    .collect();
+            async_graphql::connection::query(
+                after,
+                before,
+                first,
+                last,
+                |after, before, first, last| async move {
+                    let page = atoms::pagination::Page::new(sort, after, before, first, last);
 
-            let end_cursor = pagination.skip + edges.len();
+                    let fields = #fields { search: doc.clone() };
 
-            let PaginationOption { skip, limit } = pagination;
+                    let (found, more) = store::Driver::search(&**db, doc, &page).await?;
 
-            let mut connection = Connection::new(skip > 0, true);
+                    let edges: Vec<Edge<atoms::pagination::Key, #base, #edge_fields>> = found
+                        .into_iter()
+                        .map(|found| #edge)
+                        .collect();
 
-            connection.append(edges);
+                    let mut connection = if page.backward {
+                        Connection::with_additional_fields(more, page.before.is_some(), fields)
+                    } else {
+                        Connection::with_additional_fields(page.after.is_some(), more, fields)
+                    };
 
-            Ok(connection)
+                    connection.append(edges);
+
+                    Ok::<_, async_graphql::Error>(connection)
+                },
+            )
+            .await
         }
     }
 }
@@ -155,95 +699,117 @@ fn derive_search_struct(input: &DeriveData) -> TokenStream2 {
     let base = &input.ident;
 
     let search = search_struct_ident(input);
-    let store = Ident::new(format!("{}Store", base.to_string()).as_str(), base.span());
+    let store = Ident::new(format!("{}Store", base).as_str(), base.span());
 
     let base_str = base.to_string();
 
     let search_fields = input
         .fields
         .iter()
-        .filter(|field| field.attributes.contains(&&Attribute::Search))
+        .filter(|field| field.is_searchable())
         .map(|field| {
-            let Field { ident, ty, .. } = field;
-            let ty = &ty.ty;
+            let ident = &field.ident;
+            let filter = search_filter(input, field);
             quote! {
-                #ident: Option<#ty>
+                #ident: Option<#filter>
             }
         });
 
-    let splits = input
+    let conditions = input
         .fields
         .iter()
-        .filter(|field| field.attributes.contains(&&Attribute::Search))
-        .filter_map(|field| {
-            let Field { ty, .. } = field;
-
-            match ty.wrapper {
-                Wrapper::Option | Wrapper::None => None,
-                Wrapper::Vec => Some(""),
-            }
-        });
-
-    let paths = input
-        .fields
-        .iter()
-        .filter(|field| field.attributes.contains(&&Attribute::Search))
+        .filter(|field| field.is_searchable())
         .map(|field| {
             let Field { ident, ty, .. } = field;
-
-            let path = match ty.wrapper {
-                Wrapper::Option | Wrapper::None => {
-                    format!("body -> '{}' ->> 'end' LIKE $", ident.to_string())
-                }
-                Wrapper::Vec => format!("{}_array ->> 'end' LIKE $", ident.to_string()),
+            let name = ident.to_string();
+
+            let conditions = match ty.wrapper {
+                Wrapper::Vec => quote! {
+                    atoms::search::Filter::conditions(#ident, #name)
+                        .into_iter()
+                        .map(atoms::search::Condition::any)
+                },
+                Wrapper::Option | Wrapper::None => quote! {
+                    atoms::search::Filter::conditions(#ident, #name)
+                },
             };
 
             quote! {
-                if self.#ident.is_some() {
-                    res.push(#path.into())
+                if let Some(#ident) = self.#ident.as_ref() {
+                    res.extend(#conditions)
                 }
             }
         });
 
-    let bindings = input
-        .fields
-        .iter()
-        .filter(|field| field.attributes.contains(&&Attribute::Search))
-        .map(|field| {
-            let Field { ident, .. } = field;
+    let value_filters = derive_value_filters(input);
 
+    let (query_field, text) = if has_text(input) {
+        let language = input.search_language();
+
+        (
+            quote! { query: Option<String>, },
             quote! {
-                let query = if let Some(#ident) = self.#ident.as_ref() {
-                    query.bind(format!("%{}%", #ident))
-                } else { query };
+                fn text(&self) -> Option<atoms::search::TextQuery> {
+                    self.query.clone().map(|query| atoms::search::TextQuery {
+                        language: #language.into(),
+                        query,
+                    })
+                }
+            },
+        )
+    } else {
+        Default::default()
+    };
+
+    let (fuzzy_field, fuzzy) = if has_fuzzy(input) {
+        let fields = fuzzy_fields(input).into_iter().map(|(field, threshold)| {
+            let name = field.ident.to_string();
+            quote! {
+                atoms::search::FuzzyField {
+                    field: #name.into(),
+                    threshold: #threshold,
+                }
             }
         });
 
+        (
+            quote! { fuzzy: Option<String>, },
+            quote! {
+                fn fuzzy(&self) -> Option<atoms::search::FuzzyQuery> {
+                    self.fuzzy.clone().map(|query| atoms::search::FuzzyQuery {
+                        query,
+                        fields: vec![#(#fields),*],
+                    })
+                }
+            },
+        )
+    } else {
+        Default::default()
+    };
+
     quote! {
+        #value_filters
+
+        #[derive(Clone)]
         pub struct #search {
             #(#search_fields,)*
+            #query_field
+            #fuzzy_field
         }
 
         impl Search<#store> for #search {
             fn ty() -> String {
                 #base_str.into()
             }
-            fn array_splits(&self) -> Vec<String> {
-                vec![#(#splits,)*]
-            }
-            fn paths(&self) -> Vec<String> {
+            fn conditions(&self) -> Vec<Condition> {
                 let mut res = vec![];
 
-                #(#paths)*
+                #(#conditions)*
 
                 res
             }
-            fn bind_args<'a>(&self, query: SqlQuery<'a>) -> SqlQuery<'a> {
-
-                #(#bindings)*
-
-                query
-            }
+            #text
+            #fuzzy
         }
     }
 }
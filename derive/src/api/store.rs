@@ -5,13 +5,13 @@ use super::*;
 use crate::DeriveData;
 
 pub(crate) fn derive(input: &DeriveData) -> TokenStream2 {
-    let store_struct = derive_struct(&input);
+    let store_struct = derive_struct(input);
 
-    let impl_store = derive_store_trait(&input);
+    let impl_store = derive_store_trait(input);
 
-    let impl_del = derive_del_trait(&input);
+    let impl_del = derive_del_trait(input);
 
-    let translations = derive_translations(&input);
+    let translations = derive_translations(input);
 
     quote! {
         #store_struct
@@ -27,7 +27,7 @@ pub(crate) fn derive(input: &DeriveData) -> TokenStream2 {
 fn derive_struct(input: &DeriveData) -> TokenStream2 {
     let DeriveData { ident, fields, .. } = input;
 
-    let ident = Ident::new(format!("{}Store", ident.to_string()).as_str(), ident.span());
+    let ident = Ident::new(format!("{}Store", ident).as_str(), ident.span());
 
     let fields = fields.iter().map(|field| {
         let Field { ident, ty, .. } = field;
@@ -45,6 +45,8 @@ fn derive_struct(input: &DeriveData) -> TokenStream2 {
         #[derive(serde::Serialize, serde::Deserialize, Clone, Debug)]
         pub struct #ident {
             #(#fields,)*
+            #[serde(default, skip_serializing_if = "Option::is_none")]
+            lifecycle: Option<Delta<atoms::delta::Lifecycle>>,
         }
     }
 }
@@ -56,26 +58,68 @@ fn derive_store_trait(input: &DeriveData) -> TokenStream2 {
 
     let ident = Ident::new(format!("{}Store", ident_str).as_str(), ident.span());
 
-    let identifier = match fields
+    let (identifier, identifiers) = match fields
         .iter()
         .find(|field| field.is_identifier())
         .expect("Every struct must have an identifier field")
         .ty
         .wrapper
     {
-        Wrapper::Vec => {
+        Wrapper::Vec => (
             quote! {
                 self.identifier
                     .as_ref()
                     .and_then(|delta| delta.end.as_ref())
-                    .and_then(|vec| vec.into_iter().find(|id| id.is_primary()))
-                    .map(|id| id.to_string())
+                    .and_then(|vec| {
+                        vec.iter().find(|id| {
+                            id.is_primary() && id.system == atoms::IdentifierSystem::Yoda
+                        })
+                    })
+                    .map(|id| id.value.clone())
                     .unwrap_or_default()
-            }
-        }
+            },
+            quote! {
+                self.identifier
+                    .as_ref()
+                    .and_then(|delta| delta.end.as_ref())
+                    .into_iter()
+                    .flatten()
+                    .map(atoms::delta::Claim::identifier)
+            },
+        ),
         _ => unimplemented!("Identifier must be an array"),
     };
 
+    let unique = fields
+        .iter()
+        .filter(|field| field.attributes.contains(&Attribute::Unique))
+        .map(|field| {
+            let Field { ident, ty, .. } = field;
+            let name = ident.to_string();
+
+            match ty.wrapper {
+                Wrapper::Option => quote! {
+                    self.#ident
+                        .as_ref()
+                        .and_then(|delta| delta.end.as_ref())
+                        .map(|value| atoms::delta::Claim::field(#name, value))
+                },
+                _ => panic!("`{}` can't be unique, it must be an Option", ident),
+            }
+        });
+
+    let untouched = fields.iter().map(|field| &field.ident);
+
+    let document = derive_document(input);
+
+    let indexes = derive_indexes(input);
+
+    let personal_data = derive_personal_data(input);
+
+    let relations = derive_relations(input);
+
+    let references = derive_references(input);
+
     quote! {
         impl Store for #ident {
             fn ty() -> String {
@@ -84,6 +128,217 @@ fn derive_store_trait(input: &DeriveData) -> TokenStream2 {
             fn identifier(&self) -> String {
                 #identifier
             }
+            fn claims(&self) -> Vec<atoms::delta::Claim> {
+                #identifiers
+                    #(.chain(#unique))*
+                    .collect()
+            }
+            fn lifecycle(&self) -> Option<atoms::delta::Lifecycle> {
+                self.lifecycle.as_ref().and_then(|delta| delta.end)
+            }
+            fn tombstone(lifecycle: atoms::delta::Lifecycle) -> Self {
+                Self {
+                    #(#untouched: Default::default(),)*
+                    lifecycle: Delta::init(Some(lifecycle)),
+                }
+            }
+            #document
+            #indexes
+            #personal_data
+            #relations
+            #references
+        }
+    }
+}
+
+/// An index on the value of each field compared or sorted on directly, and a trigram index
+/// on each text field matched by substring or fuzzy search.
+///
+/// Lists and nested structs are searched through jsonpath, which these don't cover.
+fn derive_indexes(input: &DeriveData) -> TokenStream2 {
+    let ty = input.ident.to_string();
+
+    let indexes: Vec<_> = input
+        .fields
+        .iter()
+        .filter(|field| field.ty.wrapper != Wrapper::Vec)
+        .flat_map(|field| {
+            let name = field.ident.to_string();
+            let compared = field.is_searchable() && !field.attributes.contains(&Attribute::Struct);
+            let text = field.ty.ty_str() == "String";
+
+            let mut indexes = vec![];
+
+            if compared || field.attributes.contains(&Attribute::Sort) {
+                // Values without a scalar kind, such as enums, are compared as text
+                let kind = field
+                    .ty
+                    .kind()
+                    .unwrap_or_else(|| quote! { atoms::search::Kind::Text });
+
+                indexes.push(quote! { atoms::search::Index::value(#ty, #name, #kind) });
+            }
+
+            if text && (compared || field.fuzzy_threshold().is_some()) {
+                indexes.push(quote! { atoms::search::Index::trigram(#ty, #name) });
+            }
+
+            indexes
+        })
+        .collect();
+
+    if indexes.is_empty() {
+        return Default::default();
+    }
+
+    quote! {
+        fn indexes() -> Vec<atoms::search::Index> {
+            vec![#(#indexes),*]
+        }
+    }
+}
+
+fn derive_personal_data(input: &DeriveData) -> TokenStream2 {
+    let fields = input.personal_data();
+
+    if fields.is_empty() {
+        return Default::default();
+    }
+
+    quote! {
+        fn personal_data() -> Vec<String> {
+            vec![#(#fields.into()),*]
+        }
+    }
+}
+
+/// Every `Reference` held directly by a field, whether or not it is a `#[relation]`
+fn derive_references(input: &DeriveData) -> TokenStream2 {
+    let references: Vec<_> = input
+        .fields
+        .iter()
+        .filter(|field| field.ty.ty_str() == "Reference")
+        .map(|field| {
+            let ident = &field.ident;
+            let name = ident.to_string();
+
+            let held = match field.ty.wrapper {
+                Wrapper::Vec => quote! {
+                    self.#ident
+                        .as_ref()
+                        .and_then(|delta| delta.end.as_ref())
+                        .into_iter()
+                        .flatten()
+                },
+                Wrapper::Option => quote! {
+                    self.#ident
+                        .as_ref()
+                        .and_then(|delta| delta.end.as_ref())
+                        .into_iter()
+                },
+                Wrapper::None => quote! { self.#ident.end.iter().flatten() },
+            };
+
+            quote! {
+                #held.map(|reference| atoms::Referral {
+                    field: #name.into(),
+                    reference: reference.clone(),
+                })
+            }
+        })
+        .collect();
+
+    if references.is_empty() {
+        return Default::default();
+    }
+
+    quote! {
+        fn references(&self) -> Vec<atoms::Referral> {
+            std::iter::empty()
+                #(.chain(#references))*
+                .collect()
+        }
+    }
+}
+
+fn derive_relations(input: &DeriveData) -> TokenStream2 {
+    let relations: Vec<_> = input
+        .fields
+        .iter()
+        .filter_map(|field| field.relation().map(|relation| (field, relation)))
+        .map(|(field, relation)| {
+            let ident = &field.ident;
+            let name = ident.to_string();
+            let store = relation.store();
+            let integrity = relation.integrity();
+
+            quote! {
+                self.#ident
+                    .as_ref()
+                    .and_then(|delta| delta.end.clone())
+                    .map(|references| atoms::delta::Relation {
+                        field: #name.into(),
+                        ty: <#store as Store>::ty(),
+                        integrity: #integrity,
+                        references,
+                    })
+            }
+        })
+        .collect();
+
+    if relations.is_empty() {
+        return Default::default();
+    }
+
+    quote! {
+        fn relations(&self) -> Vec<atoms::delta::Relation> {
+            vec![#(#relations),*].into_iter().flatten().collect()
+        }
+    }
+}
+
+/// The text of the `#[searchable(text)]` fields, one field per line
+fn derive_document(input: &DeriveData) -> TokenStream2 {
+    let texts: Vec<_> = input
+        .fields
+        .iter()
+        .filter(|field| field.is_text_searchable())
+        .map(|field| {
+            let ident = &field.ident;
+            if field.ty.ty_str() != "String" {
+                panic!("`{}` can't be searchable(text), it must hold text", ident)
+            }
+            match field.ty.wrapper {
+                Wrapper::Option => quote! {
+                    self.#ident.as_ref().and_then(|delta| delta.end.clone())
+                },
+                Wrapper::Vec => quote! {
+                    self.#ident
+                        .as_ref()
+                        .and_then(|delta| delta.end.as_ref())
+                        .map(|texts| texts.join("\n"))
+                },
+                Wrapper::None => quote! {
+                    self.#ident.end.as_ref().map(|texts| texts.join("\n"))
+                },
+            }
+        })
+        .collect();
+
+    if texts.is_empty() {
+        return Default::default();
+    }
+
+    let language = input.search_language();
+
+    quote! {
+        fn document(&self) -> Option<atoms::search::Document> {
+            let texts: Vec<String> = vec![#(#texts),*].into_iter().flatten().collect();
+
+            Some(atoms::search::Document {
+                language: #language.into(),
+                text: texts.join("\n"),
+            })
         }
     }
 }
@@ -93,31 +348,96 @@ fn derive_del_trait(input: &DeriveData) -> TokenStream2 {
     let ident_str = base.to_string();
     let ident = Ident::new(format!("{}Store", ident_str).as_str(), base.span());
 
-    let fields = input.fields.iter().map(|field| {
-        let ident = &field.ident;
+    let fields: Vec<_> = input
+        .fields
+        .iter()
+        .map(|field| {
+            let ident = &field.ident;
 
-        quote! { #ident }
-    });
+            quote! { #ident }
+        })
+        .collect();
+
+    let delta_fields: Vec<_> = input
+        .fields
+        .iter()
+        .filter(|field| !field.is_identifier())
+        .map(|field| {
+            let ident = &field.ident;
+
+            quote! { #ident }
+        })
+        .collect();
 
     let apply_deltas = input.fields.iter().map(|field| {
         let ident = &field.ident;
         if field.is_identifier() {
-            return quote! {};
+            // Identifiers are fixed when the entity is created
+            return quote! {
+                if #ident.is_empty() {
+                    if let Some(new) = del.#ident.and_then(|del| del.end) {
+                        *#ident = new
+                    }
+                }
+            };
         };
+        let name = ident.to_string();
         match field.ty.wrapper {
             Wrapper::Vec | Wrapper::None => quote! {
-                if let Some(new) = check_delta(Some(&#ident), del.#ident.unwrap_or_default()) {
+                if let Ok(Some(new)) = check_delta(#name, Some(&#ident), del.#ident.unwrap_or_default()) {
                     *#ident = new
                 }
             },
             Wrapper::Option => quote! {
-                if let Some(new) = check_delta(#ident.as_ref(), del.#ident.unwrap_or_default()) {
+                if let Ok(Some(new)) = check_delta(#name, #ident.as_ref(), del.#ident.unwrap_or_default()) {
                     #ident.replace(new);
                 }
             },
         }
     });
 
+    let check_deltas = input
+        .fields
+        .iter()
+        .filter(|field| !field.is_identifier())
+        .map(|field| {
+            let ident = &field.ident;
+            let name = ident.to_string();
+            let current = match field.ty.wrapper {
+                Wrapper::Vec | Wrapper::None => quote! { Some(&#ident) },
+                Wrapper::Option => quote! { #ident.as_ref() },
+            };
+            quote! {
+                let #ident = match check_delta(#name, #current, del.#ident.unwrap_or_default()) {
+                    Ok(new) => (#ident, new),
+                    Err(conflict) => {
+                        conflicts.push(conflict);
+                        (#ident, None)
+                    }
+                };
+            }
+        });
+
+    let commit_deltas = input
+        .fields
+        .iter()
+        .filter(|field| !field.is_identifier())
+        .map(|field| {
+            let ident = &field.ident;
+            match field.ty.wrapper {
+                Wrapper::Vec | Wrapper::None => quote! {
+                    if let (#ident, Some(new)) = #ident {
+                        *#ident = new
+                    }
+                },
+                Wrapper::Option => quote! {
+                    if let (#ident, Some(new)) = #ident {
+                        #ident.replace(new);
+                    }
+                },
+            }
+        });
+
     quote! {
         impl Del<#ident> for #base {
             fn ty() -> String {
@@ -129,6 +449,22 @@ fn derive_del_trait(input: &DeriveData) -> TokenStream2 {
 
                 #(#apply_deltas)*
             }
+
+            fn try_apply(&mut self, del: #ident) -> Result<(), DeltaConflict> {
+                // Identifiers are fixed when the entity is created
+                let Self { #(#delta_fields,)* .. } = self;
+                let mut conflicts = vec![];
+
+                #(#check_deltas)*
+
+                if !conflicts.is_empty() {
+                    return Err(DeltaConflict { conflicts });
+                }
+
+                #(#commit_deltas)*
+
+                Ok(())
+            }
         }
     }
 }
@@ -153,6 +489,7 @@ fn derive_translations(input: &DeriveData) -> TokenStream2 {
                 fn from(org: #base) -> Self {
                     Self {
                         #(#fields,)*
+                        lifecycle: None,
                     }
                 }
             }
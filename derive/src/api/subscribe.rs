@@ -0,0 +1,207 @@
+use heck::SnakeCase;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+
+use super::query::{has_fuzzy, has_search, has_text, search_filter, search_struct_ident};
+use super::*;
+use crate::DeriveData;
+
+pub(crate) fn derive(input: &DeriveData) -> TokenStream2 {
+    let base = &input.ident;
+
+    let ident = Ident::new(format!("{}Subscription", base).as_str(), base.span());
+
+    let change = derive_change(input);
+
+    let changed = derive_changed(input);
+
+    let created = derive_created(input);
+
+    quote! {
+        #change
+
+        #[derive(Default)]
+        pub struct #ident;
+
+        #[async_graphql::Subscription]
+        impl #ident {
+            #changed
+
+            #created
+        }
+    }
+}
+
+fn change_ident(input: &DeriveData) -> Ident {
+    let base = &input.ident;
+    Ident::new(format!("{}Change", base).as_str(), base.span())
+}
+
+/// What subscribers are sent of each change: the entity it left and the delta applied
+fn derive_change(input: &DeriveData) -> TokenStream2 {
+    let base = &input.ident;
+    let ty = base.to_string();
+
+    let change = change_ident(input);
+    let entity = Ident::new(base.to_string().to_snake_case().as_str(), base.span());
+
+    let change_comment = format!("A change committed to a {}", base);
+    let id_comment = format!("The global id of the {} changed", base);
+    let entity_comment = format!(
+        "The {} as of the change, missing once deleted or purged",
+        base
+    );
+
+    quote! {
+        #[doc = #change_comment]
+        #[derive(async_graphql::SimpleObject)]
+        pub struct #change {
+            #[doc = #id_comment]
+            id: async_graphql::ID,
+            #[doc = #entity_comment]
+            #entity: Option<#base>,
+            /// The fields the delta changed, at the version it brought the entity to
+            delta: atoms::history::HistoryEntry,
+        }
+
+        impl From<store::Change<#base>> for #change {
+            fn from(change: store::Change<#base>) -> Self {
+                Self {
+                    id: atoms::node::GlobalId::new(#ty, change.id).encode(),
+                    #entity: change.entity,
+                    delta: change.delta,
+                }
+            }
+        }
+    }
+}
+
+fn derive_changed(input: &DeriveData) -> TokenStream2 {
+    let decode_id = decode_id(input);
+
+    let base = &input.ident;
+    let func_name = Ident::new(
+        format!("{}_changed", base.to_string().to_snake_case()).as_str(),
+        base.span(),
+    );
+
+    let store = Ident::new(format!("{}Store", base).as_str(), base.span());
+    let change = change_ident(input);
+
+    let changed_comment = format!(
+        "Each change committed to the {} with this global id from now on",
+        base
+    );
+
+    quote! {
+        #[doc = #changed_comment]
+        async fn #func_name(
+            &self,
+            ctx: &Context<'_>,
+            id: async_graphql::ID,
+        ) -> Result<impl futures::Stream<Item = #change>> {
+            use futures::StreamExt;
+
+            let identity = ctx.data::<auth::Identity>()?;
+            let db = ctx.data::<store::Database>()?;
+            #decode_id
+            #base::authorize_query(identity)?;
+
+            let changes = store::Driver::changes::<#base, #store>(db.clone(), Some(id)).await?;
+
+            Ok(changes.map(Into::into))
+        }
+    }
+}
+
+/// Subscribe to the entities created from now on, filtered with the arguments of the search
+/// when the type has one
+fn derive_created(input: &DeriveData) -> TokenStream2 {
+    let base = &input.ident;
+    let func_name = Ident::new(
+        format!("{}_created", base.to_string().to_snake_case()).as_str(),
+        base.span(),
+    );
+
+    let store = Ident::new(format!("{}Store", base).as_str(), base.span());
+    let change = change_ident(input);
+
+    let (params, filter) = if has_search(input) {
+        let search = search_struct_ident(input);
+
+        let mut params = vec![];
+        let mut idents = vec![];
+
+        for field in input.fields.iter().filter(|field| field.is_searchable()) {
+            let ident = &field.ident;
+            let filter = search_filter(input, field);
+
+            params.push(quote! { #ident: Option<#filter>, });
+            idents.push(quote! { #ident });
+        }
+
+        if has_text(input) {
+            params.push(quote! { query: Option<String>, });
+            idents.push(quote! { query });
+        }
+
+        if has_fuzzy(input) {
+            params.push(quote! { fuzzy: Option<String>, });
+            idents.push(quote! { fuzzy });
+        }
+
+        (
+            params,
+            quote! {
+                let doc = #search { #(#idents,)* };
+
+                let matches = move |entity: &#base| {
+                    store::Driver::matches::<_, #store, _>(entity, &doc)
+                };
+            },
+        )
+    } else {
+        (vec![], quote! { let matches = |_: &#base| true; })
+    };
+
+    let created_comment = format!("Each {} created from now on", base);
+    let search_comment = format!(
+        "Filtered like `search{}`, on the {} as it was created",
+        base, base
+    );
+    let search_comment = if has_search(input) {
+        quote! {
+            ///
+            #[doc = #search_comment]
+        }
+    } else {
+        Default::default()
+    };
+
+    quote! {
+        #[doc = #created_comment]
+        #search_comment
+        #[allow(clippy::too_many_arguments)]
+        async fn #func_name(
+            &self,
+            ctx: &Context<'_>,
+            #(#params)*
+        ) -> Result<impl futures::Stream<Item = #change>> {
+            use futures::StreamExt;
+
+            let identity = ctx.data::<auth::Identity>()?;
+            let db = ctx.data::<store::Database>()?;
+            #base::authorize_query(identity)?;
+
+            #filter
+
+            let created = store::Driver::created::<#base, #store>(db.clone()).await?;
+
+            Ok(created
+                .filter(move |change| {
+                    futures::future::ready(change.entity.as_ref().map_or(false, &matches))
+                })
+                .map(Into::into))
+        }
+    }
+}
@@ -7,7 +7,7 @@ use super::*;
 use crate::DeriveData;
 
 pub(crate) fn derive(input: &DeriveData) -> TokenStream2 {
-    let field_deltas = derive_field_deltas(&input);
+    let field_deltas = derive_field_deltas(input);
 
     quote! {
         #(#field_deltas)*
@@ -58,6 +58,7 @@ fn derive_field_deltas(input: &DeriveData) -> Vec<TokenStream2> {
                             Self {
                                 start: delta.start,
                                 end: delta.end,
+                                redacted: false,
                             }
                         }
                     }
@@ -112,6 +113,7 @@ fn derive_field_deltas(input: &DeriveData) -> Vec<TokenStream2> {
                             Self {
                                 start: delta.start,
                                 end: #conversion,
+                                redacted: false,
                             }
                         }
                     }
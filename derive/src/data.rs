@@ -1,7 +1,10 @@
 use quote::ToTokens;
 use syn::parse_quote;
 
-use self::attribute::auth::AuthAttribute;
+use self::attribute::{
+    auth::AuthAttribute, referrers::ReferrersAttribute, search::SearchAttribute,
+    snapshot::SnapshotAttribute,
+};
 use super::*;
 use std::convert::{TryFrom, TryInto};
 
@@ -54,6 +57,55 @@ impl DeriveData {
             .cloned()
             .unwrap_or_default()
     }
+
+    /// The text search configuration of `#[searchable(text)]` fields, `english` by default
+    pub fn search_language(&self) -> String {
+        self.attributes
+            .iter()
+            .find_map(|attr| match attr {
+                Attribute::Search(SearchAttribute {
+                    language: Some(language),
+                    ..
+                }) => Some(language.clone()),
+                _ => None,
+            })
+            .unwrap_or_else(|| "english".into())
+    }
+
+    /// The names of the `#[personal_data]` fields
+    pub fn personal_data(&self) -> Vec<String> {
+        self.fields
+            .iter()
+            .filter(|field| field.attributes.contains(&Attribute::PersonalData))
+            .map(|field| {
+                if field.is_identifier() {
+                    panic!("The identifier can't be personal_data, it is kept to find the entity")
+                }
+                field.ident.to_string()
+            })
+            .collect()
+    }
+
+    /// How `referencedBy` authorizes referrers, generated only with `#[referrers]`
+    pub fn referrers_attribute(&self) -> Option<ReferrersAttribute> {
+        self.attributes.iter().find_map(|attr| {
+            if let Attribute::Referrers(attr) = attr {
+                Some(attr.clone())
+            } else {
+                None
+            }
+        })
+    }
+
+    pub fn snapshot_attribute(&self) -> Option<SnapshotAttribute> {
+        self.attributes.iter().find_map(|attr| {
+            if let Attribute::Snapshot(attr) = attr {
+                Some(*attr)
+            } else {
+                None
+            }
+        })
+    }
 }
 
 pub(crate) struct Field {
@@ -68,6 +120,32 @@ impl Field {
     pub fn is_identifier(&self) -> bool {
         self.ident == "identifier"
     }
+    /// Marked `#[searchable]`, to filter on
+    pub fn is_searchable(&self) -> bool {
+        self.attributes
+            .iter()
+            .any(|attr| matches!(attr, Attribute::Search(search) if !search.text && !search.fuzzy))
+    }
+    /// Marked `#[searchable(text)]`, for full-text search
+    pub fn is_text_searchable(&self) -> bool {
+        self.attributes
+            .iter()
+            .any(|attr| matches!(attr, Attribute::Search(search) if search.text))
+    }
+    /// The similarity threshold of a field marked `#[searchable(fuzzy)]`
+    pub fn fuzzy_threshold(&self) -> Option<f64> {
+        self.attributes.iter().find_map(|attr| match attr {
+            Attribute::Search(search) if search.fuzzy => Some(search.threshold()),
+            _ => None,
+        })
+    }
+    /// The type a `#[relation]` field refers to
+    pub fn relation(&self) -> Option<&attribute::relation::RelationAttribute> {
+        self.attributes.iter().find_map(|attr| match attr {
+            Attribute::Relation(relation) => Some(relation),
+            _ => None,
+        })
+    }
     pub fn wrapped(&self) -> TokenStream2 {
         let ty = &self.ty.ty;
         self.wrap_other(ty)
@@ -152,6 +230,32 @@ impl Type {
             _ => unimplemented!("Only path types"),
         }
     }
+
+    /// The search input listing the operators that apply to this type, if it is a scalar
+    pub fn filter(&self) -> Option<TokenStream2> {
+        match self.ty_str().as_str() {
+            "String" => Some(quote! { atoms::search::StringFilter }),
+            "u8" | "u16" | "u32" | "u64" | "usize" | "i8" | "i16" | "i32" | "i64" | "isize" => {
+                Some(quote! { atoms::search::IntFilter })
+            }
+            "f32" | "f64" => Some(quote! { atoms::search::FloatFilter }),
+            "bool" => Some(quote! { atoms::search::BooleanFilter }),
+            "DateTime" => Some(quote! { atoms::search::DateTimeFilter }),
+            _ => None,
+        }
+    }
+
+    /// How values of this type are compared when searching and sorting, if it is a scalar
+    pub fn kind(&self) -> Option<TokenStream2> {
+        match self.ty_str().as_str() {
+            "String" => Some(quote! { atoms::search::Kind::Text }),
+            "u8" | "u16" | "u32" | "u64" | "usize" | "i8" | "i16" | "i32" | "i64" | "isize"
+            | "f32" | "f64" => Some(quote! { atoms::search::Kind::Number }),
+            "bool" => Some(quote! { atoms::search::Kind::Boolean }),
+            "DateTime" => Some(quote! { atoms::search::Kind::DateTime }),
+            _ => None,
+        }
+    }
 }
 
 #[derive(Debug, PartialEq, Eq)]
@@ -185,13 +289,19 @@ fn get_inner_ty(origin: &PathSegment) -> syn::Type {
     }
 }
 
-mod attribute;
+pub(crate) mod attribute;
 
 #[derive(PartialEq, Eq, Debug)]
 pub(crate) enum Attribute {
     Struct,
     Auth(attribute::auth::AuthAttribute),
-    Search,
+    Snapshot(attribute::snapshot::SnapshotAttribute),
+    Search(attribute::search::SearchAttribute),
+    Sort,
+    Unique,
+    PersonalData,
+    Relation(attribute::relation::RelationAttribute),
+    Referrers(attribute::referrers::ReferrersAttribute),
     Doc,
 }
 
@@ -205,7 +315,22 @@ impl TryFrom<syn::Attribute> for Attribute {
                 "auth" => Ok(Self::Auth(
                     attr.parse_args().unwrap_or_else(|e| panic!("{}", e)),
                 )),
-                "searchable" => Ok(Self::Search),
+                "snapshot" => Ok(Self::Snapshot(
+                    attr.parse_args().unwrap_or_else(|e| panic!("{}", e)),
+                )),
+                "searchable" if attr.tokens.is_empty() => Ok(Self::Search(Default::default())),
+                "searchable" => Ok(Self::Search(
+                    attr.parse_args().unwrap_or_else(|e| panic!("{}", e)),
+                )),
+                "sortable" => Ok(Self::Sort),
+                "unique" => Ok(Self::Unique),
+                "personal_data" => Ok(Self::PersonalData),
+                "relation" => Ok(Self::Relation(
+                    attr.parse_args().unwrap_or_else(|e| panic!("{}", e)),
+                )),
+                "referrers" => Ok(Self::Referrers(
+                    attr.parse_args().unwrap_or_else(|e| panic!("{}", e)),
+                )),
                 "doc" => Ok(Self::Doc),
                 _ => Err(()),
             }
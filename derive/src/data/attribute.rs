@@ -1 +1,5 @@
 pub mod auth;
+pub mod referrers;
+pub mod relation;
+pub mod search;
+pub mod snapshot;
@@ -0,0 +1,53 @@
+use proc_macro2::{Span, TokenStream};
+use quote::quote;
+use syn::{Ident, Token};
+
+/// `#[referrers(authorize = node::authorize_query)]` generates `referencedBy`, listing the
+/// entities that refer to this one.
+///
+/// Referrers may be of any type, so each is passed by type name, with the identity, to the
+/// function at that path, which must return `async_graphql::Result<()>`. Those it refuses are
+/// left out.
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct ReferrersAttribute {
+    /// The path to the function authorizing each referrer's type, one segment each
+    pub authorize: Vec<String>,
+}
+
+impl syn::parse::Parse for ReferrersAttribute {
+    fn parse(input: syn::parse::ParseStream) -> syn::parse::Result<Self> {
+        let key: Ident = input.parse()?;
+        if key != "authorize" {
+            return Err(syn::Error::new_spanned(key, "Expected `authorize`"));
+        }
+        input.parse::<Token![=]>()?;
+
+        let path: syn::Path = input.parse()?;
+
+        let authorize = path
+            .segments
+            .iter()
+            .map(|segment| match segment.arguments {
+                syn::PathArguments::None => Ok(segment.ident.to_string()),
+                _ => Err(syn::Error::new_spanned(
+                    segment,
+                    "The authorizer must be named without arguments",
+                )),
+            })
+            .collect::<syn::Result<_>>()?;
+
+        Ok(Self { authorize })
+    }
+}
+
+impl ReferrersAttribute {
+    /// The function authorizing each referrer's type
+    pub fn authorize(&self) -> TokenStream {
+        let segments = self
+            .authorize
+            .iter()
+            .map(|segment| Ident::new(segment, Span::call_site()));
+
+        quote! { #(#segments)::* }
+    }
+}
@@ -0,0 +1,102 @@
+use proc_macro2::{Span, TokenStream};
+use quote::quote;
+use syn::{Ident, Token};
+
+/// `#[relation(transaction::Transaction)]` resolves the references of a field to the entities
+/// they point to, of the `Api` type at that path.
+///
+/// Writes refuse references to entities that don't exist, unless relaxed with
+/// `#[relation(Organization, integrity = warn)]` to log them or `integrity = off` to allow them.
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct RelationAttribute {
+    /// The path to the referenced type, one segment each
+    pub target: Vec<String>,
+    pub integrity: Integrity,
+}
+
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub enum Integrity {
+    Strict,
+    Warn,
+    Off,
+}
+
+impl syn::parse::Parse for RelationAttribute {
+    fn parse(input: syn::parse::ParseStream) -> syn::parse::Result<Self> {
+        let path: syn::Path = input.parse()?;
+
+        let target = path
+            .segments
+            .iter()
+            .map(|segment| match segment.arguments {
+                syn::PathArguments::None => Ok(segment.ident.to_string()),
+                _ => Err(syn::Error::new_spanned(
+                    segment,
+                    "A relation must name a type without arguments",
+                )),
+            })
+            .collect::<syn::Result<_>>()?;
+
+        let mut integrity = Integrity::Strict;
+
+        if !input.is_empty() {
+            input.parse::<Token![,]>()?;
+
+            let key: Ident = input.parse()?;
+            if key != "integrity" {
+                return Err(syn::Error::new_spanned(key, "Expected `integrity`"));
+            }
+            input.parse::<Token![=]>()?;
+
+            let value: Ident = input.parse()?;
+            integrity = match value.to_string().as_str() {
+                "strict" => Integrity::Strict,
+                "warn" => Integrity::Warn,
+                "off" => Integrity::Off,
+                _ => {
+                    return Err(syn::Error::new_spanned(
+                        value,
+                        "Integrity must be `strict`, `warn` or `off`",
+                    ))
+                }
+            };
+        }
+
+        Ok(Self { target, integrity })
+    }
+}
+
+impl RelationAttribute {
+    /// The referenced type
+    pub fn ty(&self) -> TokenStream {
+        self.path(None)
+    }
+
+    /// The store derived for the referenced type
+    pub fn store(&self) -> TokenStream {
+        self.path(Some("Store"))
+    }
+
+    /// The `atoms::delta::Integrity` the field's references are checked with
+    pub fn integrity(&self) -> TokenStream {
+        match self.integrity {
+            Integrity::Strict => quote! { atoms::delta::Integrity::Strict },
+            Integrity::Warn => quote! { atoms::delta::Integrity::Warn },
+            Integrity::Off => quote! { atoms::delta::Integrity::Off },
+        }
+    }
+
+    fn path(&self, suffix: Option<&str>) -> TokenStream {
+        let last = self.target.len() - 1;
+
+        let segments = self.target.iter().enumerate().map(|(i, segment)| {
+            let segment = match suffix {
+                Some(suffix) if i == last => format!("{}{}", segment, suffix),
+                _ => segment.clone(),
+            };
+            Ident::new(&segment, Span::call_site())
+        });
+
+        quote! { #(#segments)::* }
+    }
+}
@@ -0,0 +1,88 @@
+use syn::{punctuated::Punctuated, token::Comma, Expr, ExprAssign};
+
+/// `#[searchable]` filters on a field.
+///
+/// `#[searchable(text)]` instead indexes the field for full-text search, in the language set
+/// on the struct with `#[searchable(language = "simple")]`.
+///
+/// `#[searchable(fuzzy)]` finds text fields despite typos, matching values with a trigram
+/// similarity of at least `threshold`, 0.3 unless set with `#[searchable(fuzzy, threshold = 0.5)]`.
+#[derive(PartialEq, Eq, Default, Debug, Clone)]
+pub struct SearchAttribute {
+    pub text: bool,
+    pub language: Option<String>,
+    pub fuzzy: bool,
+    /// The threshold as written, between 0 and 1
+    pub threshold: Option<String>,
+}
+
+impl SearchAttribute {
+    pub fn threshold(&self) -> f64 {
+        self.threshold
+            .as_ref()
+            .and_then(|threshold| threshold.parse().ok())
+            .unwrap_or(0.3)
+    }
+}
+
+impl syn::parse::Parse for SearchAttribute {
+    fn parse(input: syn::parse::ParseStream) -> syn::parse::Result<Self> {
+        let punctuated: Punctuated<Expr, Comma> = Punctuated::parse_terminated(input)?;
+
+        let mut res = Self::default();
+
+        for expr in punctuated {
+            match expr {
+                Expr::Path(path) if path.path.is_ident("text") => res.text = true,
+                Expr::Path(path) if path.path.is_ident("fuzzy") => res.fuzzy = true,
+                Expr::Assign(ExprAssign { left, right, .. }) => match (*left, *right) {
+                    (Expr::Path(path), Expr::Lit(lit)) if path.path.is_ident("language") => {
+                        match lit.lit {
+                            syn::Lit::Str(language) => res.language = Some(language.value()),
+                            lit => {
+                                return Err(syn::Error::new_spanned(
+                                    lit,
+                                    "Language must be a string",
+                                ))
+                            }
+                        }
+                    }
+                    (Expr::Path(path), Expr::Lit(lit)) if path.path.is_ident("threshold") => {
+                        let threshold: f64 = match &lit.lit {
+                            syn::Lit::Float(threshold) => threshold.base10_parse()?,
+                            syn::Lit::Int(threshold) => threshold.base10_parse()?,
+                            _ => -1.0,
+                        };
+
+                        if !(0.0..=1.0).contains(&threshold) {
+                            return Err(syn::Error::new_spanned(
+                                lit,
+                                "Threshold must be a number between 0 and 1",
+                            ));
+                        }
+
+                        res.threshold = Some(threshold.to_string())
+                    }
+                    (left, _) => {
+                        return Err(syn::Error::new_spanned(
+                            left,
+                            "Expected `language` or `threshold`",
+                        ))
+                    }
+                },
+                expr => {
+                    return Err(syn::Error::new_spanned(
+                        expr,
+                        "Expected `text`, `fuzzy`, `language = \"...\"` or `threshold = ...`",
+                    ))
+                }
+            }
+        }
+
+        if res.threshold.is_some() && !res.fuzzy {
+            return Err(input.error("`threshold` only applies to `fuzzy`"));
+        }
+
+        Ok(res)
+    }
+}
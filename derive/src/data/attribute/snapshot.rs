@@ -0,0 +1,42 @@
+use syn::{ExprAssign, LitInt};
+
+/// `#[snapshot(every = 100)]` stores a rolled-up snapshot after every `every` deltas
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub struct SnapshotAttribute {
+    pub every: u64,
+}
+
+impl syn::parse::Parse for SnapshotAttribute {
+    fn parse(input: syn::parse::ParseStream) -> syn::parse::Result<Self> {
+        let expr: ExprAssign = input.parse()?;
+
+        match expr.left.as_ref() {
+            syn::Expr::Path(path) if path.path.is_ident("every") => {}
+            _ => return Err(syn::Error::new_spanned(expr.left, "Expected `every`")),
+        }
+
+        let every: LitInt = match expr.right.as_ref() {
+            syn::Expr::Lit(syn::ExprLit {
+                lit: syn::Lit::Int(lit),
+                ..
+            }) => lit.clone(),
+            _ => {
+                return Err(syn::Error::new_spanned(
+                    expr.right,
+                    "Snapshot interval must be an integer",
+                ))
+            }
+        };
+
+        let every = every.base10_parse()?;
+
+        if every == 0 {
+            return Err(syn::Error::new_spanned(
+                expr.right,
+                "Snapshot interval must be at least 1",
+            ));
+        }
+
+        Ok(Self { every })
+    }
+}
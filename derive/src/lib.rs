@@ -9,7 +9,20 @@ mod support;
 mod data;
 pub(crate) use data::*;
 
-#[proc_macro_derive(Api, attributes(construct, auth, searchable))]
+#[proc_macro_derive(
+    Api,
+    attributes(
+        construct,
+        auth,
+        searchable,
+        sortable,
+        snapshot,
+        unique,
+        personal_data,
+        relation,
+        referrers
+    )
+)]
 pub fn derive_api(input: TokenStream) -> TokenStream {
     api::derive(input.into())
 }
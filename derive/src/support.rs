@@ -1,17 +1,20 @@
 use super::*;
-use heck::{CamelCase, SnakeCase};
+use heck::CamelCase;
 use quote::quote;
-use syn::{parse_quote, token::Token};
 
 pub(crate) fn derive(input: DeriveData) -> TokenStream {
     let input_derived = derive_input(&input);
 
     let standard = derive_standard(&input);
 
+    let filter = derive_filter(&input);
+
     TokenStream::from(quote! {
         #standard
 
         #input_derived
+
+        #filter
     })
 }
 
@@ -40,7 +43,7 @@ fn derive_standard(input: &DeriveData) -> TokenStream2 {
 fn derive_input(input: &DeriveData) -> TokenStream2 {
     let DeriveData { ident, fields, .. } = input;
 
-    let input_ident = Ident::new(&format!("{}Input", ident.to_string()), ident.span());
+    let input_ident = Ident::new(&format!("{}Input", ident), ident.span());
 
     let input_fields = fields.iter().map(|field| {
         let Field {
@@ -98,3 +101,56 @@ fn derive_input(input: &DeriveData) -> TokenStream2 {
         }
     }
 }
+
+/// Search inside the struct through its scalar fields, when it has any
+fn derive_filter(input: &DeriveData) -> TokenStream2 {
+    let DeriveData { ident, fields, .. } = input;
+
+    let filter_ident = Ident::new(&format!("{}Filter", ident), ident.span());
+
+    let fields: Vec<_> = fields
+        .iter()
+        .filter_map(|field| field.ty.filter().map(|filter| (&field.ident, filter)))
+        .collect();
+
+    if fields.is_empty() {
+        return quote! {};
+    }
+
+    let filter_fields = fields.iter().map(|(ident, filter)| {
+        quote! {
+            #ident: Option<#filter>
+        }
+    });
+
+    let conditions = fields.iter().map(|(ident, _)| {
+        let name = ident.to_string();
+
+        quote! {
+            if let Some(#ident) = self.#ident.as_ref() {
+                res.extend(
+                    atoms::search::Filter::conditions(#ident, #name)
+                        .into_iter()
+                        .map(|condition| condition.within(field)),
+                )
+            }
+        }
+    });
+
+    quote! {
+        #[derive(Clone, Debug, Default, InputObject)]
+        pub struct #filter_ident {
+            #(#filter_fields,)*
+        }
+
+        impl atoms::search::Filter for #filter_ident {
+            fn conditions(&self, field: &str) -> Vec<atoms::search::Condition> {
+                let mut res = vec![];
+
+                #(#conditions)*
+
+                res
+            }
+        }
+    }
+}
@@ -0,0 +1,126 @@
+use crate::data::attribute::{
+    referrers::ReferrersAttribute,
+    relation::{Integrity, RelationAttribute},
+    search::SearchAttribute,
+    snapshot::SnapshotAttribute,
+};
+use syn::parse_quote;
+
+#[test]
+fn snapshot_every() {
+    let attr: SnapshotAttribute = syn::parse2(parse_quote! { every = 50 }).unwrap();
+
+    assert_eq!(attr.every, 50);
+}
+
+#[test]
+fn snapshot_every_zero() {
+    let attr = syn::parse2::<SnapshotAttribute>(parse_quote! { every = 0 });
+
+    assert!(attr.is_err());
+}
+
+#[test]
+fn search_text() {
+    let attr: SearchAttribute = syn::parse2(parse_quote! { text }).unwrap();
+
+    assert!(attr.text);
+    assert_eq!(attr.language, None);
+}
+
+#[test]
+fn search_language() {
+    let attr: SearchAttribute = syn::parse2(parse_quote! { language = "simple" }).unwrap();
+
+    assert!(!attr.text);
+    assert_eq!(attr.language, Some("simple".into()));
+}
+
+#[test]
+fn search_fuzzy() {
+    let attr: SearchAttribute = syn::parse2(parse_quote! { fuzzy }).unwrap();
+
+    assert!(attr.fuzzy);
+    assert_eq!(attr.threshold(), 0.3);
+
+    let attr: SearchAttribute = syn::parse2(parse_quote! { fuzzy, threshold = 0.5 }).unwrap();
+
+    assert_eq!(attr.threshold(), 0.5);
+}
+
+#[test]
+fn search_threshold_out_of_range() {
+    let attr = syn::parse2::<SearchAttribute>(parse_quote! { fuzzy, threshold = 1.5 });
+
+    assert!(attr.is_err());
+}
+
+#[test]
+fn search_threshold_without_fuzzy() {
+    let attr = syn::parse2::<SearchAttribute>(parse_quote! { threshold = 0.5 });
+
+    assert!(attr.is_err());
+}
+
+#[test]
+fn search_unknown() {
+    let attr = syn::parse2::<SearchAttribute>(parse_quote! { exact });
+
+    assert!(attr.is_err());
+}
+
+#[test]
+fn relation_store() {
+    let attr: RelationAttribute = syn::parse2(parse_quote! { transaction::Transaction }).unwrap();
+
+    assert_eq!(attr.ty().to_string(), "transaction :: Transaction");
+    assert_eq!(attr.store().to_string(), "transaction :: TransactionStore");
+}
+
+#[test]
+fn relation_with_arguments() {
+    let attr = syn::parse2::<RelationAttribute>(parse_quote! { Vec<Transaction> });
+
+    assert!(attr.is_err());
+}
+
+#[test]
+fn relation_strict_by_default() {
+    let attr: RelationAttribute = syn::parse2(parse_quote! { Organization }).unwrap();
+
+    assert_eq!(attr.integrity, Integrity::Strict);
+}
+
+#[test]
+fn relation_integrity() {
+    let attr: RelationAttribute =
+        syn::parse2(parse_quote! { transaction::Transaction, integrity = warn }).unwrap();
+
+    assert_eq!(attr.target, vec!["transaction", "Transaction"]);
+    assert_eq!(attr.integrity, Integrity::Warn);
+}
+
+#[test]
+fn relation_unknown_integrity() {
+    let attr = syn::parse2::<RelationAttribute>(parse_quote! { Organization, integrity = loose });
+
+    assert!(attr.is_err());
+}
+
+#[test]
+fn referrers_authorize() {
+    let attr: ReferrersAttribute =
+        syn::parse2(parse_quote! { authorize = crate::node::authorize_query }).unwrap();
+
+    assert_eq!(
+        attr.authorize().to_string(),
+        "crate :: node :: authorize_query"
+    );
+}
+
+#[test]
+fn referrers_unknown() {
+    let attr = syn::parse2::<ReferrersAttribute>(parse_quote! { check = authorize_query });
+
+    assert!(attr.is_err());
+}
@@ -1 +1,2 @@
+mod attribute;
 mod ty;
@@ -49,3 +49,37 @@ fn ty_get_out_str() {
 
     assert_eq!(ty.ty_str(), "DateTime");
 }
+
+#[test]
+fn ty_filter() {
+    let filter = |syn_ty: syn::Type| Type::from(syn_ty).filter().map(|ty| ty.to_string());
+
+    assert_eq!(
+        filter(parse_quote! { Option<u32> }),
+        Some(quote::quote! { atoms::search::IntFilter }.to_string())
+    );
+    assert_eq!(
+        filter(parse_quote! { Vec<String> }),
+        Some(quote::quote! { atoms::search::StringFilter }.to_string())
+    );
+    assert_eq!(
+        filter(parse_quote! { Option<DateTime<Utc>> }),
+        Some(quote::quote! { atoms::search::DateTimeFilter }.to_string())
+    );
+    assert_eq!(filter(parse_quote! { Vec<Tag> }), None);
+}
+
+#[test]
+fn ty_kind() {
+    let kind = |syn_ty: syn::Type| Type::from(syn_ty).kind().map(|ty| ty.to_string());
+
+    assert_eq!(
+        kind(parse_quote! { Option<f64> }),
+        Some(quote::quote! { atoms::search::Kind::Number }.to_string())
+    );
+    assert_eq!(
+        kind(parse_quote! { Option<DateTime<Utc>> }),
+        Some(quote::quote! { atoms::search::Kind::DateTime }.to_string())
+    );
+    assert_eq!(kind(parse_quote! { Option<Tag> }), None);
+}
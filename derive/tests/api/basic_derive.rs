@@ -1,15 +1,20 @@
+#![deny(unused_variables)]
 use async_graphql::{
     connection::{Connection, Edge, EmptyFields},
-    Context, Enum, Object, Result, SimpleObject,
+    Context, Enum, Object, Result,
+};
+use atoms::{
+    delta::*,
+    pagination::PaginationOption,
+    search::{Condition, Search},
+    *,
 };
-use atoms::delta::Rollup;
-use atoms::delta::*;
-use atoms::pagination::PaginationOption;
-use atoms::search::{Search, SqlQuery};
 
-#[derive(SimpleObject, Default, Clone, Debug, derive::Api)]
+#[derive(Default, Clone, Debug, derive::Api)]
 pub struct Org {
+    #[construct]
     identifier: Vec<atoms::Identifier>,
+    #[searchable]
     name: Option<String>,
     tag: Option<Vec<Tag>>,
 }
@@ -21,35 +26,4 @@ enum Tag {
     Value,
 }
 
-pub struct OrgSearch {
-    name: Option<String>,
-}
-
-impl Search<OrgStore> for OrgSearch {
-    fn ty() -> String {
-        "Organization".into()
-    }
-    fn array_splits(&self) -> Vec<String> {
-        Default::default()
-    }
-    fn paths(&self) -> Vec<String> {
-        let mut res = vec![];
-
-        if self.name.is_some() {
-            res.push("body -> 'name' ->> 'end' LIKE $".into())
-        }
-
-        res
-    }
-    fn bind_args<'a>(&self, query: SqlQuery<'a>) -> SqlQuery<'a> {
-        let query = if let Some(name) = self.name.clone() {
-            query.bind(format!("%{}%", name))
-        } else {
-            query
-        };
-
-        query
-    }
-}
-
 pub fn main() {}
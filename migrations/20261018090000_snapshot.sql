@@ -0,0 +1,10 @@
+-- Rolled-up bodies that cover the first `version` deltas of an entity
+CREATE TABLE snapshot (
+  id UUID NOT NULL,
+  ty varchar(255) NOT NULL,
+  body jsonb NOT NULL,
+  version BIGINT NOT NULL,
+  delta_created_at TIMESTAMPTZ NOT NULL,
+  created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+  PRIMARY KEY (id, version)
+);
@@ -0,0 +1,3 @@
+-- Text of the `#[searchable(text)]` fields of each projection, and its stemmed words
+ALTER TABLE projection ADD COLUMN document TEXT, ADD COLUMN tsv TSVECTOR;
+CREATE INDEX projection_tsv ON projection USING GIN (tsv);
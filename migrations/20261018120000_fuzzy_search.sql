@@ -0,0 +1,2 @@
+-- Trigram similarity for `#[searchable(fuzzy)]` fields
+CREATE EXTENSION IF NOT EXISTS pg_trgm;
@@ -0,0 +1,11 @@
+-- Every read filters on the type, and searches are ordered by when projections were created
+-- unless told otherwise
+CREATE INDEX IF NOT EXISTS projection_ty ON projection (ty, last_updated, id);
+CREATE INDEX IF NOT EXISTS delta_id ON delta (id, created_at);
+CREATE INDEX IF NOT EXISTS delta_ty ON delta (ty);
+
+-- Casting text to timestamptz depends on the session time zone, so it can't be indexed.
+-- RFC 3339 timestamps always carry their offset, making the cast immutable for them.
+CREATE OR REPLACE FUNCTION rfc3339(text) RETURNS timestamptz
+  AS $$ SELECT $1::timestamptz $$
+  LANGUAGE sql IMMUTABLE STRICT;
@@ -0,0 +1,7 @@
+-- Indexes declared by the model, written by `indexes migrate`
+CREATE INDEX IF NOT EXISTS projection_account_email ON projection (ty, ((body -> 'email' ->> 'end')));
+CREATE INDEX IF NOT EXISTS projection_account_email_trgm ON projection USING GIN ((body -> 'email' ->> 'end') gin_trgm_ops);
+CREATE INDEX IF NOT EXISTS projection_organization_name ON projection (ty, ((body -> 'name' ->> 'end')));
+CREATE INDEX IF NOT EXISTS projection_organization_name_trgm ON projection USING GIN ((body -> 'name' ->> 'end') gin_trgm_ops);
+CREATE INDEX IF NOT EXISTS projection_organization_established ON projection (ty, (rfc3339((body -> 'established' ->> 'end'))));
+CREATE INDEX IF NOT EXISTS projection_transaction_amount ON projection (ty, (((body -> 'amount' ->> 'end'))::numeric));
@@ -0,0 +1,15 @@
+-- Every identifier of every entity, each naming at most one entity of its type
+CREATE TABLE identifier (
+  ty varchar(255) NOT NULL,
+  system varchar(255) NOT NULL,
+  value TEXT NOT NULL,
+  id UUID NOT NULL,
+  PRIMARY KEY (ty, system, value)
+);
+CREATE INDEX identifier_id ON identifier (id);
+
+INSERT INTO identifier (ty, system, value, id)
+SELECT p.ty, i ->> 'system', i ->> 'value', p.id
+FROM projection p, jsonb_array_elements(p.body -> 'identifier' -> 'end') AS i
+WHERE jsonb_typeof(p.body -> 'identifier' -> 'end') = 'array'
+ON CONFLICT DO NOTHING;
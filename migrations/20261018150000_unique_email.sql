@@ -0,0 +1,8 @@
+-- Claim the emails of existing accounts. Where several share one, only one of them
+-- keeps it, and the others are refused updates until their email changes.
+INSERT INTO identifier (ty, system, value, id)
+SELECT p.ty, 'email', p.body -> 'email' ->> 'end', p.id
+FROM projection p
+WHERE p.ty = 'Account'
+AND jsonb_typeof(p.body -> 'email' -> 'end') = 'string'
+ON CONFLICT DO NOTHING;
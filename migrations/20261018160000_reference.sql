@@ -0,0 +1,20 @@
+-- Every reference held by a projection, so an entity can find those that refer to it
+CREATE TABLE reference (
+  ty varchar(255) NOT NULL,
+  id UUID NOT NULL,
+  field varchar(255) NOT NULL,
+  reference_ty varchar(255) NOT NULL,
+  system varchar(255) NOT NULL,
+  value TEXT NOT NULL
+);
+CREATE INDEX reference_identifier ON reference (system, value);
+CREATE INDEX reference_id ON reference (id);
+
+-- References are the elements of a field's list shaped as `{ ty, value: { system, value } }`
+INSERT INTO reference (ty, id, field, reference_ty, system, value)
+SELECT p.ty, p.id, f.key, r ->> 'ty', r -> 'value' ->> 'system', r -> 'value' ->> 'value'
+FROM projection p, jsonb_each(p.body) AS f, jsonb_array_elements(
+  CASE WHEN jsonb_typeof(f.value -> 'end') = 'array' THEN f.value -> 'end' ELSE '[]' END
+) AS r
+WHERE jsonb_typeof(r -> 'ty') = 'string'
+AND jsonb_typeof(r -> 'value' -> 'system') = 'string';
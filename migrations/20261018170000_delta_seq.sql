@@ -0,0 +1,18 @@
+-- Order each entity's deltas by when they were appended. `created_at` is when the appending
+-- transaction began, and transactions waiting on each other's locks can commit in the
+-- opposite order to the one they began in.
+ALTER TABLE delta ADD COLUMN seq BIGINT;
+
+UPDATE delta SET seq = ordered.seq
+FROM (SELECT ctid, row_number() OVER (ORDER BY created_at) AS seq FROM delta) ordered
+WHERE delta.ctid = ordered.ctid;
+
+CREATE SEQUENCE delta_seq_seq OWNED BY delta.seq;
+SELECT setval('delta_seq_seq', COALESCE(MAX(seq), 0) + 1, false) FROM delta;
+
+ALTER TABLE delta
+  ALTER COLUMN seq SET DEFAULT nextval('delta_seq_seq'),
+  ALTER COLUMN seq SET NOT NULL;
+
+DROP INDEX IF EXISTS delta_id;
+CREATE INDEX delta_id ON delta (id, seq);
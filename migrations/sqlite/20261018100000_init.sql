@@ -0,0 +1,25 @@
+-- SQLite schema, applied by `store::sqlite::Sqlite::connect`
+-- Bodies are JSON text, timestamps are UTC text so they compare in order
+CREATE TABLE delta (
+  id TEXT NOT NULL,
+  ty TEXT NOT NULL,
+  body TEXT NOT NULL,
+  author TEXT NOT NULL,
+  created_at TEXT NOT NULL
+);
+CREATE INDEX delta_id ON delta (id);
+CREATE TABLE projection (
+  id TEXT PRIMARY KEY,
+  ty TEXT NOT NULL,
+  body TEXT NOT NULL,
+  last_updated TEXT NOT NULL
+);
+CREATE TABLE snapshot (
+  id TEXT NOT NULL,
+  ty TEXT NOT NULL,
+  body TEXT NOT NULL,
+  version INTEGER NOT NULL,
+  delta_created_at TEXT NOT NULL,
+  created_at TEXT NOT NULL,
+  PRIMARY KEY (id, version)
+);
@@ -0,0 +1,2 @@
+-- Text of the `#[searchable(text)]` fields of each projection, searched word by word
+ALTER TABLE projection ADD COLUMN document TEXT;
@@ -0,0 +1,4 @@
+-- Every read filters on the type, and searches are ordered by when projections were created
+-- unless told otherwise
+CREATE INDEX IF NOT EXISTS projection_ty ON projection (ty, last_updated, id);
+CREATE INDEX IF NOT EXISTS delta_ty ON delta (ty);
@@ -0,0 +1,5 @@
+-- Indexes declared by the model, written by `indexes migrate`
+CREATE INDEX IF NOT EXISTS projection_account_email ON projection (ty, json_extract(body, '$.email.end'));
+CREATE INDEX IF NOT EXISTS projection_organization_name ON projection (ty, json_extract(body, '$.name.end'));
+CREATE INDEX IF NOT EXISTS projection_organization_established ON projection (ty, julianday(json_extract(body, '$.established.end')));
+CREATE INDEX IF NOT EXISTS projection_transaction_amount ON projection (ty, json_extract(body, '$.amount.end'));
@@ -0,0 +1,14 @@
+-- Every identifier of every entity, each naming at most one entity of its type
+CREATE TABLE identifier (
+  ty TEXT NOT NULL,
+  system TEXT NOT NULL,
+  value TEXT NOT NULL,
+  id TEXT NOT NULL,
+  PRIMARY KEY (ty, system, value)
+);
+CREATE INDEX identifier_id ON identifier (id);
+
+INSERT OR IGNORE INTO identifier (ty, system, value, id)
+SELECT p.ty, json_extract(i.value, '$.system'), json_extract(i.value, '$.value'), p.id
+FROM projection p, json_each(p.body, '$.identifier.end') AS i
+WHERE json_type(p.body, '$.identifier.end') = 'array';
@@ -0,0 +1,7 @@
+-- Claim the emails of existing accounts. Where several share one, only one of them
+-- keeps it, and the others are refused updates until their email changes.
+INSERT OR IGNORE INTO identifier (ty, system, value, id)
+SELECT p.ty, 'email', json_extract(p.body, '$.email.end'), p.id
+FROM projection p
+WHERE p.ty = 'Account'
+AND json_type(p.body, '$.email.end') = 'text';
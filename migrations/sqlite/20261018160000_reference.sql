@@ -0,0 +1,21 @@
+-- Every reference held by a projection, so an entity can find those that refer to it
+CREATE TABLE reference (
+  ty TEXT NOT NULL,
+  id TEXT NOT NULL,
+  field TEXT NOT NULL,
+  reference_ty TEXT NOT NULL,
+  system TEXT NOT NULL,
+  value TEXT NOT NULL
+);
+CREATE INDEX reference_identifier ON reference (system, value);
+CREATE INDEX reference_id ON reference (id);
+
+-- References are the elements of a field's list shaped as `{ ty, value: { system, value } }`
+INSERT INTO reference (ty, id, field, reference_ty, system, value)
+SELECT p.ty, p.id, f.key, json_extract(p.body, r.fullkey || '.ty'),
+  json_extract(p.body, r.fullkey || '.value.system'),
+  json_extract(p.body, r.fullkey || '.value.value')
+FROM projection p, json_each(p.body) AS f, json_each(p.body, f.fullkey || '.end') AS r
+WHERE json_type(p.body, f.fullkey || '.end') = 'array'
+AND json_type(p.body, r.fullkey || '.ty') = 'text'
+AND json_type(p.body, r.fullkey || '.value.system') = 'text';
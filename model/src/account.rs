@@ -7,20 +7,30 @@ use serde::{Deserialize, Serialize};
     mutate = ["admin", "service", "self"],
     query = ["admin", "service", "user"]
 )]
-struct Account {
+#[snapshot(every = 50)]
+#[referrers(authorize = crate::node::authorize_query)]
+pub(crate) struct Account {
     #[construct]
     identifier: Vec<atoms::Identifier>,
     #[searchable]
+    #[searchable(fuzzy)]
+    #[unique]
+    #[personal_data]
     email: Option<String>,
     password: Option<String>,
+    #[personal_data]
     first_name: Option<String>,
+    #[personal_data]
     last_name: Option<String>,
     interests: Vec<Tag>,
     #[construct]
+    #[relation(transaction::Transaction)]
     transactions: Vec<Reference>,
     #[construct]
     payment_method: Vec<Reference>,
     #[construct]
+    #[searchable]
+    #[personal_data]
     address: Vec<Address>,
 }
 
@@ -39,3 +49,6 @@ pub struct AcctQuery(AccountQuery);
 
 #[derive(Default, MergedObject)]
 pub struct AcctMutate(AccountMutate);
+
+#[derive(Default, MergedSubscription)]
+pub struct AcctSubscription(AccountSubscription);
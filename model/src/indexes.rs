@@ -0,0 +1,13 @@
+use atoms::search::Index;
+
+use super::*;
+
+/// Every index the searches of the model rely on
+pub fn declared() -> Vec<Index> {
+    [
+        account::AccountStore::indexes(),
+        organization::OrganizationStore::indexes(),
+        transaction::TransactionStore::indexes(),
+    ]
+    .concat()
+}
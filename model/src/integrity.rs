@@ -0,0 +1,13 @@
+use atoms::delta::Dangling;
+
+use super::*;
+
+/// Every reference a projection holds to an entity that doesn't exist, across the model
+pub async fn dangling(db: &dyn store::Backend) -> sqlx::Result<Vec<Dangling>> {
+    Ok([
+        store::Driver::scan::<account::AccountStore>(db).await?,
+        store::Driver::scan::<organization::OrganizationStore>(db).await?,
+        store::Driver::scan::<transaction::TransactionStore>(db).await?,
+    ]
+    .concat())
+}
@@ -1,22 +1,27 @@
 #![allow(unused_variables)]
 use async_graphql::{
     connection::{Connection, Edge, EmptyFields},
-    Context, MergedObject, Object, Result,
+    Context, MergedObject, MergedSubscription, Object, Result,
 };
 use atoms::{
-    delta::{check_delta, Del, Delta, Rollup, Store},
+    delta::{check_delta, Del, Delta, DeltaConflict, Store},
     pagination::PaginationOption,
-    search::{Search, SqlQuery},
+    search::{Condition, Search},
     *,
 };
 use derive::{Api, Support};
 
 mod account;
+pub mod indexes;
+pub mod integrity;
+mod node;
 mod organization;
+pub mod replay;
 mod transaction;
 
 #[derive(Default, MergedObject)]
 pub struct Query(
+    node::NodeQuery,
     account::AcctQuery,
     organization::OrgQuery,
     transaction::TxnQuery,
@@ -28,3 +33,13 @@ pub struct Mutate(
     organization::OrgMutate,
     transaction::TxnMutate,
 );
+
+#[derive(Default, MergedSubscription)]
+pub struct Subscription(
+    account::AcctSubscription,
+    organization::OrgSubscription,
+    transaction::TxnSubscription,
+);
+
+#[cfg(test)]
+mod test;
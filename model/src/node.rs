@@ -0,0 +1,56 @@
+use async_graphql::{Interface, ID};
+use atoms::node::GlobalId;
+
+use super::*;
+
+/// Any entity, found by the global id every type gives as its `id`
+#[derive(Interface)]
+#[graphql(field(name = "id", type = "ID"))]
+pub(crate) enum Node {
+    Account(account::Account),
+    Organization(organization::Organization),
+    Transaction(transaction::Transaction),
+}
+
+#[derive(Default)]
+pub struct NodeQuery;
+
+#[Object]
+impl NodeQuery {
+    /// The entity with a global id, or null if there is none
+    async fn node(&self, ctx: &Context<'_>, id: ID) -> Result<Option<Node>> {
+        load(ctx, id).await
+    }
+
+    /// The entities with each global id, in order, with null for any there is none with
+    async fn nodes(&self, ctx: &Context<'_>, ids: Vec<ID>) -> Result<Vec<Option<Node>>> {
+        // Loaded together, so the projection loader reads them in a batch per type
+        futures::future::try_join_all(ids.into_iter().map(|id| load(ctx, id))).await
+    }
+}
+
+/// Whether an identity may read the entities of a type, by its name, which `referencedBy`
+/// checks each referrer with
+pub(crate) fn authorize_query(ty: &str, identity: &auth::Identity) -> Result<()> {
+    match ty {
+        "Account" => account::Account::authorize_query(identity),
+        "Organization" => organization::Organization::authorize_query(identity),
+        "Transaction" => transaction::Transaction::authorize_query(identity),
+        _ => Err(format!("No type is named {}", ty).into()),
+    }
+}
+
+async fn load(ctx: &Context<'_>, id: ID) -> Result<Option<Node>> {
+    let GlobalId { ty, id } = GlobalId::decode(&id)?;
+
+    Ok(match ty.as_str() {
+        "Account" => account::Account::node(ctx, id).await?.map(Node::Account),
+        "Organization" => organization::Organization::node(ctx, id)
+            .await?
+            .map(Node::Organization),
+        "Transaction" => transaction::Transaction::node(ctx, id)
+            .await?
+            .map(Node::Transaction),
+        _ => None,
+    })
+}
@@ -6,17 +6,27 @@ use sqlx::types::chrono::{DateTime, Utc};
     mutate = ["admin", "self"],
     query = ["admin", "organization", "user", "service"]
 )]
-struct Organization {
+#[snapshot(every = 100)]
+#[searchable(language = "english")]
+#[referrers(authorize = crate::node::authorize_query)]
+pub(crate) struct Organization {
     #[construct]
     identifier: Vec<atoms::Identifier>,
     #[searchable]
+    #[searchable(text)]
+    #[searchable(fuzzy)]
     name: Option<String>,
+    #[searchable(text)]
     mission: Option<String>,
+    #[searchable(text)]
     description: Option<String>,
+    #[sortable]
     established: Option<DateTime<Utc>>,
+    #[searchable]
     tag: Vec<Tag>,
     ceo: Option<String>,
     #[construct]
+    #[relation(Organization, integrity = warn)]
     managing_entity: Vec<atoms::Reference>,
 }
 
@@ -25,3 +35,6 @@ pub struct OrgQuery(OrganizationQuery);
 
 #[derive(Default, MergedObject)]
 pub struct OrgMutate(OrganizationMutate);
+
+#[derive(Default, MergedSubscription)]
+pub struct OrgSubscription(OrganizationSubscription);
@@ -0,0 +1,60 @@
+use store::replay::{Progress, Replay, ReplayReport};
+
+use super::*;
+
+/// Rebuild projections from the delta log.
+///
+/// `ty` limits the replay to one entity type and `id` to a single entity.
+/// With `dry_run` divergences are reported without rewriting any projection.
+pub async fn projections<F>(
+    db: &dyn store::Backend,
+    ty: Option<&str>,
+    id: Option<&str>,
+    dry_run: bool,
+    mut progress: F,
+) -> sqlx::Result<ReplayReport>
+where
+    F: FnMut(&Progress),
+{
+    let selected = |name: String| ty.is_none_or(|ty| ty == name);
+
+    let mut report = ReplayReport::default();
+
+    if selected(account::AccountStore::ty()) {
+        report.merge(
+            Replay::ty::<account::Account, account::AccountStore, _>(
+                db,
+                id,
+                dry_run,
+                &mut progress,
+            )
+            .await?,
+        );
+    }
+
+    if selected(organization::OrganizationStore::ty()) {
+        report.merge(
+            Replay::ty::<organization::Organization, organization::OrganizationStore, _>(
+                db,
+                id,
+                dry_run,
+                &mut progress,
+            )
+            .await?,
+        );
+    }
+
+    if selected(transaction::TransactionStore::ty()) {
+        report.merge(
+            Replay::ty::<transaction::Transaction, transaction::TransactionStore, _>(
+                db,
+                id,
+                dry_run,
+                &mut progress,
+            )
+            .await?,
+        );
+    }
+
+    Ok(report)
+}
@@ -0,0 +1,47 @@
+use async_graphql::{Request, Response, Schema};
+
+use crate::{Mutate, Query, Subscription};
+
+mod mutate;
+
+fn schema(db: store::Database) -> Schema<Query, Mutate, Subscription> {
+    Schema::build(Default::default(), Default::default(), Default::default())
+        .data(store::Projections::loader(db.clone()))
+        .data(db)
+        .finish()
+}
+
+fn admin() -> auth::Identity {
+    auth::Identity {
+        user_id: "tester".into(),
+        roles: vec![auth::Role::Admin],
+    }
+}
+
+async fn execute(schema: &Schema<Query, Mutate, Subscription>, query: &str) -> Response {
+    schema.execute(Request::new(query).data(admin())).await
+}
+
+/// The code a refused request was given, if it was refused
+fn error_code(response: &Response) -> Option<String> {
+    let extensions = async_graphql::to_value(&response.errors.first()?.extensions).ok()?;
+
+    match extensions {
+        async_graphql::Value::Object(extensions) => match extensions.get("code")? {
+            async_graphql::Value::String(code) => Some(code.clone()),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// The string a request's data holds under `field`
+fn data_str(response: &Response, field: &str) -> Option<String> {
+    match &response.data {
+        async_graphql::Value::Object(data) => match data.get(field)? {
+            async_graphql::Value::String(value) => Some(value.clone()),
+            _ => None,
+        },
+        _ => None,
+    }
+}
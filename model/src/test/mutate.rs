@@ -0,0 +1,68 @@
+use futures::executor::block_on;
+
+use super::{data_str, error_code, execute, schema};
+
+#[test]
+fn conflicting_updates_write_no_delta() {
+    block_on(async {
+        let schema = schema(store::Database::new(store::memory::Memory::new()));
+
+        let created = execute(
+            &schema,
+            r#"mutation { newAccount(email: "leia@yoda.dev", interests: []) }"#,
+        )
+        .await;
+        let id = data_str(&created, "newAccount").unwrap();
+
+        let updated = execute(
+            &schema,
+            &format!(
+                r#"mutation {{
+                    updateAccount(id: "{}", email: {{ start: "v1:stale", end: "luke@yoda.dev" }}) {{
+                        email {{ value }}
+                    }}
+                }}"#,
+                id
+            ),
+        )
+        .await;
+        assert_eq!(error_code(&updated).as_deref(), Some("CONFLICT"));
+
+        let history = execute(
+            &schema,
+            &format!(
+                r#"{{ accountHistory(id: "{}") {{ edges {{ node {{ version }} }} }} }}"#,
+                id
+            ),
+        )
+        .await;
+        let history = async_graphql::to_value(&history.data).unwrap().to_string();
+        assert_eq!(history.matches("version").count(), 1);
+    })
+}
+
+#[test]
+fn conflicting_upserts_create_nothing() {
+    block_on(async {
+        let schema = schema(store::Database::new(store::memory::Memory::new()));
+
+        let upserted = execute(
+            &schema,
+            r#"mutation {
+                upsertAccount(
+                    identifier: { value: "cus_1", system: STRIPE, tier: PRIMARY },
+                    interests: { start: "v1:stale", end: [EDUCATION] }
+                ) { email { value } }
+            }"#,
+        )
+        .await;
+        assert_eq!(error_code(&upserted).as_deref(), Some("CONFLICT"));
+
+        let found = execute(
+            &schema,
+            r#"{ findAccountByIdentifier(system: STRIPE, value: "cus_1") { email { value } } }"#,
+        )
+        .await;
+        assert!(!found.errors.is_empty());
+    })
+}
@@ -5,7 +5,8 @@ use super::*;
     mutate = ["admin", "service"],
     query = ["admin", "service", "user"]
 )]
-struct Transaction {
+#[referrers(authorize = crate::node::authorize_query)]
+pub(crate) struct Transaction {
     #[construct]
     identifier: Vec<atoms::Identifier>,
     #[searchable]
@@ -19,3 +20,6 @@ pub struct TxnQuery(TransactionQuery);
 
 #[derive(Default, MergedObject)]
 pub struct TxnMutate(TransactionMutate);
+
+#[derive(Default, MergedSubscription)]
+pub struct TxnSubscription(TransactionSubscription);
@@ -1,4 +1,3 @@
-use async_graphql::EmptySubscription;
-use model::{Mutate, Query};
+use model::{Mutate, Query, Subscription};
 
-pub type YodaSchema = async_graphql::Schema<Query, Mutate, EmptySubscription>;
+pub type YodaSchema = async_graphql::Schema<Query, Mutate, Subscription>;
@@ -0,0 +1,110 @@
+use std::path::Path;
+
+use sqlx::types::chrono::Utc;
+use yoda::Config;
+
+/// Keep the database indexes the searches of the model rely on
+///
+/// Usage: indexes migrate | indexes check
+///
+/// `migrate` writes a migration creating each declared index no migration creates yet,
+/// in `migrations/` for Postgres and `migrations/sqlite/` for SQLite.
+/// `check` fails when the database is missing a declared index.
+#[actix_web::main]
+async fn main() -> std::io::Result<()> {
+    dotenv::dotenv().ok();
+
+    let declared = model::indexes::declared();
+
+    match std::env::args().nth(1).as_deref() {
+        Some("migrate") => {
+            let version = Utc::now().format("%Y%m%d%H%M%S").to_string();
+
+            migrate(
+                Path::new("migrations"),
+                &version,
+                declared
+                    .iter()
+                    .map(|index| (index.name(), store::sql::index_sql(index)))
+                    .collect(),
+            )?;
+            migrate(
+                Path::new("migrations/sqlite"),
+                &version,
+                declared
+                    .iter()
+                    .filter_map(|index| Some((index.name(), store::sqlite::index_sql(index)?)))
+                    .collect(),
+            )?;
+        }
+        Some("check") => {
+            let config = Config::new()?;
+            let db = config.connect_db().await?;
+
+            let missing = db
+                .missing_indexes(&declared)
+                .await
+                .map_err(std::io::Error::other)?;
+
+            for index in &missing {
+                println!(
+                    "{} on {}.{} is missing",
+                    index.name(),
+                    index.ty,
+                    index.field
+                );
+            }
+
+            if !missing.is_empty() {
+                println!("Run `indexes migrate` and apply the migration it writes");
+                std::process::exit(1);
+            }
+
+            println!("All {} declared indexes exist", declared.len());
+        }
+        _ => {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "Usage: indexes migrate | indexes check",
+            ))
+        }
+    }
+
+    Ok(())
+}
+
+/// Write `<version>_search_indexes.sql` to `dir`, with the statements creating each index
+/// that no migration in `dir` creates yet
+fn migrate(dir: &Path, version: &str, statements: Vec<(String, String)>) -> std::io::Result<()> {
+    let mut existing = String::new();
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.extension().is_some_and(|ext| ext == "sql") {
+            existing.push_str(&std::fs::read_to_string(&path)?);
+        }
+    }
+
+    let missing: Vec<_> = statements
+        .into_iter()
+        .filter(|(name, _)| !existing.contains(&format!(" {} ON ", name)))
+        .map(|(_, statement)| statement)
+        .collect();
+
+    if missing.is_empty() {
+        println!("{} already creates every index", dir.display());
+        return Ok(());
+    }
+
+    let path = dir.join(format!("{}_search_indexes.sql", version));
+    std::fs::write(
+        &path,
+        format!(
+            "-- Indexes declared by the model, written by `indexes migrate`\n{}\n",
+            missing.join("\n")
+        ),
+    )?;
+
+    println!("Wrote {} indexes to {}", missing.len(), path.display());
+
+    Ok(())
+}
@@ -0,0 +1,31 @@
+use yoda::Config;
+
+/// List the references in projections that point at no entity
+///
+/// Usage: integrity
+///
+/// Exits with an error when any reference dangles, whatever the integrity of its field.
+#[actix_web::main]
+async fn main() -> std::io::Result<()> {
+    dotenv::dotenv().ok();
+
+    let config = Config::new()?;
+    let db = config.connect_db().await?;
+
+    let dangling = model::integrity::dangling(&*db)
+        .await
+        .map_err(std::io::Error::other)?;
+
+    for dangling in &dangling {
+        println!("{}", dangling);
+    }
+
+    if !dangling.is_empty() {
+        println!("{} dangling references", dangling.len());
+        std::process::exit(1);
+    }
+
+    println!("No dangling references");
+
+    Ok(())
+}
@@ -0,0 +1,59 @@
+use yoda::Config;
+
+/// Rebuild projections from the delta log
+///
+/// Usage: replay [--dry-run] [<ty> [<id>]]
+#[actix_web::main]
+async fn main() -> std::io::Result<()> {
+    dotenv::dotenv().ok();
+
+    let (flags, args): (Vec<_>, Vec<_>) = std::env::args()
+        .skip(1)
+        .partition(|arg| arg.starts_with("--"));
+
+    let dry_run = flags.iter().any(|flag| flag == "--dry-run");
+
+    let config = Config::new()?;
+    let db = config.connect_db().await?;
+
+    let report = model::replay::projections(
+        &*db,
+        args.first().map(String::as_str),
+        args.get(1).map(String::as_str),
+        dry_run,
+        |progress| println!("{}: {}/{}", progress.ty, progress.done, progress.total),
+    )
+    .await
+    .map_err(std::io::Error::other)?;
+
+    for divergence in &report.divergences {
+        println!(
+            "{}/{} diverged\n  projected: {}\n  replayed:  {}",
+            divergence.ty,
+            divergence.id,
+            divergence
+                .projected
+                .as_ref()
+                .map(|body| body.to_string())
+                .unwrap_or_else(|| "<missing>".into()),
+            divergence
+                .replayed
+                .as_ref()
+                .map(|body| body.to_string())
+                .unwrap_or_else(|| "<deleted>".into())
+        );
+    }
+
+    println!(
+        "Replayed {} entities, {} diverged{}",
+        report.replayed,
+        report.divergences.len(),
+        if dry_run {
+            " (dry run, nothing written)"
+        } else {
+            ""
+        }
+    );
+
+    Ok(())
+}
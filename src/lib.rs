@@ -12,17 +12,19 @@ impl Config {
         })
     }
 
-    pub async fn connect_db(&self) -> Result<sqlx::PgPool, std::io::Error> {
-        sqlx::postgres::PgPoolOptions::new()
-            .max_connections(5)
-            .connect(self.db_config.url.as_str())
+    pub async fn connect_db(&self) -> Result<store::Database, std::io::Error> {
+        self.db_config
+            .connect()
             .await
             .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))
     }
 
     pub async fn gen_schema(&self) -> YodaSchema {
+        let db = self.connect_db().await.unwrap();
+
         YodaSchema::build(Default::default(), Default::default(), Default::default())
-            .data(self.connect_db().await.unwrap())
+            .data(store::Projections::loader(db.clone()))
+            .data(db)
             .finish()
     }
 }
@@ -3,8 +3,11 @@ use actix_web::{
     guard, middleware::Logger, web, App, HttpRequest, HttpResponse, HttpServer, Result,
 };
 use actix_web_httpauth::middleware::HttpAuthentication;
-use async_graphql::http::{playground_source, GraphQLPlaygroundConfig};
-use async_graphql_actix_web::{Request, Response};
+use async_graphql::{
+    http::{playground_source, GraphQLPlaygroundConfig},
+    Data,
+};
+use async_graphql_actix_web::{Request, Response, WSSubscription};
 use schema::YodaSchema;
 use yoda::Config;
 
@@ -16,6 +19,33 @@ async fn index(schema: web::Data<YodaSchema>, http_req: HttpRequest, req: Reques
     schema.execute(req).await.into()
 }
 
+/// Subscriptions over a websocket, authenticated by the connection's init payload
+async fn index_ws(
+    schema: web::Data<YodaSchema>,
+    req: HttpRequest,
+    payload: web::Payload,
+) -> Result<HttpResponse> {
+    WSSubscription::start_with_initializer(YodaSchema::clone(&*schema), &req, payload, identify)
+}
+
+/// Browsers can't set headers on a websocket, so clients send the bearer token as
+/// `{ "Authorization": "Bearer <token>" }` when connecting instead
+async fn identify(payload: serde_json::Value) -> async_graphql::Result<Data> {
+    let token = payload["Authorization"]
+        .as_str()
+        .and_then(|authorization| authorization.strip_prefix("Bearer "))
+        .ok_or_else(|| async_graphql::Error::new("Missing bearer token"))?;
+
+    let identity = auth::Identity::from_token(token)
+        .await
+        .map_err(|e| async_graphql::Error::new(e.to_string()))?;
+
+    let mut data = Data::default();
+    data.insert(identity);
+
+    Ok(data)
+}
+
 async fn index_playground() -> Result<HttpResponse> {
     Ok(HttpResponse::Ok()
         .content_type("text/html; charset=utf-8")
@@ -28,7 +58,7 @@ async fn index_playground() -> Result<HttpResponse> {
 async fn main() -> std::io::Result<()> {
     dotenv::dotenv().ok();
 
-    std::env::set_var("RUST_LOG", "actix_web=info");
+    std::env::set_var("RUST_LOG", "actix_web=info,store=warn");
     env_logger::init();
 
     let config = Config::new()?;
@@ -49,6 +79,12 @@ async fn main() -> std::io::Result<()> {
                     .guard(guard::Post())
                     .to(index),
             )
+            .service(
+                web::resource("/")
+                    .guard(guard::Get())
+                    .guard(guard::Header("upgrade", "websocket"))
+                    .to(index_ws),
+            )
             .service(web::resource("/").guard(guard::Get()).to(index_playground))
     })
     .bind("127.0.0.1:8000")?
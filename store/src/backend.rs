@@ -0,0 +1,312 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use atoms::{
+    delta::{Claim, Duplicate},
+    history::DeltaRecord,
+    pagination::{Key, Page},
+    search::{Criteria, Document, Index},
+    Identifier, ReferenceType, Referral, Referrer,
+};
+use futures::{
+    channel::mpsc::{self, UnboundedSender},
+    stream::BoxStream,
+    StreamExt,
+};
+use sqlx::types::chrono::{DateTime, Utc};
+
+/// Storage for the delta log, projections and snapshots.
+///
+/// Bodies are passed as JSON; [`crate::Driver`] converts them to and from the derived types.
+#[async_trait]
+pub trait Backend: Send + Sync {
+    async fn begin(&self) -> sqlx::Result<Box<dyn Transaction + '_>>;
+
+    /// Deltas for an entity, oldest first
+    async fn deltas(
+        &self,
+        ty: &str,
+        id: &str,
+        range: DeltaRange,
+    ) -> sqlx::Result<Vec<DeltaRecord<serde_json::Value>>>;
+
+    /// The snapshot covering the most deltas within `as_of` and `version`, with the number it covers
+    async fn latest_snapshot(
+        &self,
+        ty: &str,
+        id: &str,
+        as_of: Option<DateTime<Utc>>,
+        version: Option<usize>,
+    ) -> sqlx::Result<Option<(serde_json::Value, usize)>>;
+
+    /// Every id of a type with at least one delta, or just `id` when given
+    async fn ids(&self, ty: &str, id: Option<&str>) -> sqlx::Result<Vec<String>>;
+
+    /// Projections of a type matching the criteria, within the page and in its order
+    async fn search(&self, ty: &str, criteria: &Criteria, page: &Page) -> sqlx::Result<Vec<Found>>;
+
+    /// How many projections of a type match the criteria
+    async fn count(&self, ty: &str, criteria: &Criteria) -> sqlx::Result<usize>;
+
+    /// The projections of the entities of a type among `ids`, in no particular order
+    async fn projections(
+        &self,
+        ty: &str,
+        ids: &[String],
+    ) -> sqlx::Result<Vec<(String, serde_json::Value)>>;
+
+    /// The id of the entity of a type with an identifier
+    async fn find_by_identifier(
+        &self,
+        ty: &str,
+        system: &str,
+        value: &str,
+    ) -> sqlx::Result<Option<String>>;
+
+    /// The ids of the entities of a type with any of `identifiers`, as
+    /// `(system, value, id)` for each identifier found
+    async fn find_by_identifiers(
+        &self,
+        ty: &str,
+        identifiers: &[Identifier],
+    ) -> sqlx::Result<Vec<(String, String, String)>>;
+
+    /// The entities holding a reference to any of `identifiers`, only through references
+    /// of `reference_type` when given
+    async fn referrers(
+        &self,
+        identifiers: &[Identifier],
+        reference_type: Option<ReferenceType>,
+    ) -> sqlx::Result<Vec<Referrer>>;
+
+    /// The indexes among `declared` that the schema lacks, leaving out any the backend
+    /// has no use for
+    async fn missing_indexes(&self, declared: &[Index]) -> sqlx::Result<Vec<Index>>;
+
+    /// Every change committed from now on, by any writer sharing the storage,
+    /// in the order each entity's deltas were appended
+    async fn changes(&self) -> sqlx::Result<BoxStream<'static, Changed>>;
+}
+
+/// Writes that are only visible once committed
+#[async_trait]
+pub trait Transaction: Send {
+    /// The projection body, locked against other transactions until this one ends
+    async fn projection(&mut self, ty: &str, id: &str) -> sqlx::Result<Option<serde_json::Value>>;
+
+    /// Hold an entity against other transactions locking it until this one ends, whether or not
+    /// it is projected.
+    ///
+    /// Taken before the projection by anything changing the entity's lifecycle.
+    async fn lock(&mut self, ty: &str, id: &str) -> sqlx::Result<()>;
+
+    /// Every delta of an entity, oldest first, including those appended in the transaction
+    async fn deltas(
+        &mut self,
+        ty: &str,
+        id: &str,
+    ) -> sqlx::Result<Vec<DeltaRecord<serde_json::Value>>>;
+
+    /// Append a delta, announcing it as [`Changed`] once the transaction commits
+    async fn append(
+        &mut self,
+        ty: &str,
+        id: &str,
+        body: serde_json::Value,
+        author: &str,
+    ) -> sqlx::Result<()>;
+
+    /// Replace the projection body, and the document indexed for full-text search
+    async fn project(
+        &mut self,
+        ty: &str,
+        id: &str,
+        body: serde_json::Value,
+        document: Option<&Document>,
+    ) -> sqlx::Result<()>;
+
+    /// Remove the entity's projection, hiding it from searches, and the references it holds
+    async fn unproject(&mut self, ty: &str, id: &str) -> sqlx::Result<()>;
+
+    /// Erase an entity: remove its projection, snapshots and claims, and redact the body
+    /// of each of its deltas to just its `lifecycle` change, if any
+    async fn purge(&mut self, ty: &str, id: &str) -> sqlx::Result<()>;
+
+    /// Replace each of `fields` with `marker` wherever one of the entity's bodies sets it:
+    /// in its deltas, snapshots and projection
+    async fn redact(
+        &mut self,
+        ty: &str,
+        id: &str,
+        fields: &[String],
+        marker: &serde_json::Value,
+    ) -> sqlx::Result<()>;
+
+    /// The entity claiming a value, including claims staged in the transaction
+    async fn claimant(&mut self, ty: &str, claim: &Claim) -> sqlx::Result<Option<String>>;
+
+    /// Replace the values an entity claims.
+    ///
+    /// Returns the first value already claimed by another entity of the type,
+    /// after which the transaction must not be committed.
+    async fn claim(
+        &mut self,
+        ty: &str,
+        id: &str,
+        claims: &[Claim],
+    ) -> sqlx::Result<Option<Duplicate>>;
+
+    /// Replace the references indexed for an entity, see [`Backend::referrers`]
+    async fn refer(&mut self, ty: &str, id: &str, referrals: &[Referral]) -> sqlx::Result<()>;
+
+    /// Store `body` as a snapshot when the entity's delta count is a multiple of `every`
+    async fn snapshot(
+        &mut self,
+        ty: &str,
+        id: &str,
+        every: u64,
+        body: serde_json::Value,
+    ) -> sqlx::Result<()>;
+
+    async fn commit(self: Box<Self>) -> sqlx::Result<()>;
+}
+
+/// A search result
+#[derive(Clone, Debug, PartialEq)]
+pub struct Found<T = serde_json::Value> {
+    /// Where the result falls in the order searched
+    pub key: Key,
+    pub body: T,
+    /// The document with the words of a text query highlighted
+    pub snippet: Option<String>,
+    /// The similarity to a fuzzy query
+    pub score: Option<f64>,
+}
+
+/// A delta committed to an entity
+#[derive(Clone, Debug, PartialEq)]
+pub struct Changed {
+    pub ty: String,
+    pub id: String,
+    /// The entity's delta count with this one, which is the last of them
+    pub version: usize,
+}
+
+/// Which deltas of an entity to read
+#[derive(Default, Clone, Copy, Debug)]
+pub struct DeltaRange {
+    /// Only deltas created at or before this time
+    pub as_of: Option<DateTime<Utc>>,
+    /// Only the first `version` deltas
+    pub version: Option<usize>,
+    /// Leave out the first `skip` deltas
+    pub skip: usize,
+}
+
+/// SQL keeping the rows strictly after (or before) a key, for a sort on `current`
+/// that puts missing values last.
+///
+/// `value` and `id` are the parameters holding the key, `value` being `None` when the key
+/// has no value to sort by.
+pub(crate) fn keyset_sql(
+    current: &str,
+    ascending: bool,
+    after: bool,
+    value: Option<&str>,
+    id: &str,
+) -> String {
+    let op = if ascending == after { ">" } else { "<" };
+
+    match (value, after) {
+        (Some(value), true) => format!(
+            "({0} {1} {2} OR ({0} = {2} AND id {1} {3}) OR {0} IS NULL)",
+            current, op, value, id
+        ),
+        (Some(value), false) => format!(
+            "({0} {1} {2} OR ({0} = {2} AND id {1} {3}))",
+            current, op, value, id
+        ),
+        (None, true) => format!("({} IS NULL AND id {} {})", current, op, id),
+        (None, false) => format!("({} IS NOT NULL OR id {} {})", current, op, id),
+    }
+}
+
+/// The results within a page, in its order, for backends that sort them in memory
+pub(crate) fn page_of(mut found: Vec<Found>, page: &Page) -> Vec<Found> {
+    found.retain(|found| page.contains(&found.key));
+    found.sort_by(|a, b| page.sort.compare(&a.key, &b.key));
+
+    let skip = if page.backward {
+        found.len().saturating_sub(page.limit)
+    } else {
+        0
+    };
+
+    found.into_iter().skip(skip).take(page.limit).collect()
+}
+
+/// A referrer read back from the SQL backends, which store its reference type as text
+pub(crate) fn referrer(
+    ty: String,
+    id: String,
+    field: String,
+    reference_type: String,
+) -> sqlx::Result<Referrer> {
+    let reference_type = serde_json::from_value(serde_json::Value::String(reference_type))
+        .map_err(|e| sqlx::Error::Decode(Box::new(e)))?;
+
+    Ok(Referrer {
+        ty,
+        id,
+        field,
+        reference_type,
+    })
+}
+
+/// The subscribers to the changes of a backend whose writers all share this process
+#[derive(Default)]
+pub(crate) struct Feed(std::sync::Mutex<Vec<UnboundedSender<Changed>>>);
+
+impl Feed {
+    pub(crate) fn subscribe(&self) -> BoxStream<'static, Changed> {
+        let (sender, receiver) = mpsc::unbounded();
+        self.0
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .push(sender);
+
+        receiver.boxed()
+    }
+
+    /// Hand committed changes to every subscriber, forgetting those that have gone
+    pub(crate) fn publish(&self, changes: Vec<Changed>) {
+        let mut senders = self.0.lock().unwrap_or_else(|e| e.into_inner());
+
+        for changed in changes {
+            senders.retain(|sender| sender.unbounded_send(changed.clone()).is_ok());
+        }
+    }
+
+    /// End every subscriber's stream, such as when the changes stop coming
+    pub(crate) fn close(&self) {
+        self.0.lock().unwrap_or_else(|e| e.into_inner()).clear();
+    }
+}
+
+/// The backend shared by every resolver, stored in the schema data
+#[derive(Clone)]
+pub struct Database(Arc<dyn Backend>);
+
+impl Database {
+    pub fn new<B: Backend + 'static>(backend: B) -> Self {
+        Self(Arc::new(backend))
+    }
+}
+
+impl std::ops::Deref for Database {
+    type Target = dyn Backend;
+
+    fn deref(&self) -> &Self::Target {
+        self.0.as_ref()
+    }
+}
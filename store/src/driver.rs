@@ -0,0 +1,896 @@
+use atoms::{
+    delta::{
+        Claim, Dangling, Del, Delta, Duplicate, Integrity, Lifecycle, Relation, Rollup, Store,
+    },
+    history::{DeltaRecord, HistoryEntry},
+    pagination::Page,
+    search::Search,
+    Identifier, IdentifierSystem, Reference, ReferenceType, Referrer,
+};
+use futures::{future, stream::BoxStream, StreamExt};
+use serde::{de::DeserializeOwned, Serialize};
+use sqlx::types::chrono::{DateTime, Utc};
+
+use crate::{
+    backend::{Backend, Changed, DeltaRange, Found, Transaction},
+    Database, ProjectionLoader,
+};
+
+fn encode<S: Serialize>(doc: S) -> sqlx::Result<serde_json::Value> {
+    serde_json::to_value(doc).map_err(|e| sqlx::Error::Protocol(e.to_string()))
+}
+
+/// How many projections [`Driver::scan`] reads at a time
+const SCAN_PAGE: usize = 100;
+
+/// The entity a Yoda identifier names: its value, as long as it is a uuid.
+///
+/// Yoda ids are the uuids the tables key entities on, and anything else can't name one.
+fn yoda_id(identifier: &Identifier) -> Option<String> {
+    Some(identifier.value.clone()).filter(|value| sqlx::types::Uuid::parse_str(value).is_ok())
+}
+
+/// Whether a delta body erases any field, see [`Delta::redacted`]
+fn redacts(body: &serde_json::Value) -> bool {
+    body.as_object()
+        .is_some_and(|fields| fields.values().any(|delta| delta["redacted"] == true))
+}
+
+fn decode<S: DeserializeOwned>(body: serde_json::Value) -> sqlx::Result<S> {
+    serde_json::from_value(body).map_err(|e| sqlx::Error::Decode(Box::new(e)))
+}
+
+/// A delta committed to an entity, with the entity it left
+#[derive(Clone, Debug)]
+pub struct Change<T> {
+    pub id: String,
+    /// Missing once the entity is deleted or purged
+    pub entity: Option<T>,
+    /// The fields the delta changed, at the version it brought the entity to
+    pub delta: HistoryEntry,
+}
+
+/// An entity rolled up to a version, whatever its lifecycle
+struct Rolled<T> {
+    id: String,
+    doc: T,
+    lifecycle: Lifecycle,
+    version: usize,
+}
+
+/// Typed access to a [`Backend`] for the derived stores
+pub struct Driver;
+
+impl Driver {
+    /// Read the current projection, locking it until the transaction ends
+    /// so concurrent updates check their deltas against the same value.
+    pub async fn query_proj<T, S>(
+        transaction: &mut (dyn Transaction + '_),
+        id: &str,
+    ) -> sqlx::Result<T>
+    where
+        S: DeserializeOwned + Serialize + std::fmt::Debug + Store,
+        T: Del<S>,
+    {
+        let body = transaction
+            .projection(&S::ty(), id)
+            .await?
+            .ok_or(sqlx::Error::RowNotFound)?;
+
+        Ok(decode::<S>(body)?.into())
+    }
+
+    /// The raw projection body, if the entity has been projected
+    pub async fn query_proj_body<S>(
+        transaction: &mut (dyn Transaction + '_),
+        id: &str,
+    ) -> sqlx::Result<Option<serde_json::Value>>
+    where
+        S: Store,
+    {
+        transaction.projection(&S::ty(), id).await
+    }
+
+    /// Lock an entity until the transaction ends, see [`Transaction::lock`]
+    pub async fn lock<S>(transaction: &mut (dyn Transaction + '_), id: &str) -> sqlx::Result<()>
+    where
+        S: Store,
+    {
+        transaction.lock(&S::ty(), id).await
+    }
+
+    /// Every delta for an entity as seen within the transaction, such as once it is locked
+    pub async fn query_in<T, S>(
+        transaction: &mut (dyn Transaction + '_),
+        id: &str,
+    ) -> sqlx::Result<Vec<S>>
+    where
+        S: DeserializeOwned + Serialize + std::fmt::Debug,
+        T: Del<S>,
+    {
+        transaction
+            .deltas(&T::ty(), id)
+            .await?
+            .into_iter()
+            .map(|record| decode(record.body))
+            .collect()
+    }
+
+    /// Every id of a type with at least one delta, or just `id` when given
+    pub async fn ids<S>(db: &dyn Backend, id: Option<&str>) -> sqlx::Result<Vec<String>>
+    where
+        S: Store,
+    {
+        db.ids(&S::ty(), id).await
+    }
+
+    pub async fn query<T, S>(db: &dyn Backend, id: &str) -> sqlx::Result<Vec<S>>
+    where
+        S: DeserializeOwned + Serialize + std::fmt::Debug,
+        T: Del<S>,
+    {
+        Self::query_as_of::<T, S>(db, id, None, None, 0).await
+    }
+
+    /// Deltas for an entity up to and including `as_of`, and at most the first `version` of them,
+    /// skipping the first `skip`
+    pub async fn query_as_of<T, S>(
+        db: &dyn Backend,
+        id: &str,
+        as_of: Option<DateTime<Utc>>,
+        version: Option<usize>,
+        skip: usize,
+    ) -> sqlx::Result<Vec<S>>
+    where
+        S: DeserializeOwned + Serialize + std::fmt::Debug,
+        T: Del<S>,
+    {
+        db.deltas(
+            &T::ty(),
+            id,
+            DeltaRange {
+                as_of,
+                version,
+                skip,
+            },
+        )
+        .await?
+        .into_iter()
+        .map(|record| decode(record.body))
+        .collect()
+    }
+
+    /// Roll up an entity as of a time or version, starting from the latest snapshot that covers it.
+    ///
+    /// Fails with [`sqlx::Error::RowNotFound`] when the entity didn't exist yet,
+    /// or was deleted or purged by then.
+    pub async fn rollup<T, S>(
+        db: &dyn Backend,
+        id: &str,
+        as_of: Option<DateTime<Utc>>,
+        version: Option<usize>,
+    ) -> sqlx::Result<T>
+    where
+        S: DeserializeOwned + Serialize + std::fmt::Debug + Store,
+        T: Del<S> + Default,
+    {
+        let (start, covered) = match db.latest_snapshot(&T::ty(), id, as_of, version).await? {
+            Some((body, covered)) => (T::from(decode::<S>(body)?), covered),
+            None => (T::default(), 0),
+        };
+
+        let dels = Self::query_as_of::<T, S>(db, id, as_of, version, covered).await?;
+
+        // Nothing had been written to it, if it exists at all
+        if covered == 0 && dels.is_empty() {
+            return Err(sqlx::Error::RowNotFound);
+        }
+
+        // Snapshots are only taken of active entities, so the deltas after one decide
+        if Lifecycle::of(&dels) != Lifecycle::Active {
+            return Err(sqlx::Error::RowNotFound);
+        }
+
+        Ok(dels.into_iter().rollup_onto(start))
+    }
+
+    /// Roll up an entity over every delta, whatever its lifecycle, such as to restore it.
+    ///
+    /// The entity is locked first, so its lifecycle can't change until the transaction ends.
+    pub async fn rollup_with_lifecycle<T, S>(
+        transaction: &mut (dyn Transaction + '_),
+        id: &str,
+    ) -> sqlx::Result<(T, Lifecycle)>
+    where
+        S: DeserializeOwned + Serialize + std::fmt::Debug + Store,
+        T: Del<S> + Default,
+    {
+        Self::lock::<S>(transaction, id).await?;
+
+        let dels = Self::query_in::<T, S>(transaction, id).await?;
+        let lifecycle = Lifecycle::of(&dels);
+
+        Ok((dels.into_iter().rollup(), lifecycle))
+    }
+
+    /// Store a snapshot of `doc` when the entity's delta count is a multiple of `every`.
+    ///
+    /// `doc` must be the entity rolled up over every delta written so far.
+    pub async fn snapshot<S>(
+        transaction: &mut (dyn Transaction + '_),
+        id: &str,
+        every: u64,
+        doc: S,
+    ) -> sqlx::Result<()>
+    where
+        S: Serialize + Send + Store,
+    {
+        transaction
+            .snapshot(&S::ty(), id, every, encode(doc)?)
+            .await
+    }
+
+    /// The first `limit` deltas for an entity, oldest first, with their author and timestamp
+    pub async fn history<T, S>(
+        db: &dyn Backend,
+        id: &str,
+        limit: usize,
+    ) -> sqlx::Result<Vec<DeltaRecord<S>>>
+    where
+        S: DeserializeOwned + Serialize + std::fmt::Debug,
+        T: Del<S>,
+    {
+        db.deltas(
+            &T::ty(),
+            id,
+            DeltaRange {
+                version: Some(limit),
+                ..Default::default()
+            },
+        )
+        .await?
+        .into_iter()
+        .map(|record| {
+            Ok(DeltaRecord {
+                body: decode(record.body)?,
+                author: record.author,
+                created_at: record.created_at,
+            })
+        })
+        .collect()
+    }
+
+    /// A page of search results, and whether more lie beyond it in the direction it was read
+    pub async fn search<T, S>(
+        db: &dyn Backend,
+        doc: T,
+        page: &Page,
+    ) -> sqlx::Result<(Vec<Found<S>>, bool)>
+    where
+        T: Search<S>,
+        S: DeserializeOwned + Serialize + std::fmt::Debug,
+    {
+        // One result past the page tells whether there are more
+        let mut found = db
+            .search(
+                &T::ty(),
+                &doc.criteria(),
+                &Page {
+                    limit: page.limit + 1,
+                    ..page.clone()
+                },
+            )
+            .await?;
+
+        let more = found.len() > page.limit;
+        if more {
+            if page.backward {
+                found.remove(0);
+            } else {
+                found.pop();
+            }
+        }
+
+        let found = found
+            .into_iter()
+            .map(|found| {
+                Ok(Found {
+                    key: found.key,
+                    body: decode(found.body)?,
+                    snippet: found.snippet,
+                    score: found.score,
+                })
+            })
+            .collect::<sqlx::Result<_>>()?;
+
+        Ok((found, more))
+    }
+
+    /// How many results a search has across every page
+    pub async fn count<T, S>(db: &dyn Backend, doc: &T) -> sqlx::Result<usize>
+    where
+        T: Search<S>,
+    {
+        db.count(&T::ty(), &doc.criteria()).await
+    }
+
+    pub async fn delta<S>(
+        transaction: &mut (dyn Transaction + '_),
+        id: &str,
+        doc: S,
+        author: &auth::Identity,
+    ) -> sqlx::Result<()>
+    where
+        S: Serialize + Send + Store,
+    {
+        transaction
+            .append(&S::ty(), id, encode(doc)?, &author.user_id)
+            .await
+    }
+
+    /// Replace the projection of an entity, with its search document and the references it holds
+    pub async fn project<S>(
+        transaction: &mut (dyn Transaction + '_),
+        id: &str,
+        doc: S,
+    ) -> sqlx::Result<()>
+    where
+        S: Serialize + Send + Store,
+    {
+        let document = doc.document();
+        let references = doc.references();
+
+        transaction
+            .project(&S::ty(), id, encode(doc)?, document.as_ref())
+            .await?;
+
+        transaction.refer(&S::ty(), id, &references).await
+    }
+
+    /// Record a change to an entity's lifecycle.
+    ///
+    /// Deleting removes its projection and purging erases it, see [`Transaction::purge`];
+    /// projecting a restored entity is left to the caller. The entity is locked first.
+    pub async fn tombstone<S>(
+        transaction: &mut (dyn Transaction + '_),
+        id: &str,
+        lifecycle: Lifecycle,
+        author: &auth::Identity,
+    ) -> sqlx::Result<()>
+    where
+        S: Serialize + Send + Store,
+    {
+        Self::lock::<S>(transaction, id).await?;
+
+        match lifecycle {
+            Lifecycle::Active => {}
+            Lifecycle::Deleted => transaction.unproject(&S::ty(), id).await?,
+            Lifecycle::Purged => transaction.purge(&S::ty(), id).await?,
+        }
+
+        Self::delta(transaction, id, S::tombstone(lifecycle), author).await
+    }
+
+    /// Erase the `#[personal_data]` fields of an entity from its whole history, recording the
+    /// erasure as a delta by `author`.
+    ///
+    /// Whatever its lifecycle, the entity gives up any values of those fields it claimed,
+    /// keeping the rest for a deleted entity to be restored with. A projected entity is
+    /// projected afresh, dropping the values from its search document.
+    pub async fn erase<T, S>(
+        transaction: &mut (dyn Transaction + '_),
+        id: &str,
+        author: &auth::Identity,
+    ) -> sqlx::Result<()>
+    where
+        S: DeserializeOwned + Serialize + std::fmt::Debug + Send + Store,
+        T: Del<S> + Default,
+    {
+        let (fields, marker) = (S::personal_data(), encode(Delta::<()>::redacted())?);
+
+        Self::lock::<S>(transaction, id).await?;
+        let projected = transaction.projection(&S::ty(), id).await?.is_some();
+
+        transaction.redact(&S::ty(), id, &fields, &marker).await?;
+
+        let erasure = fields
+            .iter()
+            .map(|field| (field.clone(), marker.clone()))
+            .collect::<serde_json::Map<_, _>>();
+        transaction
+            .append(&S::ty(), id, erasure.into(), &author.user_id)
+            .await?;
+
+        // Rolled up over the redacted deltas, the fields are as if never set
+        let (doc, lifecycle) = Self::rollup_with_lifecycle::<T, S>(transaction, id).await?;
+
+        // A purged entity claims nothing
+        if lifecycle == Lifecycle::Purged {
+            return Ok(());
+        }
+
+        let erased: S = doc.into();
+
+        let mut claims = erased.claims();
+        let mut seen = std::collections::HashSet::new();
+        claims.retain(|claim| !fields.contains(&claim.key) && seen.insert(claim.clone()));
+
+        // Erasing only ever gives up claims, so can't collide with another entity
+        transaction.claim(&S::ty(), id, &claims).await?;
+
+        if projected {
+            Self::project(transaction, id, erased).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Claim the identifiers and `#[unique]` values of an entity, before projecting it.
+    ///
+    /// Returns the value another entity already has, refusing the write.
+    pub async fn claim<S>(
+        transaction: &mut (dyn Transaction + '_),
+        id: &str,
+        doc: &S,
+    ) -> sqlx::Result<Option<Duplicate>>
+    where
+        S: Store,
+    {
+        // Listing a value twice doesn't take it from the entity itself
+        let mut claims = doc.claims();
+        let mut seen = std::collections::HashSet::new();
+        claims.retain(|claim| seen.insert(claim.clone()));
+
+        transaction.claim(&S::ty(), id, &claims).await
+    }
+
+    /// Check the references a delta writes to its `#[relation]` fields, before projecting it.
+    ///
+    /// Returns the first dangling reference of a strict field, refusing the write.
+    /// Those of a field set to warn are logged instead.
+    pub async fn check_references<S>(
+        transaction: &mut (dyn Transaction + '_),
+        id: &str,
+        doc: &S,
+    ) -> sqlx::Result<Option<Dangling>>
+    where
+        S: Store,
+    {
+        for relation in doc.relations() {
+            if relation.integrity == Integrity::Off {
+                continue;
+            }
+
+            for dangling in Self::dangling::<S>(transaction, id, &relation).await? {
+                if relation.integrity == Integrity::Strict {
+                    return Ok(Some(dangling));
+                }
+                log::warn!("{}", dangling);
+            }
+        }
+
+        Ok(None)
+    }
+
+    /// Every dangling reference held by the projections of a type, whatever the integrity
+    /// of their fields.
+    ///
+    /// Reads what is committed without locking anything, a page of projections at a time.
+    pub async fn scan<S>(db: &dyn Backend) -> sqlx::Result<Vec<Dangling>>
+    where
+        S: DeserializeOwned + Store,
+    {
+        let mut page = Page {
+            limit: SCAN_PAGE,
+            ..Default::default()
+        };
+
+        let mut found = vec![];
+
+        loop {
+            let projections = db.search(&S::ty(), &Default::default(), &page).await?;
+
+            for projection in &projections {
+                let doc = decode::<S>(projection.body.clone())?;
+                for relation in doc.relations() {
+                    found.extend(
+                        Self::dangling_committed::<S>(db, &projection.key.id, &relation).await?,
+                    );
+                }
+            }
+
+            match projections.last() {
+                Some(last) if projections.len() == page.limit => {
+                    page.after = Some(last.key.clone())
+                }
+                _ => return Ok(found),
+            }
+        }
+    }
+
+    /// The references of a relation pointing at no projected entity, as last committed
+    async fn dangling_committed<S>(
+        db: &dyn Backend,
+        id: &str,
+        relation: &Relation,
+    ) -> sqlx::Result<Vec<Dangling>>
+    where
+        S: Store,
+    {
+        let mut referenced = vec![];
+
+        for reference in &relation.references {
+            let identifier = reference.identifier();
+
+            referenced.push(match identifier.system {
+                IdentifierSystem::Yoda => yoda_id(identifier),
+                _ => {
+                    db.find_by_identifier(
+                        &relation.ty,
+                        &identifier.system.to_string(),
+                        &identifier.value,
+                    )
+                    .await?
+                }
+            });
+        }
+
+        let ids = referenced.iter().flatten().cloned().collect::<Vec<_>>();
+        let projected = db
+            .projections(&relation.ty, &ids)
+            .await?
+            .into_iter()
+            .map(|(id, _)| id)
+            .collect::<std::collections::HashSet<_>>();
+
+        Ok(relation
+            .references
+            .iter()
+            .zip(referenced)
+            .filter(|(_, referenced)| !referenced.as_ref().is_some_and(|id| projected.contains(id)))
+            .map(|(reference, _)| Dangling {
+                ty: S::ty(),
+                id: id.into(),
+                field: relation.field.clone(),
+                target: relation.ty.clone(),
+                reference: reference.clone(),
+            })
+            .collect())
+    }
+
+    /// The references of a relation pointing at no projected entity, as seen within the transaction
+    async fn dangling<S>(
+        transaction: &mut (dyn Transaction + '_),
+        id: &str,
+        relation: &Relation,
+    ) -> sqlx::Result<Vec<Dangling>>
+    where
+        S: Store,
+    {
+        let mut dangling = vec![];
+
+        for reference in &relation.references {
+            let identifier = reference.identifier();
+
+            let referenced = match identifier.system {
+                IdentifierSystem::Yoda => yoda_id(identifier),
+                _ => {
+                    transaction
+                        .claimant(&relation.ty, &Claim::identifier(identifier))
+                        .await?
+                }
+            };
+
+            let exists = match referenced {
+                Some(referenced) => transaction
+                    .projection(&relation.ty, &referenced)
+                    .await?
+                    .is_some(),
+                None => false,
+            };
+
+            if !exists {
+                dangling.push(Dangling {
+                    ty: S::ty(),
+                    id: id.into(),
+                    field: relation.field.clone(),
+                    target: relation.ty.clone(),
+                    reference: reference.clone(),
+                });
+            }
+        }
+
+        Ok(dangling)
+    }
+
+    /// Every change committed from now on to the entity of the type with `id`, or to any
+    /// of them without one.
+    ///
+    /// Changes that can no longer be read back, such as those to entities purged since,
+    /// are left out.
+    pub async fn changes<T, S>(
+        db: Database,
+        id: Option<String>,
+    ) -> sqlx::Result<BoxStream<'static, Change<T>>>
+    where
+        S: DeserializeOwned + Serialize + std::fmt::Debug + Store + Send + 'static,
+        T: Del<S> + Default + Clone + Send + 'static,
+    {
+        Self::changes_where::<T, S, _>(db, move |changed| {
+            id.as_ref().is_none_or(|id| changed.id == *id)
+        })
+        .await
+    }
+
+    /// The first change committed to each entity of the type created from now on
+    pub async fn created<T, S>(db: Database) -> sqlx::Result<BoxStream<'static, Change<T>>>
+    where
+        S: DeserializeOwned + Serialize + std::fmt::Debug + Store + Send + 'static,
+        T: Del<S> + Default + Clone + Send + 'static,
+    {
+        Self::changes_where::<T, S, _>(db, |changed| changed.version == 1).await
+    }
+
+    /// The changes to entities of the type that are `wanted`, only read back once chosen.
+    ///
+    /// The entity each change left is kept, so the next change to it reads just its own deltas.
+    async fn changes_where<T, S, F>(
+        db: Database,
+        wanted: F,
+    ) -> sqlx::Result<BoxStream<'static, Change<T>>>
+    where
+        S: DeserializeOwned + Serialize + std::fmt::Debug + Store + Send + 'static,
+        T: Del<S> + Default + Clone + Send + 'static,
+        F: Fn(&Changed) -> bool + Send + 'static,
+    {
+        let ty = S::ty();
+        let last = std::sync::Arc::new(std::sync::Mutex::new(None));
+
+        Ok(db
+            .changes()
+            .await?
+            .filter(move |changed| future::ready(changed.ty == ty && wanted(changed)))
+            .filter_map(move |changed| {
+                let db = db.clone();
+                let last = last.clone();
+                async move {
+                    let known = last.lock().ok()?.take();
+
+                    match Self::change::<T, S>(&*db, &changed, known).await {
+                        Ok((change, rolled)) => {
+                            *last.lock().ok()? = Some(rolled);
+                            Some(change)
+                        }
+                        Err(e) => {
+                            log::warn!("Couldn't read {} {}: {}", changed.ty, changed.id, e);
+                            None
+                        }
+                    }
+                }
+            })
+            .boxed())
+    }
+
+    /// The entity as of a change, and what the change's delta did to it.
+    ///
+    /// Carries on from `known` when it is the same entity at an earlier version, and otherwise
+    /// from the latest snapshot before the change, reading only the deltas since. An erasure
+    /// since `known` means reading it afresh, so erased values aren't carried on.
+    async fn change<T, S>(
+        db: &dyn Backend,
+        changed: &Changed,
+        known: Option<Rolled<T>>,
+    ) -> sqlx::Result<(Change<T>, Rolled<T>)>
+    where
+        S: DeserializeOwned + Serialize + std::fmt::Debug + Store,
+        T: Del<S> + Default + Clone,
+    {
+        let Changed { id, version, .. } = changed;
+
+        let known = known.filter(|known| known.id == *id && known.version < *version);
+        let (mut rolled, records) = match known {
+            Some(known) => {
+                let records = Self::records_since::<S>(db, changed, known.version).await?;
+
+                // An erasure redacts the deltas the known entity was rolled up from
+                if records.iter().any(|record| redacts(&record.body)) {
+                    Self::rolled_afresh::<T, S>(db, changed).await?
+                } else {
+                    (known, records)
+                }
+            }
+            None => Self::rolled_afresh::<T, S>(db, changed).await?,
+        };
+
+        let records = records
+            .into_iter()
+            .map(|record| {
+                Ok(DeltaRecord {
+                    body: decode::<S>(record.body)?,
+                    author: record.author,
+                    created_at: record.created_at,
+                })
+            })
+            .collect::<sqlx::Result<Vec<_>>>()?;
+
+        let delta = atoms::history::entries_onto(
+            &mut rolled.doc,
+            &mut rolled.lifecycle,
+            rolled.version,
+            records,
+        )
+        .pop()
+        .filter(|entry| entry.version == *version)
+        .ok_or(sqlx::Error::RowNotFound)?;
+        rolled.version = *version;
+
+        let change = Change {
+            id: id.clone(),
+            entity: Some(rolled.doc.clone()).filter(|_| rolled.lifecycle == Lifecycle::Active),
+            delta,
+        };
+
+        Ok((change, rolled))
+    }
+
+    /// The entity as stored before a change, from the latest snapshot before it, with the
+    /// deltas since up to the change's own
+    async fn rolled_afresh<T, S>(
+        db: &dyn Backend,
+        changed: &Changed,
+    ) -> sqlx::Result<(Rolled<T>, Vec<DeltaRecord<serde_json::Value>>)>
+    where
+        S: DeserializeOwned + Serialize + std::fmt::Debug + Store,
+        T: Del<S> + Default,
+    {
+        let snapshot = db
+            .latest_snapshot(
+                &S::ty(),
+                &changed.id,
+                None,
+                Some(changed.version.saturating_sub(1)),
+            )
+            .await?;
+        let (doc, covered) = match snapshot {
+            Some((body, covered)) => (T::from(decode::<S>(body)?), covered),
+            None => (T::default(), 0),
+        };
+
+        // Snapshots are only taken of active entities
+        let rolled = Rolled {
+            id: changed.id.clone(),
+            doc,
+            lifecycle: Lifecycle::Active,
+            version: covered,
+        };
+        let records = Self::records_since::<S>(db, changed, covered).await?;
+
+        Ok((rolled, records))
+    }
+
+    /// The deltas to an entity after the first `skip`, up to a change's own
+    async fn records_since<S>(
+        db: &dyn Backend,
+        changed: &Changed,
+        skip: usize,
+    ) -> sqlx::Result<Vec<DeltaRecord<serde_json::Value>>>
+    where
+        S: Store,
+    {
+        db.deltas(
+            &S::ty(),
+            &changed.id,
+            DeltaRange {
+                version: Some(changed.version),
+                skip,
+                ..Default::default()
+            },
+        )
+        .await
+    }
+
+    /// Whether an entity matches a search, judged on the projection it would have
+    pub fn matches<T, S, Q>(entity: &T, search: &Q) -> bool
+    where
+        S: Serialize + Store,
+        T: Del<S> + Clone,
+        Q: Search<S>,
+    {
+        let doc: S = entity.clone().into();
+        let document = doc.document();
+
+        encode(&doc).is_ok_and(|body| {
+            search.criteria().matches(
+                &body,
+                document.as_ref().map(|document| document.text.as_str()),
+            )
+        })
+    }
+
+    /// The id of the entity with an identifier, as seen within the transaction
+    pub async fn identified<S>(
+        transaction: &mut (dyn Transaction + '_),
+        identifier: &Identifier,
+    ) -> sqlx::Result<Option<String>>
+    where
+        S: Store,
+    {
+        transaction
+            .claimant(&S::ty(), &Claim::identifier(identifier))
+            .await
+    }
+
+    /// The ids of the entities `references` point to, in order, skipping those that point nowhere.
+    ///
+    /// Identifiers of other systems are looked up through the batching loader.
+    pub async fn referenced<S>(
+        loader: &ProjectionLoader,
+        references: &[Reference],
+    ) -> sqlx::Result<Vec<String>>
+    where
+        S: Store,
+    {
+        let keys: Vec<_> = references
+            .iter()
+            .map(|reference| reference.identifier())
+            .filter(|identifier| identifier.system != IdentifierSystem::Yoda)
+            .map(|identifier| (S::ty(), identifier.clone()))
+            .collect();
+
+        let found = loader
+            .load_many(keys)
+            .await
+            .map_err(|e| sqlx::Error::Protocol(e.to_string()))?;
+
+        Ok(references
+            .iter()
+            .map(|reference| reference.identifier())
+            .filter_map(|identifier| match identifier.system {
+                IdentifierSystem::Yoda => Some(identifier.value.clone()),
+                _ => found.get(&(S::ty(), identifier.clone())).cloned(),
+            })
+            .collect())
+    }
+
+    /// Entities by id through the batching loader, in order, skipping any that aren't projected
+    pub async fn load<T, S>(loader: &ProjectionLoader, ids: Vec<String>) -> sqlx::Result<Vec<T>>
+    where
+        S: DeserializeOwned + Serialize + std::fmt::Debug + Store,
+        T: Del<S>,
+    {
+        let keys: Vec<_> = ids.into_iter().map(|id| (S::ty(), id)).collect();
+
+        let found = loader
+            .load_many(keys.clone())
+            .await
+            .map_err(|e| sqlx::Error::Protocol(e.to_string()))?;
+
+        keys.iter()
+            .filter_map(|key| found.get(key).cloned())
+            .map(|body| Ok(decode::<S>(body)?.into()))
+            .collect()
+    }
+
+    /// The entities referring to one with any of `identifiers`, through references of
+    /// `reference_type` when given
+    pub async fn referrers(
+        db: &dyn Backend,
+        identifiers: &[Identifier],
+        reference_type: Option<ReferenceType>,
+    ) -> sqlx::Result<Vec<Referrer>> {
+        db.referrers(identifiers, reference_type).await
+    }
+
+    /// The id of the entity with an identifier, from any system
+    pub async fn find_by_identifier<S>(
+        db: &dyn Backend,
+        system: IdentifierSystem,
+        value: &str,
+    ) -> sqlx::Result<Option<String>>
+    where
+        S: Store,
+    {
+        db.find_by_identifier(&S::ty(), &system.to_string(), value)
+            .await
+    }
+}
@@ -8,6 +8,33 @@ impl DatabaseConfig {
 
         Ok(Self { url })
     }
+
+    /// Connect to the backend named by the url scheme, `postgres://` or `sqlite:`
+    pub async fn connect(&self) -> sqlx::Result<Database> {
+        let scheme = self.url.split(':').next().unwrap_or_default();
+
+        match scheme {
+            "postgres" | "postgresql" => {
+                Ok(Database::new(sql::Postgres::connect(&self.url).await?))
+            }
+            "sqlite" => Ok(Database::new(sqlite::Sqlite::connect(&self.url).await?)),
+            _ => Err(sqlx::Error::Configuration(
+                format!("Unsupported database url scheme: {}", scheme).into(),
+            )),
+        }
+    }
 }
 
+mod backend;
+mod driver;
+mod loader;
+pub mod memory;
+pub mod replay;
 pub mod sql;
+pub mod sqlite;
+#[cfg(test)]
+mod test;
+
+pub use backend::{Backend, Changed, Database, DeltaRange, Found, Transaction};
+pub use driver::{Change, Driver};
+pub use loader::{ProjectionLoader, Projections};
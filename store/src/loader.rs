@@ -0,0 +1,84 @@
+use std::{collections::HashMap, sync::Arc};
+
+use async_graphql::dataloader::{DataLoader, Loader};
+use async_trait::async_trait;
+use atoms::Identifier;
+
+use crate::Database;
+
+/// Loads projections by type and id, batching the reads resolvers make together
+pub struct Projections(Database);
+
+/// The loader relation resolvers read referenced entities through, stored in the schema data
+pub type ProjectionLoader = DataLoader<Projections>;
+
+impl Projections {
+    pub fn loader(db: Database) -> ProjectionLoader {
+        DataLoader::new(Self(db))
+    }
+}
+
+#[async_trait]
+impl Loader<(String, String)> for Projections {
+    type Value = serde_json::Value;
+    // Handed to every resolver waiting on the batch
+    type Error = Arc<sqlx::Error>;
+
+    async fn load(
+        &self,
+        keys: &[(String, String)],
+    ) -> Result<HashMap<(String, String), Self::Value>, Self::Error> {
+        let mut ids: HashMap<&str, Vec<String>> = HashMap::new();
+        for (ty, id) in keys {
+            ids.entry(ty.as_str()).or_default().push(id.clone());
+        }
+
+        let mut found = HashMap::new();
+        for (ty, ids) in ids {
+            for (id, body) in self.0.projections(ty, &ids).await? {
+                found.insert((ty.to_string(), id), body);
+            }
+        }
+
+        Ok(found)
+    }
+}
+
+/// Loads the ids of entities by type and identifier, batching the lookups relation
+/// resolvers make together
+#[async_trait]
+impl Loader<(String, Identifier)> for Projections {
+    type Value = String;
+    type Error = Arc<sqlx::Error>;
+
+    async fn load(
+        &self,
+        keys: &[(String, Identifier)],
+    ) -> Result<HashMap<(String, Identifier), Self::Value>, Self::Error> {
+        let mut identifiers: HashMap<&str, Vec<Identifier>> = HashMap::new();
+        for (ty, identifier) in keys {
+            identifiers
+                .entry(ty.as_str())
+                .or_default()
+                .push(identifier.clone());
+        }
+
+        let mut found = HashMap::new();
+        for (ty, identifiers) in identifiers {
+            let ids = self.0.find_by_identifiers(ty, &identifiers).await?;
+
+            for identifier in identifiers {
+                let system = identifier.system.to_string();
+                let id = ids
+                    .iter()
+                    .find(|(s, value, _)| *s == system && *value == identifier.value);
+
+                if let Some((_, _, id)) = id {
+                    found.insert((ty.to_string(), identifier), id.clone());
+                }
+            }
+        }
+
+        Ok(found)
+    }
+}
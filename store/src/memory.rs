@@ -0,0 +1,556 @@
+use std::{collections::HashMap, sync::RwLock};
+
+use async_trait::async_trait;
+use atoms::{
+    delta::{Claim, Duplicate},
+    history::DeltaRecord,
+    pagination::{Key, Page, SortBy},
+    search::{Criteria, Document, Index},
+    Identifier, ReferenceType, Referral, Referrer,
+};
+use futures::{
+    lock::{Mutex, MutexGuard},
+    stream::BoxStream,
+};
+use sqlx::types::chrono::{DateTime, Utc};
+
+use crate::backend::{page_of, Backend, Changed, DeltaRange, Feed, Found, Transaction};
+
+#[derive(Clone)]
+struct StoredDelta {
+    ty: String,
+    id: String,
+    record: DeltaRecord<serde_json::Value>,
+}
+
+#[derive(Clone)]
+struct StoredProjection {
+    ty: String,
+    body: serde_json::Value,
+    document: Option<String>,
+    last_updated: DateTime<Utc>,
+}
+
+impl StoredProjection {
+    fn matches(&self, ty: &str, criteria: &Criteria) -> bool {
+        self.ty == ty && criteria.matches(&self.body, self.document.as_deref())
+    }
+}
+
+#[derive(Clone)]
+struct StoredSnapshot {
+    ty: String,
+    id: String,
+    body: serde_json::Value,
+    version: usize,
+    delta_created_at: DateTime<Utc>,
+}
+
+#[derive(Clone, Default)]
+struct State {
+    deltas: Vec<StoredDelta>,
+    projections: HashMap<String, StoredProjection>,
+    snapshots: Vec<StoredSnapshot>,
+    /// The entity claiming each type, key and value
+    claims: HashMap<(String, String, String), String>,
+    /// The references each type and id holds
+    references: HashMap<(String, String), Vec<Referral>>,
+}
+
+impl State {
+    fn deltas<'a>(
+        &'a self,
+        ty: &'a str,
+        id: &'a str,
+    ) -> impl Iterator<Item = &'a DeltaRecord<serde_json::Value>> + 'a {
+        self.deltas
+            .iter()
+            .filter(move |delta| delta.ty == ty && delta.id == id)
+            .map(|delta| &delta.record)
+    }
+}
+
+/// Storage held entirely in memory, for tests and local demos.
+///
+/// Transactions run one at a time against a copy of the state that replaces it on commit,
+/// so reads outside a transaction only ever see committed data.
+#[derive(Default)]
+pub struct Memory {
+    state: RwLock<State>,
+    writer: Mutex<()>,
+    feed: Feed,
+}
+
+impl Memory {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn read(&self) -> std::sync::RwLockReadGuard<'_, State> {
+        self.state.read().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+pub struct MemoryTransaction<'a> {
+    memory: &'a Memory,
+    _writer: MutexGuard<'a, ()>,
+    staged: State,
+    changed: Vec<Changed>,
+}
+
+#[async_trait]
+impl Backend for Memory {
+    async fn begin(&self) -> sqlx::Result<Box<dyn Transaction + '_>> {
+        let writer = self.writer.lock().await;
+        let staged = self.read().clone();
+
+        Ok(Box::new(MemoryTransaction {
+            memory: self,
+            _writer: writer,
+            staged,
+            changed: vec![],
+        }))
+    }
+
+    async fn deltas(
+        &self,
+        ty: &str,
+        id: &str,
+        range: DeltaRange,
+    ) -> sqlx::Result<Vec<DeltaRecord<serde_json::Value>>> {
+        let DeltaRange {
+            as_of,
+            version,
+            skip,
+        } = range;
+
+        Ok(self
+            .read()
+            .deltas(ty, id)
+            .filter(|record| as_of.is_none_or(|as_of| record.created_at <= as_of))
+            .take(version.unwrap_or(usize::MAX))
+            .skip(skip)
+            .cloned()
+            .collect())
+    }
+
+    async fn latest_snapshot(
+        &self,
+        ty: &str,
+        id: &str,
+        as_of: Option<DateTime<Utc>>,
+        version: Option<usize>,
+    ) -> sqlx::Result<Option<(serde_json::Value, usize)>> {
+        Ok(self
+            .read()
+            .snapshots
+            .iter()
+            .filter(|snapshot| snapshot.ty == ty && snapshot.id == id)
+            .filter(|snapshot| as_of.is_none_or(|as_of| snapshot.delta_created_at <= as_of))
+            .filter(|snapshot| version.is_none_or(|version| snapshot.version <= version))
+            .max_by_key(|snapshot| snapshot.version)
+            .map(|snapshot| (snapshot.body.clone(), snapshot.version)))
+    }
+
+    async fn ids(&self, ty: &str, id: Option<&str>) -> sqlx::Result<Vec<String>> {
+        let mut ids: Vec<String> = vec![];
+
+        for delta in self.read().deltas.iter() {
+            if delta.ty == ty && id.is_none_or(|id| delta.id == id) && !ids.contains(&delta.id) {
+                ids.push(delta.id.clone());
+            }
+        }
+
+        Ok(ids)
+    }
+
+    async fn search(&self, ty: &str, criteria: &Criteria, page: &Page) -> sqlx::Result<Vec<Found>> {
+        let state = self.read();
+        let text = criteria.text.as_ref();
+
+        let found = state
+            .projections
+            .iter()
+            .filter(|(_, projection)| projection.matches(ty, criteria))
+            .map(|(id, projection)| {
+                let document = projection.document.as_deref().unwrap_or_default();
+                let score = criteria
+                    .fuzzy
+                    .as_ref()
+                    .and_then(|fuzzy| fuzzy.score(&projection.body));
+
+                let value = match &page.sort.by {
+                    SortBy::LastUpdated => {
+                        serde_json::to_value(projection.last_updated).unwrap_or_default()
+                    }
+                    SortBy::Field(field) => projection.body[field]["end"].clone(),
+                    SortBy::Relevance => text
+                        .map(|text| serde_json::json!(text.rank(document)))
+                        .unwrap_or_default(),
+                    SortBy::Similarity => serde_json::to_value(score).unwrap_or_default(),
+                };
+
+                Found {
+                    key: Key {
+                        value,
+                        id: id.clone(),
+                    },
+                    body: projection.body.clone(),
+                    snippet: text.map(|text| text.highlight(document)),
+                    score,
+                }
+            })
+            .collect();
+
+        Ok(page_of(found, page))
+    }
+
+    async fn count(&self, ty: &str, criteria: &Criteria) -> sqlx::Result<usize> {
+        Ok(self
+            .read()
+            .projections
+            .values()
+            .filter(|projection| projection.matches(ty, criteria))
+            .count())
+    }
+
+    async fn projections(
+        &self,
+        ty: &str,
+        ids: &[String],
+    ) -> sqlx::Result<Vec<(String, serde_json::Value)>> {
+        let state = self.read();
+
+        Ok(ids
+            .iter()
+            .filter_map(|id| {
+                state
+                    .projections
+                    .get(id)
+                    .filter(|projection| projection.ty == ty)
+                    .map(|projection| (id.clone(), projection.body.clone()))
+            })
+            .collect())
+    }
+
+    async fn find_by_identifier(
+        &self,
+        ty: &str,
+        system: &str,
+        value: &str,
+    ) -> sqlx::Result<Option<String>> {
+        Ok(self
+            .read()
+            .claims
+            .get(&(ty.into(), system.into(), value.into()))
+            .cloned())
+    }
+
+    async fn find_by_identifiers(
+        &self,
+        ty: &str,
+        identifiers: &[Identifier],
+    ) -> sqlx::Result<Vec<(String, String, String)>> {
+        let state = self.read();
+
+        Ok(identifiers
+            .iter()
+            .map(|identifier| (identifier.system.to_string(), identifier.value.clone()))
+            .filter_map(|(system, value)| {
+                let id = state
+                    .claims
+                    .get(&(ty.into(), system.clone(), value.clone()))?;
+                Some((system, value, id.clone()))
+            })
+            .collect())
+    }
+
+    async fn referrers(
+        &self,
+        identifiers: &[Identifier],
+        reference_type: Option<ReferenceType>,
+    ) -> sqlx::Result<Vec<Referrer>> {
+        let mut referrers: Vec<_> = self
+            .read()
+            .references
+            .iter()
+            .flat_map(|((ty, id), referrals)| {
+                referrals
+                    .iter()
+                    .filter(|referral| {
+                        identifiers.contains(referral.reference.identifier())
+                            && reference_type.is_none_or(|reference_type| {
+                                referral.reference.reference_type() == reference_type
+                            })
+                    })
+                    .map(move |referral| Referrer {
+                        ty: ty.clone(),
+                        id: id.clone(),
+                        field: referral.field.clone(),
+                        reference_type: referral.reference.reference_type(),
+                    })
+            })
+            .collect();
+
+        referrers.sort_by_key(|referrer| {
+            (
+                referrer.ty.clone(),
+                referrer.id.clone(),
+                referrer.field.clone(),
+                referrer.reference_type.to_string(),
+            )
+        });
+        referrers.dedup();
+
+        Ok(referrers)
+    }
+
+    /// Every search is a scan here
+    async fn missing_indexes(&self, _declared: &[Index]) -> sqlx::Result<Vec<Index>> {
+        Ok(vec![])
+    }
+
+    async fn changes(&self) -> sqlx::Result<BoxStream<'static, Changed>> {
+        Ok(self.feed.subscribe())
+    }
+}
+
+#[async_trait]
+impl Transaction for MemoryTransaction<'_> {
+    async fn projection(&mut self, ty: &str, id: &str) -> sqlx::Result<Option<serde_json::Value>> {
+        Ok(self
+            .staged
+            .projections
+            .get(id)
+            .filter(|projection| projection.ty == ty)
+            .map(|projection| projection.body.clone()))
+    }
+
+    /// Transactions already run one at a time
+    async fn lock(&mut self, _ty: &str, _id: &str) -> sqlx::Result<()> {
+        Ok(())
+    }
+
+    async fn deltas(
+        &mut self,
+        ty: &str,
+        id: &str,
+    ) -> sqlx::Result<Vec<DeltaRecord<serde_json::Value>>> {
+        Ok(self.staged.deltas(ty, id).cloned().collect())
+    }
+
+    async fn append(
+        &mut self,
+        ty: &str,
+        id: &str,
+        body: serde_json::Value,
+        author: &str,
+    ) -> sqlx::Result<()> {
+        self.staged.deltas.push(StoredDelta {
+            ty: ty.into(),
+            id: id.into(),
+            record: DeltaRecord {
+                body,
+                author: author.into(),
+                created_at: Utc::now(),
+            },
+        });
+
+        self.changed.push(Changed {
+            ty: ty.into(),
+            id: id.into(),
+            version: self.staged.deltas(ty, id).count(),
+        });
+
+        Ok(())
+    }
+
+    async fn project(
+        &mut self,
+        ty: &str,
+        id: &str,
+        body: serde_json::Value,
+        document: Option<&Document>,
+    ) -> sqlx::Result<()> {
+        let projection =
+            self.staged
+                .projections
+                .entry(id.into())
+                .or_insert_with(|| StoredProjection {
+                    ty: ty.into(),
+                    body: Default::default(),
+                    document: None,
+                    last_updated: Utc::now(),
+                });
+
+        projection.body = body;
+        projection.document = document.map(|document| document.text.clone());
+
+        Ok(())
+    }
+
+    async fn unproject(&mut self, ty: &str, id: &str) -> sqlx::Result<()> {
+        let projections = &mut self.staged.projections;
+
+        if projections.get(id).map(|projection| projection.ty.as_str()) == Some(ty) {
+            projections.remove(id);
+        }
+        self.staged.references.remove(&(ty.into(), id.into()));
+
+        Ok(())
+    }
+
+    async fn purge(&mut self, ty: &str, id: &str) -> sqlx::Result<()> {
+        self.unproject(ty, id).await?;
+
+        let staged = &mut self.staged;
+        staged
+            .snapshots
+            .retain(|snapshot| snapshot.ty != ty || snapshot.id != id);
+        staged
+            .claims
+            .retain(|(claimed, _, _), owner| claimed != ty || owner != id);
+
+        for delta in staged
+            .deltas
+            .iter_mut()
+            .filter(|delta| delta.ty == ty && delta.id == id)
+        {
+            let body = &mut delta.record.body;
+            *body = match body.get("lifecycle") {
+                Some(lifecycle) => serde_json::json!({ "lifecycle": lifecycle }),
+                None => serde_json::json!({}),
+            };
+        }
+
+        Ok(())
+    }
+
+    async fn redact(
+        &mut self,
+        ty: &str,
+        id: &str,
+        fields: &[String],
+        marker: &serde_json::Value,
+    ) -> sqlx::Result<()> {
+        let staged = &mut self.staged;
+
+        let deltas = staged
+            .deltas
+            .iter_mut()
+            .filter(|delta| delta.ty == ty && delta.id == id)
+            .map(|delta| &mut delta.record.body);
+        let snapshots = staged
+            .snapshots
+            .iter_mut()
+            .filter(|snapshot| snapshot.ty == ty && snapshot.id == id)
+            .map(|snapshot| &mut snapshot.body);
+        let projection = staged
+            .projections
+            .get_mut(id)
+            .filter(|projection| projection.ty == ty)
+            .map(|projection| &mut projection.body);
+
+        for body in deltas.chain(snapshots).chain(projection) {
+            for field in fields {
+                if body[field].is_object() {
+                    body[field] = marker.clone();
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn claimant(&mut self, ty: &str, claim: &Claim) -> sqlx::Result<Option<String>> {
+        Ok(self
+            .staged
+            .claims
+            .get(&(ty.into(), claim.key.clone(), claim.value.clone()))
+            .cloned())
+    }
+
+    async fn claim(
+        &mut self,
+        ty: &str,
+        id: &str,
+        claims: &[Claim],
+    ) -> sqlx::Result<Option<Duplicate>> {
+        let staged = &mut self.staged.claims;
+
+        staged.retain(|(claimed, _, _), owner| claimed != ty || owner != id);
+
+        for claim in claims {
+            let key = (ty.into(), claim.key.clone(), claim.value.clone());
+
+            if let Some(owner) = staged.get(&key) {
+                return Ok(Some(Duplicate {
+                    ty: ty.into(),
+                    key: claim.key.clone(),
+                    value: claim.value.clone(),
+                    id: owner.clone(),
+                }));
+            }
+            staged.insert(key, id.into());
+        }
+
+        Ok(None)
+    }
+
+    async fn refer(&mut self, ty: &str, id: &str, referrals: &[Referral]) -> sqlx::Result<()> {
+        self.staged
+            .references
+            .insert((ty.into(), id.into()), referrals.to_vec());
+
+        Ok(())
+    }
+
+    async fn snapshot(
+        &mut self,
+        ty: &str,
+        id: &str,
+        every: u64,
+        body: serde_json::Value,
+    ) -> sqlx::Result<()> {
+        let (version, delta_created_at) = self
+            .staged
+            .deltas(ty, id)
+            .fold((0, None), |(version, _), record| {
+                (version + 1, Some(record.created_at))
+            });
+
+        let exists = self
+            .staged
+            .snapshots
+            .iter()
+            .any(|snapshot| snapshot.id == id && snapshot.version == version);
+
+        if let Some(delta_created_at) = delta_created_at {
+            if (version as u64).is_multiple_of(every) && !exists {
+                self.staged.snapshots.push(StoredSnapshot {
+                    ty: ty.into(),
+                    id: id.into(),
+                    body,
+                    version,
+                    delta_created_at,
+                });
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn commit(self: Box<Self>) -> sqlx::Result<()> {
+        let MemoryTransaction {
+            memory,
+            staged,
+            changed,
+            ..
+        } = *self;
+
+        *memory.state.write().unwrap_or_else(|e| e.into_inner()) = staged;
+        memory.feed.publish(changed);
+
+        Ok(())
+    }
+}
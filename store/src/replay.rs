@@ -0,0 +1,122 @@
+use atoms::delta::{Del, Lifecycle, Rollup, Store};
+
+use crate::{Backend, Driver};
+
+/// How far a replay of one type has got
+#[derive(Debug, Clone)]
+pub struct Progress {
+    pub ty: String,
+    pub done: usize,
+    pub total: usize,
+}
+
+/// An entity whose projection does not match a replay of its deltas
+#[derive(Debug, Clone)]
+pub struct Divergence {
+    pub ty: String,
+    pub id: String,
+    /// `None` when the entity has no projection at all
+    pub projected: Option<serde_json::Value>,
+    /// `None` when the entity is deleted or purged, and so shouldn't be projected
+    pub replayed: Option<serde_json::Value>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ReplayReport {
+    pub replayed: usize,
+    pub divergences: Vec<Divergence>,
+}
+
+impl ReplayReport {
+    pub fn merge(&mut self, other: ReplayReport) {
+        self.replayed += other.replayed;
+        self.divergences.extend(other.divergences);
+    }
+}
+
+/// Recompute projections from the delta log
+pub struct Replay;
+
+impl Replay {
+    /// Replay a single entity, rewriting its projection if it diverged.
+    ///
+    /// With `dry_run` the divergence is reported but nothing is written.
+    pub async fn entity<T, S>(
+        db: &dyn Backend,
+        id: &str,
+        dry_run: bool,
+    ) -> sqlx::Result<Option<Divergence>>
+    where
+        S: serde::de::DeserializeOwned + serde::Serialize + std::fmt::Debug + Send + Store,
+        T: Del<S> + Default,
+    {
+        let mut transaction = db.begin().await?;
+
+        // Lock the entity, then its projection before reading the deltas, so no update can commit in between
+        Driver::lock::<S>(&mut *transaction, id).await?;
+        let projected = Driver::query_proj_body::<S>(&mut *transaction, id).await?;
+
+        let dels = Driver::query_in::<T, S>(&mut *transaction, id).await?;
+        let active = Lifecycle::of(&dels) == Lifecycle::Active;
+        let doc: T = dels.into_iter().rollup();
+        let replayed: S = doc.into();
+        let replayed_body =
+            Some(serde_json::to_value(&replayed).unwrap_or_default()).filter(|_| active);
+
+        if projected == replayed_body {
+            return Ok(None);
+        }
+
+        if !dry_run {
+            if active {
+                if let Some(duplicate) = Driver::claim(&mut *transaction, id, &replayed).await? {
+                    return Err(sqlx::Error::Protocol(duplicate.to_string()));
+                }
+                Driver::project(&mut *transaction, id, replayed).await?;
+            } else {
+                transaction.unproject(&S::ty(), id).await?;
+            }
+            transaction.commit().await?;
+        }
+
+        Ok(Some(Divergence {
+            ty: S::ty(),
+            id: id.into(),
+            projected,
+            replayed: replayed_body,
+        }))
+    }
+
+    /// Replay every entity of a type, or only `id` when given
+    pub async fn ty<T, S, F>(
+        db: &dyn Backend,
+        id: Option<&str>,
+        dry_run: bool,
+        mut progress: F,
+    ) -> sqlx::Result<ReplayReport>
+    where
+        S: serde::de::DeserializeOwned + serde::Serialize + std::fmt::Debug + Send + Store,
+        T: Del<S> + Default,
+        F: FnMut(&Progress),
+    {
+        let ids = Driver::ids::<S>(db, id).await?;
+        let total = ids.len();
+
+        let mut report = ReplayReport::default();
+
+        for (i, id) in ids.iter().enumerate() {
+            if let Some(divergence) = Self::entity::<T, S>(db, id, dry_run).await? {
+                report.divergences.push(divergence);
+            }
+            report.replayed += 1;
+
+            progress(&Progress {
+                ty: S::ty(),
+                done: i + 1,
+                total,
+            });
+        }
+
+        Ok(report)
+    }
+}
@@ -1,126 +1,790 @@
-use std::str::FromStr;
+use std::{
+    str::FromStr,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+};
 
+use async_trait::async_trait;
 use atoms::{
-    delta::{Del, Store},
-    pagination::PaginationOption,
-    search::Search,
+    delta::{Claim, Duplicate},
+    history::DeltaRecord,
+    pagination::{Key, Page, Sort, SortBy},
+    search::{Condition, Criteria, Document, Index, IndexMethod, Kind, Operator},
+    Identifier, ReferenceType, Referral, Referrer,
+};
+use futures::stream::BoxStream;
+use sqlx::{
+    postgres::PgListener,
+    types::{
+        chrono::{DateTime, Utc},
+        Uuid,
+    },
+    Row,
+};
+
+use crate::backend::{
+    keyset_sql, referrer, Backend, Changed, DeltaRange, Feed, Found, Transaction,
 };
-use serde::Serialize;
-use sqlx::{types::Uuid, Row};
 
 fn convert_id(id: &str) -> sqlx::Result<Uuid> {
     sqlx::types::Uuid::from_str(id).map_err(|e| sqlx::Error::Configuration(Box::new(e)))
 }
 
-pub struct Driver;
+fn record(row: &sqlx::postgres::PgRow) -> sqlx::Result<DeltaRecord<serde_json::Value>> {
+    Ok(DeltaRecord {
+        body: row.try_get("body")?,
+        author: row.try_get("author")?,
+        created_at: row.try_get("created_at")?,
+    })
+}
+
+/// The SQL for a search condition, comparing against parameter `$param`
+fn condition_sql(condition: &Condition, param: usize) -> String {
+    if !condition.is_nested() {
+        return compare_sql(condition, field_sql(&condition.field), param);
+    }
+
+    // jsonpath in lax mode searches each element of the lists met along the path
+    let (field, keys) = condition.path();
+    let path = keys
+        .iter()
+        .fold(format!("$.{}.\"end\"[*]", json_key(field)), |path, key| {
+            format!("{}.{}[*]", path, json_key(key))
+        });
+
+    format!(
+        "EXISTS (SELECT 1 FROM jsonb_path_query(body, '{}') AS v(value) WHERE {})",
+        path.replace('\'', "''"),
+        compare_sql(condition, "value #>> '{}'".into(), param)
+    )
+}
+
+fn json_key(key: &str) -> String {
+    format!("\"{}\"", key.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/// The text of a field's current value
+fn field_sql(field: &str) -> String {
+    format!("(body -> '{}' ->> 'end')", field.replace('\'', "''"))
+}
+
+/// `current`, the text of a value, as the kind it is compared as.
+///
+/// The same expressions are indexed, see [`index_sql`], so text is left uncast and timestamps
+/// go through `rfc3339`, an immutable cast.
+fn cast_sql(current: &str, kind: Kind) -> String {
+    match kind {
+        Kind::Text => current.into(),
+        Kind::DateTime => format!("rfc3339({})", current),
+        kind => format!("({})::{}", current, sql_ty(kind)),
+    }
+}
+
+/// Compare `current`, the text of a value, with parameter `$param`
+fn compare_sql(condition: &Condition, current: String, param: usize) -> String {
+    let sql_ty = sql_ty(condition.kind);
+
+    let current = cast_sql(&current, condition.kind);
+
+    let compare = |op: &str| format!("{} {} ${}::{}", current, op, param, sql_ty);
+
+    match condition.op {
+        Operator::Eq => compare("="),
+        Operator::In => format!("{} = ANY(${}::{}[])", current, param, sql_ty),
+        Operator::Gt => compare(">"),
+        Operator::Gte => compare(">="),
+        Operator::Lt => compare("<"),
+        Operator::Lte => compare("<="),
+        Operator::Prefix => format!("{} LIKE ${}", current, param),
+        Operator::Contains => format!("{} ILIKE ${}", current, param),
+    }
+}
+
+fn sql_ty(kind: Kind) -> &'static str {
+    match kind {
+        Kind::Text => "text",
+        Kind::Number => "numeric",
+        Kind::Boolean => "boolean",
+        Kind::DateTime => "timestamptz",
+    }
+}
+
+/// The statement creating an index, for a migration
+pub fn index_sql(index: &Index) -> String {
+    let current = field_sql(&index.field);
+
+    match index.method {
+        IndexMethod::Value(kind) => format!(
+            "CREATE INDEX IF NOT EXISTS {} ON projection (ty, ({}));",
+            index.name(),
+            cast_sql(&current, kind)
+        ),
+        IndexMethod::Trigram => format!(
+            "CREATE INDEX IF NOT EXISTS {} ON projection USING GIN ({} gin_trgm_ops);",
+            index.name(),
+            current
+        ),
+    }
+}
+
+/// The SQL results are ordered by, and the type of its values
+fn sort_sql(sort: &Sort, filter: &FilterSql) -> (String, &'static str) {
+    match &sort.by {
+        SortBy::LastUpdated => ("last_updated".into(), sql_ty(sort.kind)),
+        SortBy::Field(field) => (cast_sql(&field_sql(field), sort.kind), sql_ty(sort.kind)),
+        SortBy::Relevance => match &filter.tsquery {
+            Some(tsquery) => (format!("ts_rank(tsv, {})::float8", tsquery), "float8"),
+            None => ("NULL::float8".into(), "float8"),
+        },
+        SortBy::Similarity => match &filter.similarity {
+            Some(similarity) => (similarity.clone(), "float8"),
+            None => ("NULL::float8".into(), "float8"),
+        },
+    }
+}
+
+/// Escape the LIKE wildcards in `text`
+fn like_escape(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_")
+}
+
+type Query<'q> = sqlx::query::Query<'q, sqlx::Postgres, sqlx::postgres::PgArguments>;
+
+/// The SQL keeping projections that match a search
+struct FilterSql {
+    paths: Vec<String>,
+    /// The `tsquery` of the text query
+    tsquery: Option<String>,
+    /// The similarity of the closest fuzzy field, using `pg_trgm`
+    similarity: Option<String>,
+    /// The first parameter left for the caller
+    next: usize,
+}
+
+/// The SQL keeping projections that match every condition, the text query and the fuzzy query.
+///
+/// Parameters start from `$2`, after the type.
+fn filter_sql(criteria: &Criteria) -> FilterSql {
+    let mut paths: Vec<_> = criteria
+        .conditions
+        .iter()
+        .enumerate()
+        .map(|(i, condition)| condition_sql(condition, i + 2))
+        .collect();
+    let mut next = criteria.conditions.len() + 2;
+
+    let tsquery = criteria.text.as_ref().map(|_| {
+        next += 2;
+        format!(
+            "websearch_to_tsquery(${}::regconfig, ${})",
+            next - 2,
+            next - 1
+        )
+    });
+
+    if let Some(tsquery) = &tsquery {
+        paths.push(format!("tsv @@ {}", tsquery))
+    }
+
+    let similarity = criteria.fuzzy.as_ref().map(|fuzzy| {
+        next += 1;
+
+        let similarities: Vec<_> = fuzzy
+            .fields
+            .iter()
+            .map(|field| {
+                let current = field_sql(&field.field);
+                let similarity = format!("similarity({}, ${})", current, next - 1);
+
+                // `%` matches at pg_trgm's default threshold of 0.3 and, unlike `similarity`,
+                // can use the trigram index, so it narrows down stricter thresholds first
+                let matches = if field.threshold >= 0.3 {
+                    format!(
+                        "({} % ${} AND {} >= {})",
+                        current,
+                        next - 1,
+                        similarity,
+                        field.threshold
+                    )
+                } else {
+                    format!("{} >= {}", similarity, field.threshold)
+                };
+
+                (similarity, matches)
+            })
+            .collect();
+
+        paths.push(format!(
+            "({})",
+            similarities
+                .iter()
+                .map(|(_, matches)| matches.clone())
+                .collect::<Vec<_>>()
+                .join(" OR ")
+        ));
+
+        format!(
+            "GREATEST({})::float8",
+            similarities
+                .into_iter()
+                .map(|(similarity, _)| similarity)
+                .collect::<Vec<_>>()
+                .join(", ")
+        )
+    });
+
+    FilterSql {
+        paths,
+        tsquery,
+        similarity,
+        next,
+    }
+}
+
+/// Bind the parameters of [`filter_sql`]
+fn bind_filter<'q>(query: Query<'q>, criteria: &Criteria) -> Query<'q> {
+    let query = criteria
+        .conditions
+        .iter()
+        .fold(query, |query, condition| match condition.op {
+            Operator::In => query.bind(condition.texts()),
+            Operator::Prefix => query.bind(format!("{}%", like_escape(&condition.text()))),
+            Operator::Contains => query.bind(format!("%{}%", like_escape(&condition.text()))),
+            _ => query.bind(condition.text()),
+        });
+
+    let query = match &criteria.text {
+        Some(text) => query.bind(text.language.clone()).bind(text.query.clone()),
+        None => query,
+    };
+
+    match &criteria.fuzzy {
+        Some(fuzzy) => query.bind(fuzzy.query.clone()),
+        None => query,
+    }
+}
+
+/// The channel transactions notify of each delta they append, see [`Backend::changes`]
+const CHANGES: &str = "yoda_change";
+
+/// A change announced on [`CHANGES`]
+fn changed(payload: &str) -> Option<Changed> {
+    let payload: serde_json::Value = serde_json::from_str(payload).ok()?;
+
+    Some(Changed {
+        ty: payload["ty"].as_str()?.into(),
+        id: payload["id"].as_str()?.into(),
+        version: payload["version"].as_u64()? as usize,
+    })
+}
+
+/// Postgres storage, see `migrations/` for the schema
+pub struct Postgres {
+    pool: sqlx::PgPool,
+    feed: Arc<Feed>,
+    /// Whether a connection is listening on [`CHANGES`] for the feed
+    listening: Arc<AtomicBool>,
+}
+
+impl Postgres {
+    pub fn new(pool: sqlx::PgPool) -> Self {
+        Self {
+            pool,
+            feed: Default::default(),
+            listening: Default::default(),
+        }
+    }
+
+    pub async fn connect(url: &str) -> sqlx::Result<Self> {
+        let pool = sqlx::postgres::PgPoolOptions::new()
+            .max_connections(5)
+            .connect(url)
+            .await?;
+
+        Ok(Self::new(pool))
+    }
+}
+
+pub struct PostgresTransaction(sqlx::Transaction<'static, sqlx::Postgres>);
 
-impl Driver {
-    pub async fn query_proj<T, S>(
-        pool: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+#[async_trait]
+impl Backend for Postgres {
+    async fn begin(&self) -> sqlx::Result<Box<dyn Transaction + '_>> {
+        Ok(Box::new(PostgresTransaction(self.pool.begin().await?)))
+    }
+
+    async fn deltas(
+        &self,
+        ty: &str,
         id: &str,
-    ) -> sqlx::Result<T>
-    where
-        S: serde::de::DeserializeOwned + serde::Serialize + std::fmt::Debug + Store,
-        T: Del<S>,
-    {
-        let sqlx::types::Json(res): sqlx::types::Json<S> = sqlx::query::<sqlx::Postgres>(
+        range: DeltaRange,
+    ) -> sqlx::Result<Vec<DeltaRecord<serde_json::Value>>> {
+        let DeltaRange {
+            as_of,
+            version,
+            skip,
+        } = range;
+
+        sqlx::query::<sqlx::Postgres>(
             "
-            SELECT body FROM projection p
+            SELECT body, author, created_at FROM delta d
             WHERE ty = $1
             AND id = $2
+            AND ($3::timestamptz IS NULL OR created_at <= $3)
+            ORDER BY seq
+            OFFSET $5
+            LIMIT $4::bigint - $5
         ",
         )
-        .bind(S::ty())
+        .bind(ty)
         .bind(convert_id(id)?)
-        .fetch_one(pool)
+        .bind(as_of)
+        .bind(version.map(|version| version as i64))
+        .bind(skip as i64)
+        .fetch_all(&self.pool)
         .await?
-        .try_get("body")?;
-
-        Ok(res.into())
+        .iter()
+        .map(record)
+        .collect()
     }
-    pub async fn query<T, S>(pool: &sqlx::PgPool, id: &str) -> sqlx::Result<Vec<S>>
-    where
-        S: serde::de::DeserializeOwned + serde::Serialize + std::fmt::Debug,
-        T: Del<S>,
-    {
+
+    async fn latest_snapshot(
+        &self,
+        ty: &str,
+        id: &str,
+        as_of: Option<DateTime<Utc>>,
+        version: Option<usize>,
+    ) -> sqlx::Result<Option<(serde_json::Value, usize)>> {
         sqlx::query::<sqlx::Postgres>(
             "
-            SELECT body FROM delta d
+            SELECT body, version FROM snapshot s
             WHERE ty = $1
             AND id = $2
-            order by created_at
+            AND ($3::timestamptz IS NULL OR delta_created_at <= $3)
+            AND ($4::bigint IS NULL OR version <= $4)
+            ORDER BY version DESC
+            LIMIT 1
         ",
         )
-        .bind(T::ty())
+        .bind(ty)
         .bind(convert_id(id)?)
-        .fetch_all(pool)
+        .bind(as_of)
+        .bind(version.map(|version| version as i64))
+        .fetch_optional(&self.pool)
+        .await?
+        .map(|row| {
+            let version: i64 = row.try_get("version")?;
+            Ok((row.try_get("body")?, version as usize))
+        })
+        .transpose()
+    }
+
+    async fn ids(&self, ty: &str, id: Option<&str>) -> sqlx::Result<Vec<String>> {
+        sqlx::query::<sqlx::Postgres>(
+            "
+            SELECT DISTINCT id FROM delta d
+            WHERE ty = $1
+            AND ($2::uuid IS NULL OR id = $2)
+        ",
+        )
+        .bind(ty)
+        .bind(id.map(convert_id).transpose()?)
+        .fetch_all(&self.pool)
         .await?
         .into_iter()
-        .map(|row| -> sqlx::Result<S> {
-            let sqlx::types::Json(res): sqlx::types::Json<S> = row.try_get("body")?;
-            Ok(res)
+        .map(|row| -> sqlx::Result<String> {
+            let id: Uuid = row.try_get("id")?;
+            Ok(id.to_string())
         })
         .collect()
     }
 
-    pub async fn search<T, S>(
-        pool: &sqlx::PgPool,
-        doc: T,
-        pagination: PaginationOption,
-    ) -> sqlx::Result<Vec<S>>
-    where
-        T: Search<S>,
-        S: serde::de::DeserializeOwned + serde::Serialize + std::fmt::Debug,
-    {
-        let array_breakups = doc.array_splits().join("");
+    async fn search(&self, ty: &str, criteria: &Criteria, page: &Page) -> sqlx::Result<Vec<Found>> {
+        let filter = filter_sql(criteria);
 
-        let mut paths = doc
-            .paths()
-            .into_iter()
-            .enumerate()
-            .map(|(i, path)| path.replace('$', format!("${}", i + 2).as_str()))
-            .collect::<Vec<_>>()
-            .join("AND ");
+        let (current, sql_ty) = sort_sql(&page.sort, &filter);
+
+        // Each key takes a parameter for its id, and one for its value when it has one
+        let mut keys = vec![];
+        let mut paths = filter.paths;
+        let mut param = filter.next;
+        for &(key, after) in &[(&page.after, true), (&page.before, false)] {
+            if let Some(key) = key {
+                let value = if key.value.is_null() {
+                    None
+                } else {
+                    param += 1;
+                    Some(format!("${}::{}", param - 1, sql_ty))
+                };
+                let id = format!("${}", param);
+                param += 1;
 
+                paths.push(keyset_sql(
+                    &current,
+                    page.sort.ascending(),
+                    after,
+                    value.as_deref(),
+                    &id,
+                ));
+                keys.push(key);
+            }
+        }
+
+        let mut paths = paths.join(" AND ");
         if !paths.is_empty() {
             paths = format!("AND {}", paths)
         }
 
+        // The last results of a page are found by reading it backwards
+        let direction = if page.sort.ascending() != page.backward {
+            "ASC"
+        } else {
+            "DESC"
+        };
+        let nulls = if page.backward { "FIRST" } else { "LAST" };
+
+        let snippet = match &filter.tsquery {
+            Some(tsquery) => format!(
+                "ts_headline(${}::regconfig, document, {})",
+                criteria.conditions.len() + 2,
+                tsquery
+            ),
+            None => "NULL".into(),
+        };
+        let score = filter.similarity.as_deref().unwrap_or("NULL::float8");
+
         let query_str = format!(
             "
-                SELECT body FROM projection p
-                {}
+                SELECT id::text AS id, body, to_jsonb({1}) AS key, {5} AS snippet, {6} AS score
+                FROM projection p
                 WHERE ty = $1
-                {}
-                ORDER BY last_updated
-                LIMIT {}
-                OFFSET {}
+                {0}
+                ORDER BY {1} {2} NULLS {3}, id {2}
+                LIMIT {4}
             ",
-            array_breakups, paths, pagination.limit, pagination.skip
+            paths, current, direction, nulls, page.limit, snippet, score
         );
 
-        let query = sqlx::query(query_str.as_str()).bind(T::ty());
+        let mut query = bind_filter(sqlx::query(query_str.as_str()).bind(ty), criteria);
+
+        for key in keys {
+            if !key.value.is_null() {
+                query = query.bind(key.text());
+            }
+            query = query.bind(convert_id(&key.id)?);
+        }
 
-        doc.bind_args(query)
-            .fetch_all(pool)
+        let mut found = query
+            .fetch_all(&self.pool)
             .await?
             .into_iter()
             .map(|row| {
-                let sqlx::types::Json(res): sqlx::types::Json<S> = row.try_get("body")?;
-                Ok(res)
+                // Results missing the field have no key value
+                let value: Option<serde_json::Value> = row.try_get("key")?;
+                Ok(Found {
+                    key: Key {
+                        value: value.unwrap_or_default(),
+                        id: row.try_get("id")?,
+                    },
+                    body: row.try_get("body")?,
+                    snippet: row.try_get("snippet")?,
+                    score: row.try_get("score")?,
+                })
             })
-            .collect()
+            .collect::<sqlx::Result<Vec<_>>>()?;
+
+        if page.backward {
+            found.reverse();
+        }
+
+        Ok(found)
+    }
+
+    async fn count(&self, ty: &str, criteria: &Criteria) -> sqlx::Result<usize> {
+        let paths: String = filter_sql(criteria)
+            .paths
+            .iter()
+            .map(|path| format!("AND {}", path))
+            .collect::<Vec<_>>()
+            .join(" ");
+
+        let query_str = format!(
+            "
+                SELECT COUNT(*) AS count FROM projection p
+                WHERE ty = $1
+                {}
+            ",
+            paths
+        );
+
+        let count: i64 = bind_filter(sqlx::query(query_str.as_str()).bind(ty), criteria)
+            .fetch_one(&self.pool)
+            .await?
+            .try_get("count")?;
+
+        Ok(count as usize)
+    }
+
+    async fn projections(
+        &self,
+        ty: &str,
+        ids: &[String],
+    ) -> sqlx::Result<Vec<(String, serde_json::Value)>> {
+        // An id that isn't a uuid can't name a projection
+        let ids: Vec<Uuid> = ids.iter().filter_map(|id| convert_id(id).ok()).collect();
+
+        sqlx::query::<sqlx::Postgres>(
+            "
+            SELECT id::text AS id, body FROM projection
+            WHERE ty = $1
+            AND id = ANY($2)
+        ",
+        )
+        .bind(ty)
+        .bind(ids)
+        .fetch_all(&self.pool)
+        .await?
+        .into_iter()
+        .map(|row| Ok((row.try_get("id")?, row.try_get("body")?)))
+        .collect()
     }
 
-    pub async fn delta<S>(
-        pool: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    async fn find_by_identifier(
+        &self,
+        ty: &str,
+        system: &str,
+        value: &str,
+    ) -> sqlx::Result<Option<String>> {
+        sqlx::query::<sqlx::Postgres>(
+            "
+            SELECT id::text AS id FROM identifier
+            WHERE ty = $1
+            AND system = $2
+            AND value = $3
+        ",
+        )
+        .bind(ty)
+        .bind(system)
+        .bind(value)
+        .fetch_optional(&self.pool)
+        .await?
+        .map(|row| row.try_get("id"))
+        .transpose()
+    }
+
+    async fn find_by_identifiers(
+        &self,
+        ty: &str,
+        identifiers: &[Identifier],
+    ) -> sqlx::Result<Vec<(String, String, String)>> {
+        sqlx::query::<sqlx::Postgres>(
+            "
+            SELECT DISTINCT c.system, c.value, c.id::text AS id FROM identifier c
+            JOIN UNNEST($2::text[], $3::text[]) AS i (system, value)
+            ON c.system = i.system
+            AND c.value = i.value
+            WHERE c.ty = $1
+        ",
+        )
+        .bind(ty)
+        .bind(
+            identifiers
+                .iter()
+                .map(|identifier| identifier.system.to_string())
+                .collect::<Vec<_>>(),
+        )
+        .bind(
+            identifiers
+                .iter()
+                .map(|identifier| identifier.value.clone())
+                .collect::<Vec<_>>(),
+        )
+        .fetch_all(&self.pool)
+        .await?
+        .iter()
+        .map(|row| {
+            Ok((
+                row.try_get("system")?,
+                row.try_get("value")?,
+                row.try_get("id")?,
+            ))
+        })
+        .collect()
+    }
+
+    async fn referrers(
+        &self,
+        identifiers: &[Identifier],
+        reference_type: Option<ReferenceType>,
+    ) -> sqlx::Result<Vec<Referrer>> {
+        sqlx::query::<sqlx::Postgres>(
+            "
+            SELECT DISTINCT r.ty, r.id::text AS id, r.field, r.reference_ty FROM reference r
+            JOIN UNNEST($1::text[], $2::text[]) AS i (system, value)
+            ON r.system = i.system
+            AND r.value = i.value
+            WHERE ($3::text IS NULL OR r.reference_ty = $3)
+            ORDER BY r.ty, id, r.field, r.reference_ty
+        ",
+        )
+        .bind(
+            identifiers
+                .iter()
+                .map(|identifier| identifier.system.to_string())
+                .collect::<Vec<_>>(),
+        )
+        .bind(
+            identifiers
+                .iter()
+                .map(|identifier| identifier.value.clone())
+                .collect::<Vec<_>>(),
+        )
+        .bind(reference_type.map(|reference_type| reference_type.to_string()))
+        .fetch_all(&self.pool)
+        .await?
+        .iter()
+        .map(|row| {
+            referrer(
+                row.try_get("ty")?,
+                row.try_get("id")?,
+                row.try_get("field")?,
+                row.try_get("reference_ty")?,
+            )
+        })
+        .collect()
+    }
+
+    async fn missing_indexes(&self, declared: &[Index]) -> sqlx::Result<Vec<Index>> {
+        let existing = sqlx::query::<sqlx::Postgres>(
+            "SELECT indexname FROM pg_indexes WHERE tablename = 'projection'",
+        )
+        .fetch_all(&self.pool)
+        .await?
+        .iter()
+        .map(|row| row.try_get("indexname"))
+        .collect::<sqlx::Result<Vec<String>>>()?;
+
+        Ok(declared
+            .iter()
+            .filter(|index| !existing.contains(&index.name()))
+            .cloned()
+            .collect())
+    }
+
+    /// The changes committed by every server sharing the database.
+    ///
+    /// One connection of the pool listens on their behalf from the first subscription on.
+    /// Should it fail for good, every subscriber's stream ends and the next subscription
+    /// listens again.
+    async fn changes(&self) -> sqlx::Result<BoxStream<'static, Changed>> {
+        if self
+            .listening
+            .compare_exchange(false, true, Ordering::SeqCst, Ordering::SeqCst)
+            .is_ok()
+        {
+            let listener = async {
+                let mut listener = PgListener::connect_with(&self.pool).await?;
+                listener.listen(CHANGES).await?;
+                Ok::<_, sqlx::Error>(listener)
+            };
+
+            let mut listener = match listener.await {
+                Ok(listener) => listener,
+                Err(e) => {
+                    self.listening.store(false, Ordering::SeqCst);
+                    return Err(e);
+                }
+            };
+
+            let feed = self.feed.clone();
+            let listening = self.listening.clone();
+
+            async_std::task::spawn(async move {
+                // Lost connections are reestablished while receiving
+                loop {
+                    match listener.recv().await {
+                        Ok(notification) => match changed(notification.payload()) {
+                            Some(changed) => feed.publish(vec![changed]),
+                            None => log::warn!("Unexpected change: {}", notification.payload()),
+                        },
+                        Err(e) => {
+                            log::warn!("Stopped listening for changes: {}", e);
+                            break;
+                        }
+                    }
+                }
+
+                listening.store(false, Ordering::SeqCst);
+                feed.close();
+            });
+        }
+
+        Ok(self.feed.subscribe())
+    }
+}
+
+#[async_trait]
+impl Transaction for PostgresTransaction {
+    async fn projection(&mut self, ty: &str, id: &str) -> sqlx::Result<Option<serde_json::Value>> {
+        sqlx::query::<sqlx::Postgres>(
+            "
+            SELECT body FROM projection p
+            WHERE ty = $1
+            AND id = $2
+            FOR UPDATE
+        ",
+        )
+        .bind(ty)
+        .bind(convert_id(id)?)
+        .fetch_optional(&mut self.0)
+        .await?
+        .map(|row| row.try_get("body"))
+        .transpose()
+    }
+
+    async fn lock(&mut self, ty: &str, id: &str) -> sqlx::Result<()> {
+        sqlx::query("SELECT pg_advisory_xact_lock(hashtextextended($1 || ':' || $2, 0))")
+            .bind(ty)
+            .bind(id)
+            .execute(&mut self.0)
+            .await?;
+
+        Ok(())
+    }
+
+    async fn deltas(
+        &mut self,
+        ty: &str,
         id: &str,
-        doc: S,
-        author: &auth::Identity,
-    ) -> sqlx::Result<()>
-    where
-        S: Serialize + Send + Store,
-    {
+    ) -> sqlx::Result<Vec<DeltaRecord<serde_json::Value>>> {
+        sqlx::query::<sqlx::Postgres>(
+            "
+            SELECT body, author, created_at FROM delta
+            WHERE ty = $1
+            AND id = $2
+            ORDER BY seq
+        ",
+        )
+        .bind(ty)
+        .bind(convert_id(id)?)
+        .fetch_all(&mut self.0)
+        .await?
+        .iter()
+        .map(record)
+        .collect()
+    }
+
+    async fn append(
+        &mut self,
+        ty: &str,
+        id: &str,
+        body: serde_json::Value,
+        author: &str,
+    ) -> sqlx::Result<()> {
         sqlx::query(
             "
             INSERT INTO delta
@@ -129,37 +793,259 @@ impl Driver {
         ",
         )
         .bind(convert_id(id)?)
-        .bind(S::ty())
-        .bind(sqlx::types::Json(doc))
-        .bind(&author.user_id)
-        .execute(pool)
+        .bind(ty)
+        .bind(body)
+        .bind(author)
+        .execute(&mut self.0)
+        .await?;
+
+        // Postgres holds notifications back until the transaction commits
+        sqlx::query(
+            "
+            SELECT pg_notify($1, json_build_object(
+                'ty', $2::text,
+                'id', $3::text,
+                'version', (SELECT COUNT(*) FROM delta WHERE ty = $2 AND id = $3::uuid)
+            )::text)
+        ",
+        )
+        .bind(CHANGES)
+        .bind(ty)
+        .bind(id)
+        .execute(&mut self.0)
         .await?;
 
         Ok(())
     }
 
-    pub async fn project<S>(
-        pool: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    async fn project(
+        &mut self,
+        ty: &str,
         id: &str,
-        doc: S,
-    ) -> sqlx::Result<()>
-    where
-        S: Serialize + Send + Store,
-    {
+        body: serde_json::Value,
+        document: Option<&Document>,
+    ) -> sqlx::Result<()> {
         sqlx::query(
             "
             INSERT INTO projection
-            (id, ty, body)
-            VALUES ($1, $2, $3)
-            ON CONFLICT (id) DO UPDATE SET body = EXCLUDED.body
+            (id, ty, body, document, tsv)
+            VALUES ($1, $2, $3, $4, to_tsvector($5::regconfig, $4))
+            ON CONFLICT (id) DO UPDATE
+            SET body = EXCLUDED.body, document = EXCLUDED.document, tsv = EXCLUDED.tsv
+        ",
+        )
+        .bind(convert_id(id)?)
+        .bind(ty)
+        .bind(body)
+        .bind(document.map(|document| document.text.clone()))
+        .bind(document.map(|document| document.language.clone()))
+        .execute(&mut self.0)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn unproject(&mut self, ty: &str, id: &str) -> sqlx::Result<()> {
+        sqlx::query("DELETE FROM projection WHERE ty = $1 AND id = $2")
+            .bind(ty)
+            .bind(convert_id(id)?)
+            .execute(&mut self.0)
+            .await?;
+
+        sqlx::query("DELETE FROM reference WHERE ty = $1 AND id = $2")
+            .bind(ty)
+            .bind(convert_id(id)?)
+            .execute(&mut self.0)
+            .await?;
+
+        Ok(())
+    }
+
+    async fn purge(&mut self, ty: &str, id: &str) -> sqlx::Result<()> {
+        self.unproject(ty, id).await?;
+
+        for table in &["snapshot", "identifier"] {
+            sqlx::query(&format!("DELETE FROM {} WHERE ty = $1 AND id = $2", table))
+                .bind(ty)
+                .bind(convert_id(id)?)
+                .execute(&mut self.0)
+                .await?;
+        }
+
+        sqlx::query(
+            "
+            UPDATE delta
+            SET body = CASE
+                WHEN body ? 'lifecycle' THEN jsonb_build_object('lifecycle', body -> 'lifecycle')
+                ELSE '{}'::jsonb
+            END
+            WHERE ty = $1
+            AND id = $2
         ",
         )
+        .bind(ty)
         .bind(convert_id(id)?)
-        .bind(S::ty())
-        .bind(sqlx::types::Json(doc))
-        .execute(pool)
+        .execute(&mut self.0)
         .await?;
 
         Ok(())
     }
+
+    async fn redact(
+        &mut self,
+        ty: &str,
+        id: &str,
+        fields: &[String],
+        marker: &serde_json::Value,
+    ) -> sqlx::Result<()> {
+        for table in &["delta", "snapshot", "projection"] {
+            for field in fields {
+                sqlx::query(&format!(
+                    "
+                    UPDATE {}
+                    SET body = jsonb_set(body, ARRAY[$3], $4)
+                    WHERE ty = $1
+                    AND id = $2
+                    AND jsonb_typeof(body -> $3) = 'object'
+                ",
+                    table
+                ))
+                .bind(ty)
+                .bind(convert_id(id)?)
+                .bind(field)
+                .bind(marker)
+                .execute(&mut self.0)
+                .await?;
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn claimant(&mut self, ty: &str, claim: &Claim) -> sqlx::Result<Option<String>> {
+        sqlx::query(
+            "
+            SELECT id::text AS id FROM identifier
+            WHERE ty = $1
+            AND system = $2
+            AND value = $3
+        ",
+        )
+        .bind(ty)
+        .bind(&claim.key)
+        .bind(&claim.value)
+        .fetch_optional(&mut self.0)
+        .await?
+        .map(|row| row.try_get("id"))
+        .transpose()
+    }
+
+    async fn claim(
+        &mut self,
+        ty: &str,
+        id: &str,
+        claims: &[Claim],
+    ) -> sqlx::Result<Option<Duplicate>> {
+        sqlx::query("DELETE FROM identifier WHERE ty = $1 AND id = $2")
+            .bind(ty)
+            .bind(convert_id(id)?)
+            .execute(&mut self.0)
+            .await?;
+
+        for claim in claims {
+            // Waits on a concurrent insert of the same value, then skips it if committed
+            let inserted = sqlx::query(
+                "
+                INSERT INTO identifier
+                (ty, system, value, id)
+                VALUES ($1, $2, $3, $4)
+                ON CONFLICT DO NOTHING
+            ",
+            )
+            .bind(ty)
+            .bind(&claim.key)
+            .bind(&claim.value)
+            .bind(convert_id(id)?)
+            .execute(&mut self.0)
+            .await?
+            .rows_affected();
+
+            if inserted == 0 {
+                return Ok(Some(Duplicate {
+                    ty: ty.into(),
+                    key: claim.key.clone(),
+                    value: claim.value.clone(),
+                    id: self.claimant(ty, claim).await?.unwrap_or_default(),
+                }));
+            }
+        }
+
+        Ok(None)
+    }
+
+    async fn refer(&mut self, ty: &str, id: &str, referrals: &[Referral]) -> sqlx::Result<()> {
+        sqlx::query("DELETE FROM reference WHERE ty = $1 AND id = $2")
+            .bind(ty)
+            .bind(convert_id(id)?)
+            .execute(&mut self.0)
+            .await?;
+
+        for referral in referrals {
+            let identifier = referral.reference.identifier();
+
+            sqlx::query(
+                "
+                INSERT INTO reference
+                (ty, id, field, reference_ty, system, value)
+                VALUES ($1, $2, $3, $4, $5, $6)
+            ",
+            )
+            .bind(ty)
+            .bind(convert_id(id)?)
+            .bind(&referral.field)
+            .bind(referral.reference.reference_type().to_string())
+            .bind(identifier.system.to_string())
+            .bind(&identifier.value)
+            .execute(&mut self.0)
+            .await?;
+        }
+
+        Ok(())
+    }
+
+    async fn snapshot(
+        &mut self,
+        ty: &str,
+        id: &str,
+        every: u64,
+        body: serde_json::Value,
+    ) -> sqlx::Result<()> {
+        sqlx::query(
+            "
+            WITH covered AS (
+                SELECT COUNT(*) AS version, MAX(created_at) AS delta_created_at
+                FROM delta
+                WHERE ty = $1
+                AND id = $2
+            )
+            INSERT INTO snapshot
+            (id, ty, body, version, delta_created_at)
+            SELECT $2, $1, $3, version, delta_created_at FROM covered
+            WHERE version % $4 = 0
+            ON CONFLICT DO NOTHING
+        ",
+        )
+        .bind(ty)
+        .bind(convert_id(id)?)
+        .bind(body)
+        .bind(every as i64)
+        .execute(&mut self.0)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn commit(self: Box<Self>) -> sqlx::Result<()> {
+        self.0.commit().await
+    }
 }
@@ -0,0 +1,986 @@
+use std::str::FromStr;
+
+use async_trait::async_trait;
+use atoms::{
+    delta::{Claim, Duplicate},
+    history::DeltaRecord,
+    pagination::{Key, Page, Sort, SortBy},
+    search::{Condition, Criteria, Document, Index, IndexMethod, Kind, Operator, TextQuery},
+    Identifier, ReferenceType, Referral, Referrer,
+};
+use futures::{
+    lock::{Mutex, MutexGuard},
+    stream::BoxStream,
+};
+use sqlx::{
+    sqlite::{SqliteConnectOptions, SqlitePoolOptions},
+    types::{
+        chrono::{DateTime, Utc},
+        Json,
+    },
+    Row,
+};
+
+use crate::backend::{
+    keyset_sql, page_of, referrer, Backend, Changed, DeltaRange, Feed, Found, Transaction,
+};
+
+/// SQLite storage in a single file, see `migrations/sqlite/` for the schema.
+///
+/// SQLite has no row locks, so transactions run one at a time like [`crate::memory::Memory`].
+/// Changes are only seen by subscribers in the process that wrote them.
+pub struct Sqlite {
+    pool: sqlx::SqlitePool,
+    writer: Mutex<()>,
+    feed: Feed,
+}
+
+impl Sqlite {
+    pub fn new(pool: sqlx::SqlitePool) -> Self {
+        Self {
+            pool,
+            writer: Mutex::new(()),
+            feed: Feed::default(),
+        }
+    }
+
+    /// Open (or create) the database at `url` and bring its schema up to date
+    pub async fn connect(url: &str) -> sqlx::Result<Self> {
+        let options = SqliteConnectOptions::from_str(url)?
+            .create_if_missing(true)
+            .busy_timeout(std::time::Duration::from_secs(5));
+
+        // Every connection to an in-memory database would get its own empty database
+        let max_connections = if url.contains(":memory:") { 1 } else { 5 };
+
+        let pool = SqlitePoolOptions::new()
+            .max_connections(max_connections)
+            .connect_with(options)
+            .await?;
+
+        sqlx::migrate!("../migrations/sqlite").run(&pool).await?;
+
+        Ok(Self::new(pool))
+    }
+}
+
+pub struct SqliteTransaction<'a> {
+    transaction: sqlx::Transaction<'static, sqlx::Sqlite>,
+    _writer: MutexGuard<'a, ()>,
+    feed: &'a Feed,
+    changed: Vec<Changed>,
+}
+
+/// The SQL for a search condition, comparing against parameter `?param`.
+///
+/// Values are bound as JSON so they compare with the same types `json_extract` gives the body.
+fn condition_sql(condition: &Condition, param: usize) -> String {
+    if !condition.is_nested() {
+        let path = format!("$.{}.end", condition.field).replace('\'', "''");
+
+        return compare_sql(condition, format!("json_extract(body, '{}')", path), param);
+    }
+
+    // Step through the path one key at a time, searching each element of the lists met
+    let (field, keys) = condition.path();
+    let steps: Vec<_> = std::iter::once(("body".to_string(), format!("$.{}.end", field)))
+        .chain(
+            keys.iter()
+                .enumerate()
+                .map(|(i, key)| (format!("e{}.value", i), format!("$.{}", key))),
+        )
+        .enumerate()
+        .map(|(i, (holder, path))| {
+            let path = path.replace('\'', "''");
+            format!(
+                "json_each(CASE json_type({0}, '{1}') WHEN 'array' THEN json_extract({0}, '{1}') \
+                 ELSE json_array(json_extract({0}, '{1}')) END) AS e{2}",
+                holder, path, i
+            )
+        })
+        .collect();
+
+    format!(
+        "EXISTS (SELECT 1 FROM {} WHERE {})",
+        steps.join(", "),
+        compare_sql(condition, format!("e{}.value", keys.len()), param)
+    )
+}
+
+/// Compare `current` with parameter `?param`
+fn compare_sql(condition: &Condition, current: String, param: usize) -> String {
+    // Timestamps are compared as instants, whatever their offset or precision
+    let (current, value, each) = match condition.kind {
+        Kind::DateTime => (
+            format!("julianday({})", current),
+            format!("julianday(json_extract(?{}, '$'))", param),
+            format!("SELECT julianday(value) FROM json_each(?{})", param),
+        ),
+        _ => (
+            current,
+            format!("json_extract(?{}, '$')", param),
+            format!("SELECT value FROM json_each(?{})", param),
+        ),
+    };
+
+    let compare = |op: &str| format!("{} {} {}", current, op, value);
+
+    match condition.op {
+        Operator::Eq => compare("="),
+        Operator::In => format!("{} IN ({})", current, each),
+        Operator::Gt => compare(">"),
+        Operator::Gte => compare(">="),
+        Operator::Lt => compare("<"),
+        Operator::Lte => compare("<="),
+        Operator::Prefix => format!("substr({0}, 1, length(?{1})) = ?{1}", current, param),
+        Operator::Contains => format!("instr(lower({}), lower(?{})) > 0", current, param),
+    }
+}
+
+type Query<'q> = sqlx::query::Query<'q, sqlx::Sqlite, sqlx::sqlite::SqliteArguments<'q>>;
+
+/// The SQL keeping projections that match every condition and contain every word of the
+/// text query, with how often the words occur when there is one.
+///
+/// The fuzzy query is left out, SQLite having nothing like `pg_trgm`.
+/// Parameters start from `?2`, after the type, with one for each word.
+fn filter_sql(criteria: &Criteria) -> (Vec<String>, Option<String>) {
+    let conditions = &criteria.conditions;
+    let text = criteria.text.as_ref();
+
+    let mut paths: Vec<_> = conditions
+        .iter()
+        .enumerate()
+        .map(|(i, condition)| condition_sql(condition, i + 2))
+        .collect();
+
+    let params: Vec<_> = text
+        .map(TextQuery::words)
+        .unwrap_or_default()
+        .iter()
+        .enumerate()
+        .map(|(i, _)| conditions.len() + 2 + i)
+        .collect();
+
+    paths.extend(
+        params
+            .iter()
+            .map(|param| format!("instr(lower(document), ?{}) > 0", param)),
+    );
+
+    let rank = text.map(|_| {
+        params
+            .iter()
+            .map(|param| {
+                format!(
+                    "(length(lower(document)) - length(replace(lower(document), ?{0}, ''))) \
+                     / length(?{0})",
+                    param
+                )
+            })
+            .chain(std::iter::once("0".to_string()))
+            .collect::<Vec<_>>()
+            .join(" + ")
+    });
+
+    (paths, rank)
+}
+
+/// Bind the parameters of [`filter_sql`]
+fn bind_filter<'q>(query: Query<'q>, criteria: &Criteria) -> Query<'q> {
+    let query = criteria
+        .conditions
+        .iter()
+        .fold(query, |query, condition| match condition.op {
+            Operator::Prefix | Operator::Contains => query.bind(condition.text()),
+            _ => query.bind(Json(condition.value.clone())),
+        });
+
+    criteria
+        .text
+        .as_ref()
+        .map(TextQuery::words)
+        .unwrap_or_default()
+        .into_iter()
+        .fold(query, |query, word| query.bind(word))
+}
+
+/// The value results are ordered by, and the SQL they are compared on
+fn sort_sql(sort: &Sort, rank: Option<&str>) -> (String, String) {
+    match &sort.by {
+        SortBy::LastUpdated => ("last_updated".into(), "last_updated".into()),
+        SortBy::Relevance => {
+            let rank = rank.unwrap_or("NULL").to_string();
+            (rank.clone(), rank)
+        }
+        // Only known once a fuzzy search is scored in memory
+        SortBy::Similarity => ("NULL".into(), "NULL".into()),
+        SortBy::Field(field) => {
+            let value = format!(
+                "json_extract(body, '{}')",
+                format!("$.{}.end", field).replace('\'', "''")
+            );
+            match sort.kind {
+                Kind::DateTime => (value.clone(), format!("julianday({})", value)),
+                _ => (value.clone(), value),
+            }
+        }
+    }
+}
+
+/// The statement creating an index, for a migration.
+///
+/// SQLite has no trigram indexes, leaving those out.
+pub fn index_sql(index: &Index) -> Option<String> {
+    let current = format!(
+        "json_extract(body, '{}')",
+        format!("$.{}.end", index.field).replace('\'', "''")
+    );
+
+    let current = match index.method {
+        IndexMethod::Value(Kind::DateTime) => format!("julianday({})", current),
+        IndexMethod::Value(_) => current,
+        IndexMethod::Trigram => return None,
+    };
+
+    Some(format!(
+        "CREATE INDEX IF NOT EXISTS {} ON projection (ty, {});",
+        index.name(),
+        current
+    ))
+}
+
+fn body(row: &sqlx::sqlite::SqliteRow) -> sqlx::Result<serde_json::Value> {
+    let Json(body) = row.try_get("body")?;
+    Ok(body)
+}
+
+fn record(row: &sqlx::sqlite::SqliteRow) -> sqlx::Result<DeltaRecord<serde_json::Value>> {
+    Ok(DeltaRecord {
+        body: body(row)?,
+        author: row.try_get("author")?,
+        created_at: row.try_get("created_at")?,
+    })
+}
+
+#[async_trait]
+impl Backend for Sqlite {
+    async fn begin(&self) -> sqlx::Result<Box<dyn Transaction + '_>> {
+        let writer = self.writer.lock().await;
+
+        Ok(Box::new(SqliteTransaction {
+            transaction: self.pool.begin().await?,
+            _writer: writer,
+            feed: &self.feed,
+            changed: vec![],
+        }))
+    }
+
+    async fn deltas(
+        &self,
+        ty: &str,
+        id: &str,
+        range: DeltaRange,
+    ) -> sqlx::Result<Vec<DeltaRecord<serde_json::Value>>> {
+        let DeltaRange {
+            as_of,
+            version,
+            skip,
+        } = range;
+
+        sqlx::query::<sqlx::Sqlite>(
+            "
+            SELECT body, author, created_at FROM delta
+            WHERE ty = ?1
+            AND id = ?2
+            AND (?3 IS NULL OR created_at <= ?3)
+            ORDER BY created_at, rowid
+            LIMIT ?4
+            OFFSET ?5
+        ",
+        )
+        .bind(ty)
+        .bind(id)
+        .bind(as_of)
+        .bind(
+            version
+                .map(|version| version.saturating_sub(skip) as i64)
+                .unwrap_or(-1),
+        )
+        .bind(skip as i64)
+        .fetch_all(&self.pool)
+        .await?
+        .iter()
+        .map(record)
+        .collect()
+    }
+
+    async fn latest_snapshot(
+        &self,
+        ty: &str,
+        id: &str,
+        as_of: Option<DateTime<Utc>>,
+        version: Option<usize>,
+    ) -> sqlx::Result<Option<(serde_json::Value, usize)>> {
+        sqlx::query::<sqlx::Sqlite>(
+            "
+            SELECT body, version FROM snapshot
+            WHERE ty = ?1
+            AND id = ?2
+            AND (?3 IS NULL OR delta_created_at <= ?3)
+            AND (?4 IS NULL OR version <= ?4)
+            ORDER BY version DESC
+            LIMIT 1
+        ",
+        )
+        .bind(ty)
+        .bind(id)
+        .bind(as_of)
+        .bind(version.map(|version| version as i64))
+        .fetch_optional(&self.pool)
+        .await?
+        .map(|row| {
+            let version: i64 = row.try_get("version")?;
+            Ok((body(&row)?, version as usize))
+        })
+        .transpose()
+    }
+
+    async fn ids(&self, ty: &str, id: Option<&str>) -> sqlx::Result<Vec<String>> {
+        sqlx::query::<sqlx::Sqlite>(
+            "
+            SELECT DISTINCT id FROM delta
+            WHERE ty = ?1
+            AND (?2 IS NULL OR id = ?2)
+        ",
+        )
+        .bind(ty)
+        .bind(id)
+        .fetch_all(&self.pool)
+        .await?
+        .into_iter()
+        .map(|row| row.try_get("id"))
+        .collect()
+    }
+
+    async fn search(&self, ty: &str, criteria: &Criteria, page: &Page) -> sqlx::Result<Vec<Found>> {
+        let text = criteria.text.as_ref();
+        let (mut paths, rank) = filter_sql(criteria);
+
+        let (value, current) = sort_sql(&page.sort, rank.as_deref());
+
+        // Fuzzy searches are scored in memory, so their results are also sorted and paged there
+        let fuzzy = criteria.fuzzy.as_ref();
+
+        // Each key takes a parameter for its id, and one for its value when it has one
+        let mut keys = vec![];
+        let mut param = criteria.conditions.len() + 2 + text.map_or(0, |text| text.words().len());
+        for &(key, after) in &[(&page.after, true), (&page.before, false)] {
+            if let Some(key) = key.as_ref().filter(|_| fuzzy.is_none()) {
+                let value = if key.value.is_null() {
+                    None
+                } else {
+                    param += 1;
+                    Some(match (&page.sort.by, page.sort.kind) {
+                        (SortBy::Field(_), Kind::DateTime) => {
+                            format!("julianday(json_extract(?{}, '$'))", param - 1)
+                        }
+                        _ => format!("json_extract(?{}, '$')", param - 1),
+                    })
+                };
+                let id = format!("?{}", param);
+                param += 1;
+
+                paths.push(keyset_sql(
+                    &current,
+                    page.sort.ascending(),
+                    after,
+                    value.as_deref(),
+                    &id,
+                ));
+                keys.push(key);
+            }
+        }
+
+        let mut paths = paths.join(" AND ");
+        if !paths.is_empty() {
+            paths = format!("AND {}", paths)
+        }
+
+        // The last results of a page are found by reading it backwards
+        let direction = if page.sort.ascending() != page.backward {
+            "ASC"
+        } else {
+            "DESC"
+        };
+        let nulls = if page.backward { "FIRST" } else { "LAST" };
+
+        let order = match fuzzy {
+            Some(_) => String::new(),
+            None => format!(
+                "ORDER BY {0} {1} NULLS {2}, id {1} LIMIT {3}",
+                current, direction, nulls, page.limit
+            ),
+        };
+
+        let query_str = format!(
+            "
+                SELECT id, body, document, json_quote({1}) AS key FROM projection
+                WHERE ty = ?1
+                {0}
+                {2}
+            ",
+            paths, value, order
+        );
+
+        let mut query = bind_filter(sqlx::query(query_str.as_str()).bind(ty), criteria);
+
+        for key in keys {
+            if !key.value.is_null() {
+                query = query.bind(Json(key.value.clone()));
+            }
+            query = query.bind(key.id.clone());
+        }
+
+        let mut found = query
+            .fetch_all(&self.pool)
+            .await?
+            .iter()
+            .map(|row| {
+                let Json(value) = row.try_get("key")?;
+                let document: Option<String> = row.try_get("document")?;
+                Ok(Found {
+                    key: Key {
+                        value,
+                        id: row.try_get("id")?,
+                    },
+                    body: body(row)?,
+                    snippet: text.map(|text| text.highlight(&document.unwrap_or_default())),
+                    score: None,
+                })
+            })
+            .collect::<sqlx::Result<Vec<_>>>()?;
+
+        if let Some(fuzzy) = fuzzy {
+            let found = found
+                .into_iter()
+                .filter_map(|mut found| {
+                    found.score = Some(fuzzy.score(&found.body)?);
+                    if page.sort.by == SortBy::Similarity {
+                        found.key.value = serde_json::json!(found.score);
+                    }
+                    Some(found)
+                })
+                .collect();
+
+            return Ok(page_of(found, page));
+        }
+
+        if page.backward {
+            found.reverse();
+        }
+
+        Ok(found)
+    }
+
+    async fn count(&self, ty: &str, criteria: &Criteria) -> sqlx::Result<usize> {
+        let (paths, _) = filter_sql(criteria);
+
+        let paths: String = paths
+            .iter()
+            .map(|path| format!("AND {}", path))
+            .collect::<Vec<_>>()
+            .join(" ");
+
+        if let Some(fuzzy) = &criteria.fuzzy {
+            let query_str = format!(
+                "
+                    SELECT body FROM projection
+                    WHERE ty = ?1
+                    {}
+                ",
+                paths
+            );
+
+            let rows = bind_filter(sqlx::query(query_str.as_str()).bind(ty), criteria)
+                .fetch_all(&self.pool)
+                .await?;
+
+            let mut count = 0;
+            for row in rows.iter() {
+                if fuzzy.score(&body(row)?).is_some() {
+                    count += 1;
+                }
+            }
+
+            return Ok(count);
+        }
+
+        let query_str = format!(
+            "
+                SELECT COUNT(*) AS count FROM projection
+                WHERE ty = ?1
+                {}
+            ",
+            paths
+        );
+
+        let count: i64 = bind_filter(sqlx::query(query_str.as_str()).bind(ty), criteria)
+            .fetch_one(&self.pool)
+            .await?
+            .try_get("count")?;
+
+        Ok(count as usize)
+    }
+
+    async fn projections(
+        &self,
+        ty: &str,
+        ids: &[String],
+    ) -> sqlx::Result<Vec<(String, serde_json::Value)>> {
+        sqlx::query::<sqlx::Sqlite>(
+            "
+            SELECT id, body FROM projection
+            WHERE ty = ?1
+            AND id IN (SELECT value FROM json_each(?2))
+        ",
+        )
+        .bind(ty)
+        .bind(Json(ids))
+        .fetch_all(&self.pool)
+        .await?
+        .iter()
+        .map(|row| Ok((row.try_get("id")?, body(row)?)))
+        .collect()
+    }
+
+    async fn find_by_identifier(
+        &self,
+        ty: &str,
+        system: &str,
+        value: &str,
+    ) -> sqlx::Result<Option<String>> {
+        sqlx::query::<sqlx::Sqlite>(
+            "
+            SELECT id FROM identifier
+            WHERE ty = ?1
+            AND system = ?2
+            AND value = ?3
+        ",
+        )
+        .bind(ty)
+        .bind(system)
+        .bind(value)
+        .fetch_optional(&self.pool)
+        .await?
+        .map(|row| row.try_get("id"))
+        .transpose()
+    }
+
+    async fn find_by_identifiers(
+        &self,
+        ty: &str,
+        identifiers: &[Identifier],
+    ) -> sqlx::Result<Vec<(String, String, String)>> {
+        sqlx::query::<sqlx::Sqlite>(
+            "
+            SELECT DISTINCT c.system, c.value, c.id FROM identifier c
+            JOIN json_each(?2) i
+            ON c.system = json_extract(i.value, '$.system')
+            AND c.value = json_extract(i.value, '$.value')
+            WHERE c.ty = ?1
+        ",
+        )
+        .bind(ty)
+        .bind(Json(identifiers))
+        .fetch_all(&self.pool)
+        .await?
+        .iter()
+        .map(|row| {
+            Ok((
+                row.try_get("system")?,
+                row.try_get("value")?,
+                row.try_get("id")?,
+            ))
+        })
+        .collect()
+    }
+
+    async fn referrers(
+        &self,
+        identifiers: &[Identifier],
+        reference_type: Option<ReferenceType>,
+    ) -> sqlx::Result<Vec<Referrer>> {
+        sqlx::query::<sqlx::Sqlite>(
+            "
+            SELECT DISTINCT r.ty, r.id, r.field, r.reference_ty FROM reference r
+            JOIN json_each(?1) i
+            ON r.system = json_extract(i.value, '$.system')
+            AND r.value = json_extract(i.value, '$.value')
+            WHERE (?2 IS NULL OR r.reference_ty = ?2)
+            ORDER BY r.ty, r.id, r.field, r.reference_ty
+        ",
+        )
+        .bind(Json(identifiers))
+        .bind(reference_type.map(|reference_type| reference_type.to_string()))
+        .fetch_all(&self.pool)
+        .await?
+        .iter()
+        .map(|row| {
+            referrer(
+                row.try_get("ty")?,
+                row.try_get("id")?,
+                row.try_get("field")?,
+                row.try_get("reference_ty")?,
+            )
+        })
+        .collect()
+    }
+
+    async fn missing_indexes(&self, declared: &[Index]) -> sqlx::Result<Vec<Index>> {
+        let existing = sqlx::query::<sqlx::Sqlite>(
+            "SELECT name FROM sqlite_master WHERE type = 'index' AND tbl_name = 'projection'",
+        )
+        .fetch_all(&self.pool)
+        .await?
+        .iter()
+        .map(|row| row.try_get("name"))
+        .collect::<sqlx::Result<Vec<String>>>()?;
+
+        Ok(declared
+            .iter()
+            .filter(|index| index_sql(index).is_some() && !existing.contains(&index.name()))
+            .cloned()
+            .collect())
+    }
+
+    async fn changes(&self) -> sqlx::Result<BoxStream<'static, Changed>> {
+        Ok(self.feed.subscribe())
+    }
+}
+
+#[async_trait]
+impl Transaction for SqliteTransaction<'_> {
+    async fn projection(&mut self, ty: &str, id: &str) -> sqlx::Result<Option<serde_json::Value>> {
+        sqlx::query::<sqlx::Sqlite>(
+            "
+            SELECT body FROM projection
+            WHERE ty = ?1
+            AND id = ?2
+        ",
+        )
+        .bind(ty)
+        .bind(id)
+        .fetch_optional(&mut self.transaction)
+        .await?
+        .map(|row| body(&row))
+        .transpose()
+    }
+
+    /// Transactions already run one at a time
+    async fn lock(&mut self, _ty: &str, _id: &str) -> sqlx::Result<()> {
+        Ok(())
+    }
+
+    async fn deltas(
+        &mut self,
+        ty: &str,
+        id: &str,
+    ) -> sqlx::Result<Vec<DeltaRecord<serde_json::Value>>> {
+        sqlx::query::<sqlx::Sqlite>(
+            "
+            SELECT body, author, created_at FROM delta
+            WHERE ty = ?1
+            AND id = ?2
+            ORDER BY created_at, rowid
+        ",
+        )
+        .bind(ty)
+        .bind(id)
+        .fetch_all(&mut self.transaction)
+        .await?
+        .iter()
+        .map(record)
+        .collect()
+    }
+
+    async fn append(
+        &mut self,
+        ty: &str,
+        id: &str,
+        body: serde_json::Value,
+        author: &str,
+    ) -> sqlx::Result<()> {
+        sqlx::query(
+            "
+            INSERT INTO delta
+            (id, ty, body, author, created_at)
+            VALUES (?1, ?2, ?3, ?4, ?5)
+        ",
+        )
+        .bind(id)
+        .bind(ty)
+        .bind(Json(body))
+        .bind(author)
+        .bind(Utc::now())
+        .execute(&mut self.transaction)
+        .await?;
+
+        let version: i64 = sqlx::query::<sqlx::Sqlite>(
+            "SELECT COUNT(*) AS count FROM delta WHERE ty = ?1 AND id = ?2",
+        )
+        .bind(ty)
+        .bind(id)
+        .fetch_one(&mut self.transaction)
+        .await?
+        .try_get("count")?;
+
+        self.changed.push(Changed {
+            ty: ty.into(),
+            id: id.into(),
+            version: version as usize,
+        });
+
+        Ok(())
+    }
+
+    async fn project(
+        &mut self,
+        ty: &str,
+        id: &str,
+        body: serde_json::Value,
+        document: Option<&Document>,
+    ) -> sqlx::Result<()> {
+        sqlx::query(
+            "
+            INSERT INTO projection
+            (id, ty, body, last_updated, document)
+            VALUES (?1, ?2, ?3, ?4, ?5)
+            ON CONFLICT (id) DO UPDATE SET body = excluded.body, document = excluded.document
+        ",
+        )
+        .bind(id)
+        .bind(ty)
+        .bind(Json(body))
+        .bind(Utc::now())
+        .bind(document.map(|document| document.text.clone()))
+        .execute(&mut self.transaction)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn unproject(&mut self, ty: &str, id: &str) -> sqlx::Result<()> {
+        sqlx::query("DELETE FROM projection WHERE ty = ?1 AND id = ?2")
+            .bind(ty)
+            .bind(id)
+            .execute(&mut self.transaction)
+            .await?;
+
+        sqlx::query("DELETE FROM reference WHERE ty = ?1 AND id = ?2")
+            .bind(ty)
+            .bind(id)
+            .execute(&mut self.transaction)
+            .await?;
+
+        Ok(())
+    }
+
+    async fn purge(&mut self, ty: &str, id: &str) -> sqlx::Result<()> {
+        self.unproject(ty, id).await?;
+
+        for table in &["snapshot", "identifier"] {
+            sqlx::query(&format!("DELETE FROM {} WHERE ty = ?1 AND id = ?2", table))
+                .bind(ty)
+                .bind(id)
+                .execute(&mut self.transaction)
+                .await?;
+        }
+
+        sqlx::query(
+            "
+            UPDATE delta
+            SET body = CASE
+                WHEN json_type(body, '$.lifecycle') IS NOT NULL
+                THEN json_object('lifecycle', json_extract(body, '$.lifecycle'))
+                ELSE '{}'
+            END
+            WHERE ty = ?1
+            AND id = ?2
+        ",
+        )
+        .bind(ty)
+        .bind(id)
+        .execute(&mut self.transaction)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn redact(
+        &mut self,
+        ty: &str,
+        id: &str,
+        fields: &[String],
+        marker: &serde_json::Value,
+    ) -> sqlx::Result<()> {
+        for table in &["delta", "snapshot", "projection"] {
+            for field in fields {
+                sqlx::query(&format!(
+                    "
+                    UPDATE {}
+                    SET body = json_set(body, '$.' || ?3, json(?4))
+                    WHERE ty = ?1
+                    AND id = ?2
+                    AND json_type(body, '$.' || ?3) = 'object'
+                ",
+                    table
+                ))
+                .bind(ty)
+                .bind(id)
+                .bind(field)
+                .bind(Json(marker))
+                .execute(&mut self.transaction)
+                .await?;
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn claimant(&mut self, ty: &str, claim: &Claim) -> sqlx::Result<Option<String>> {
+        sqlx::query(
+            "
+            SELECT id FROM identifier
+            WHERE ty = ?1
+            AND system = ?2
+            AND value = ?3
+        ",
+        )
+        .bind(ty)
+        .bind(&claim.key)
+        .bind(&claim.value)
+        .fetch_optional(&mut self.transaction)
+        .await?
+        .map(|row| row.try_get("id"))
+        .transpose()
+    }
+
+    async fn claim(
+        &mut self,
+        ty: &str,
+        id: &str,
+        claims: &[Claim],
+    ) -> sqlx::Result<Option<Duplicate>> {
+        sqlx::query("DELETE FROM identifier WHERE ty = ?1 AND id = ?2")
+            .bind(ty)
+            .bind(id)
+            .execute(&mut self.transaction)
+            .await?;
+
+        for claim in claims {
+            let inserted = sqlx::query(
+                "
+                INSERT INTO identifier
+                (ty, system, value, id)
+                VALUES (?1, ?2, ?3, ?4)
+                ON CONFLICT DO NOTHING
+            ",
+            )
+            .bind(ty)
+            .bind(&claim.key)
+            .bind(&claim.value)
+            .bind(id)
+            .execute(&mut self.transaction)
+            .await?
+            .rows_affected();
+
+            if inserted == 0 {
+                return Ok(Some(Duplicate {
+                    ty: ty.into(),
+                    key: claim.key.clone(),
+                    value: claim.value.clone(),
+                    id: self.claimant(ty, claim).await?.unwrap_or_default(),
+                }));
+            }
+        }
+
+        Ok(None)
+    }
+
+    async fn refer(&mut self, ty: &str, id: &str, referrals: &[Referral]) -> sqlx::Result<()> {
+        sqlx::query("DELETE FROM reference WHERE ty = ?1 AND id = ?2")
+            .bind(ty)
+            .bind(id)
+            .execute(&mut self.transaction)
+            .await?;
+
+        for referral in referrals {
+            let identifier = referral.reference.identifier();
+
+            sqlx::query(
+                "
+                INSERT INTO reference
+                (ty, id, field, reference_ty, system, value)
+                VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+            ",
+            )
+            .bind(ty)
+            .bind(id)
+            .bind(&referral.field)
+            .bind(referral.reference.reference_type().to_string())
+            .bind(identifier.system.to_string())
+            .bind(&identifier.value)
+            .execute(&mut self.transaction)
+            .await?;
+        }
+
+        Ok(())
+    }
+
+    async fn snapshot(
+        &mut self,
+        ty: &str,
+        id: &str,
+        every: u64,
+        body: serde_json::Value,
+    ) -> sqlx::Result<()> {
+        sqlx::query(
+            "
+            WITH covered AS (
+                SELECT COUNT(*) AS version, MAX(created_at) AS delta_created_at
+                FROM delta
+                WHERE ty = ?1
+                AND id = ?2
+            )
+            INSERT OR IGNORE INTO snapshot
+            (id, ty, body, version, delta_created_at, created_at)
+            SELECT ?2, ?1, ?3, version, delta_created_at, ?5 FROM covered
+            WHERE version % ?4 = 0
+        ",
+        )
+        .bind(ty)
+        .bind(id)
+        .bind(Json(body))
+        .bind(every as i64)
+        .bind(Utc::now())
+        .execute(&mut self.transaction)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn commit(self: Box<Self>) -> sqlx::Result<()> {
+        let SqliteTransaction {
+            transaction,
+            feed,
+            changed,
+            ..
+        } = *self;
+
+        transaction.commit().await?;
+        feed.publish(changed);
+
+        Ok(())
+    }
+}
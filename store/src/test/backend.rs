@@ -0,0 +1,923 @@
+use atoms::{
+    delta::{Claim, Duplicate},
+    pagination::{Direction, Key, Page, Sort, SortBy},
+    search::{Condition, Criteria, Document, FuzzyField, FuzzyQuery, Kind, Operator, TextQuery},
+    Identifier, IdentifierSystem, IdentifierTier, Reference, ReferenceType, Referral, Referrer,
+};
+use futures::{FutureExt, StreamExt};
+use serde_json::json;
+
+use crate::{Backend, Changed, DeltaRange};
+
+/// Scenarios every [`Backend`] must pass
+pub(super) async fn all(db: &dyn Backend) {
+    uncommitted_writes_are_discarded(db).await;
+    deltas_respect_range(db).await;
+    transactions_read_their_own_deltas(db).await;
+    snapshot_taken_every_n_deltas(db).await;
+    search_matches_every_condition(db).await;
+    search_compares_by_type(db).await;
+    search_within_lists_and_structs(db).await;
+    search_sorts_by_field(db).await;
+    search_pages_by_key(db).await;
+    search_text_by_relevance(db).await;
+    search_fuzzy_by_similarity(db).await;
+    claims_are_unique(db).await;
+    purge_redacts_deltas(db).await;
+    redact_replaces_fields(db).await;
+    projections_by_id(db).await;
+    referrers_by_identifier(db).await;
+    changes_follow_commits(db).await;
+}
+
+/// Ids are unique across types, and uuids so the Postgres backend runs the same scenarios
+pub(super) fn id(n: u8) -> String {
+    format!("00000000-0000-0000-0000-{:012}", n)
+}
+
+/// A reference of type `ty` to the identifier `value` from `system`
+pub(super) fn reference(ty: &str, system: &str, value: &str) -> Reference {
+    serde_json::from_value(json!({
+        "ty": ty,
+        "value": { "value": value, "system": system, "tier": "Primary" },
+    }))
+    .unwrap()
+}
+
+fn page(limit: usize) -> Page {
+    Page {
+        limit,
+        ..Default::default()
+    }
+}
+
+fn body(name: &str) -> serde_json::Value {
+    json!({ "name": { "start": null, "end": name } })
+}
+
+async fn write(db: &dyn Backend, ty: &str, id: &str, name: &str) {
+    let mut transaction = db.begin().await.unwrap();
+    transaction
+        .append(ty, id, body(name), "tester")
+        .await
+        .unwrap();
+    transaction.project(ty, id, body(name), None).await.unwrap();
+    transaction.snapshot(ty, id, 2, body(name)).await.unwrap();
+    transaction.commit().await.unwrap();
+}
+
+async fn uncommitted_writes_are_discarded(db: &dyn Backend) {
+    {
+        let mut transaction = db.begin().await.unwrap();
+        transaction
+            .append("Uncommitted", &id(1), body("Boston"), "tester")
+            .await
+            .unwrap();
+        transaction
+            .project("Uncommitted", &id(1), body("Boston"), None)
+            .await
+            .unwrap();
+    }
+
+    let ids = db.ids("Uncommitted", None).await.unwrap();
+    assert!(ids.is_empty());
+
+    let found = db
+        .search("Uncommitted", &Criteria::default(), &page(10))
+        .await
+        .unwrap();
+    assert!(found.is_empty());
+}
+
+async fn deltas_respect_range(db: &dyn Backend) {
+    write(db, "Range", &id(2), "Boston").await;
+    write(db, "Range", &id(2), "Cambridge").await;
+    write(db, "Range", &id(2), "Somerville").await;
+
+    let deltas = db
+        .deltas(
+            "Range",
+            &id(2),
+            DeltaRange {
+                version: Some(2),
+                skip: 1,
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(deltas.len(), 1);
+    assert_eq!(deltas[0].body, body("Cambridge"));
+    assert_eq!(deltas[0].author, "tester");
+
+    let all = db
+        .deltas("Range", &id(2), Default::default())
+        .await
+        .unwrap();
+    assert_eq!(all.len(), 3);
+
+    let before = db
+        .deltas(
+            "Range",
+            &id(2),
+            DeltaRange {
+                as_of: Some(all[1].created_at),
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+    assert_eq!(before.len(), 2);
+}
+
+async fn transactions_read_their_own_deltas(db: &dyn Backend) {
+    write(db, "Staged", &id(44), "Boston").await;
+
+    let mut transaction = db.begin().await.unwrap();
+    transaction
+        .append("Staged", &id(44), body("Cambridge"), "tester")
+        .await
+        .unwrap();
+
+    let bodies: Vec<_> = transaction
+        .deltas("Staged", &id(44))
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|record| record.body)
+        .collect();
+    assert_eq!(bodies, vec![body("Boston"), body("Cambridge")]);
+}
+
+async fn snapshot_taken_every_n_deltas(db: &dyn Backend) {
+    write(db, "Snapshot", &id(3), "Boston").await;
+    write(db, "Snapshot", &id(3), "Cambridge").await;
+    write(db, "Snapshot", &id(3), "Somerville").await;
+
+    let latest = db
+        .latest_snapshot("Snapshot", &id(3), None, None)
+        .await
+        .unwrap();
+    assert_eq!(latest, Some((body("Cambridge"), 2)));
+
+    let before = db
+        .latest_snapshot("Snapshot", &id(3), None, Some(1))
+        .await
+        .unwrap();
+    assert_eq!(before, None);
+}
+
+async fn search_matches_every_condition(db: &dyn Backend) {
+    write(db, "Search", &id(4), "Boston").await;
+    write(db, "Search", &id(5), "Cambridge").await;
+    write(db, "Search", &id(6), "Boston Harbor").await;
+    write(db, "Other", &id(7), "Boston").await;
+
+    let search = |conditions: Vec<Condition>, after: Option<Key>| async move {
+        db.search("Search", &conditions.into(), &Page { after, ..page(10) })
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|found| found.body)
+            .collect::<Vec<_>>()
+    };
+
+    let boston = || {
+        vec![Condition::new(
+            "name",
+            Operator::Contains,
+            Kind::Text,
+            "boston",
+        )]
+    };
+
+    assert_eq!(
+        search(boston(), None).await,
+        vec![body("Boston"), body("Boston Harbor")]
+    );
+    let first = db
+        .search("Search", &boston().into(), &page(1))
+        .await
+        .unwrap();
+    assert_eq!(first.len(), 1);
+    assert_eq!(
+        search(boston(), Some(first[0].key.clone())).await,
+        vec![body("Boston Harbor")]
+    );
+    assert_eq!(search(vec![], None).await.len(), 3);
+    assert_eq!(db.count("Search", &boston().into()).await.unwrap(), 2);
+    assert_eq!(db.count("Search", &Criteria::default()).await.unwrap(), 3);
+    assert!(search(
+        vec![Condition::new(
+            "name",
+            Operator::Contains,
+            Kind::Text,
+            "Salem"
+        )],
+        None
+    )
+    .await
+    .is_empty());
+}
+
+async fn search_compares_by_type(db: &dyn Backend) {
+    let txn = |amount: u32, completed: bool, at: &str| {
+        json!({
+            "amount": { "start": null, "end": amount },
+            "completed": { "start": null, "end": completed },
+            "at": { "start": null, "end": at },
+        })
+    };
+
+    for (n, body) in vec![
+        txn(5, true, "2021-06-01T12:00:00Z"),
+        txn(15, false, "2021-06-01T12:00:00.5Z"),
+        txn(50, true, "2021-06-01T14:00:00+02:00"),
+    ]
+    .into_iter()
+    .enumerate()
+    {
+        let id = id(8 + n as u8);
+        let mut transaction = db.begin().await.unwrap();
+        transaction.project("Txn", &id, body, None).await.unwrap();
+        transaction.commit().await.unwrap();
+    }
+
+    let amounts = |conditions: Vec<Condition>| async move {
+        db.search("Txn", &conditions.into(), &page(10))
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|found| found.body["amount"]["end"].as_u64().unwrap())
+            .collect::<Vec<_>>()
+    };
+
+    let amount = |op, value: u32| Condition::new("amount", op, Kind::Number, value);
+
+    assert_eq!(amounts(vec![amount(Operator::Eq, 5)]).await, vec![5]);
+    assert_eq!(amounts(vec![amount(Operator::Gt, 5)]).await, vec![15, 50]);
+    assert_eq!(
+        amounts(vec![amount(Operator::Gte, 5), amount(Operator::Lt, 50)]).await,
+        vec![5, 15]
+    );
+    assert_eq!(
+        amounts(vec![Condition::new(
+            "amount",
+            Operator::In,
+            Kind::Number,
+            vec![5, 50]
+        )])
+        .await,
+        vec![5, 50]
+    );
+    assert_eq!(
+        amounts(vec![Condition::new(
+            "completed",
+            Operator::Eq,
+            Kind::Boolean,
+            true
+        )])
+        .await,
+        vec![5, 50]
+    );
+    assert_eq!(
+        amounts(vec![Condition::new(
+            "at",
+            Operator::Lte,
+            Kind::DateTime,
+            "2021-06-01T12:00:00Z"
+        )])
+        .await,
+        vec![5, 50]
+    );
+    assert_eq!(
+        amounts(vec![Condition::new(
+            "at",
+            Operator::In,
+            Kind::DateTime,
+            vec!["2021-06-01T12:00:00.500Z"]
+        )])
+        .await,
+        vec![15]
+    );
+}
+
+async fn search_within_lists_and_structs(db: &dyn Backend) {
+    let account = |tags: serde_json::Value, address: serde_json::Value| {
+        json!({
+            "tag": { "start": null, "end": tags },
+            "address": { "start": null, "end": address },
+        })
+    };
+
+    for (n, body) in vec![
+        account(
+            json!(["Religious", "Education"]),
+            json!([{ "city": "Cambridge", "number": 5 }, { "city": "Boston", "number": 10 }]),
+        ),
+        account(
+            json!(["Politics"]),
+            json!({ "city": "Boston", "number": 5 }),
+        ),
+        account(json!([]), json!([{ "city": "Salem", "number": 15 }])),
+    ]
+    .into_iter()
+    .enumerate()
+    {
+        let id = id(11 + n as u8);
+        let mut transaction = db.begin().await.unwrap();
+        transaction
+            .project("Nested", &id, body, None)
+            .await
+            .unwrap();
+        transaction.commit().await.unwrap();
+    }
+
+    let found = |conditions: Vec<Condition>| async move {
+        db.search("Nested", &conditions.into(), &page(10))
+            .await
+            .unwrap()
+            .len()
+    };
+
+    let tag = |value| Condition::new("tag", Operator::Eq, Kind::Text, value).any();
+    let city = |value| Condition::new("city", Operator::Eq, Kind::Text, value).within("address");
+
+    assert_eq!(found(vec![tag("Education")]).await, 1);
+    assert_eq!(
+        db.count("Nested", &vec![city("Boston")].into())
+            .await
+            .unwrap(),
+        2
+    );
+    assert_eq!(
+        found(vec![Condition::new(
+            "tag",
+            Operator::In,
+            Kind::Text,
+            vec!["Education", "Politics"]
+        )
+        .any()])
+        .await,
+        2
+    );
+    assert_eq!(found(vec![city("Boston")]).await, 2);
+    assert_eq!(found(vec![city("Boston"), tag("Politics")]).await, 1);
+    assert_eq!(
+        found(vec![Condition::new(
+            "number",
+            Operator::Gt,
+            Kind::Number,
+            10
+        )
+        .within("address")])
+        .await,
+        1
+    );
+    assert_eq!(
+        found(vec![Condition::new(
+            "city",
+            Operator::Contains,
+            Kind::Text,
+            "SAL"
+        )
+        .within("address")])
+        .await,
+        1
+    );
+}
+
+/// Writes amounts 5, 15, nothing, 15 and 50 to five ids from `first`
+pub(super) async fn write_amounts(db: &dyn Backend, ty: &str, first: u8) {
+    for (n, amount) in vec![json!(5), json!(15), json!(null), json!(15), json!(50)]
+        .into_iter()
+        .enumerate()
+    {
+        let body = if amount.is_null() {
+            json!({})
+        } else {
+            json!({ "amount": { "start": null, "end": amount } })
+        };
+
+        let mut transaction = db.begin().await.unwrap();
+        transaction
+            .project(ty, &id(first + n as u8), body, None)
+            .await
+            .unwrap();
+        transaction.commit().await.unwrap();
+    }
+}
+
+pub(super) fn by_amount(direction: Direction) -> Sort {
+    Sort {
+        by: SortBy::Field("amount".into()),
+        kind: Kind::Number,
+        direction,
+    }
+}
+
+async fn keys(db: &dyn Backend, ty: &str, page: Page) -> Vec<Key> {
+    db.search(ty, &Criteria::default(), &page)
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|found| found.key)
+        .collect()
+}
+
+fn ids(keys: &[Key]) -> Vec<String> {
+    keys.iter().map(|key| key.id.clone()).collect()
+}
+
+async fn search_sorts_by_field(db: &dyn Backend) {
+    write_amounts(db, "Sorted", 14).await;
+
+    let ascending = keys(
+        db,
+        "Sorted",
+        Page {
+            sort: by_amount(Direction::Asc),
+            ..page(10)
+        },
+    )
+    .await;
+    assert_eq!(
+        ids(&ascending),
+        vec![id(14), id(15), id(17), id(18), id(16)]
+    );
+    assert_eq!(ascending[0].value, json!(5));
+    assert!(ascending[4].value.is_null());
+
+    // Ties are broken by id in the same direction, and missing values stay last
+    let descending = keys(
+        db,
+        "Sorted",
+        Page {
+            sort: by_amount(Direction::Desc),
+            ..page(10)
+        },
+    )
+    .await;
+    assert_eq!(
+        ids(&descending),
+        vec![id(18), id(17), id(15), id(14), id(16)]
+    );
+}
+
+async fn search_pages_by_key(db: &dyn Backend) {
+    write_amounts(db, "Paged", 19).await;
+
+    let all = keys(
+        db,
+        "Paged",
+        Page {
+            sort: by_amount(Direction::Asc),
+            ..page(10)
+        },
+    )
+    .await;
+
+    let paged = |after: Option<usize>, before: Option<usize>, limit, backward| {
+        let page = Page {
+            sort: by_amount(Direction::Asc),
+            after: after.map(|i| all[i].clone()),
+            before: before.map(|i| all[i].clone()),
+            limit,
+            backward,
+        };
+        async move { ids(&keys(db, "Paged", page).await) }
+    };
+
+    assert_eq!(paged(None, None, 2, false).await, ids(&all[..2]));
+    assert_eq!(paged(Some(1), None, 2, false).await, ids(&all[2..4]));
+    assert_eq!(paged(Some(3), None, 2, false).await, ids(&all[4..]));
+    assert!(paged(Some(4), None, 2, false).await.is_empty());
+
+    assert_eq!(paged(None, None, 2, true).await, ids(&all[3..]));
+    assert_eq!(paged(None, Some(3), 2, true).await, ids(&all[1..3]));
+    assert_eq!(paged(None, Some(1), 2, true).await, ids(&all[..1]));
+    assert_eq!(paged(None, Some(4), 10, true).await, ids(&all[..4]));
+
+    assert_eq!(paged(Some(0), Some(3), 10, false).await, ids(&all[1..3]));
+
+    let descending = keys(
+        db,
+        "Paged",
+        Page {
+            sort: by_amount(Direction::Desc),
+            after: Some(all[2].clone()),
+            ..page(10)
+        },
+    )
+    .await;
+    assert_eq!(
+        ids(&descending),
+        vec![all[1].id.clone(), all[0].id.clone(), all[4].id.clone()]
+    );
+}
+
+async fn search_text_by_relevance(db: &dyn Backend) {
+    for (n, text) in vec![
+        Some("Boston food bank\nFeeding families in Boston"),
+        Some("Cambridge library\nBooks for Boston and Cambridge"),
+        Some("Salem museum"),
+        None,
+    ]
+    .into_iter()
+    .enumerate()
+    {
+        let document = text.map(|text| Document {
+            language: "english".into(),
+            text: text.into(),
+        });
+
+        let mut transaction = db.begin().await.unwrap();
+        transaction
+            .project("Text", &id(24 + n as u8), json!({}), document.as_ref())
+            .await
+            .unwrap();
+        transaction.commit().await.unwrap();
+    }
+
+    let query = |query: &str| Criteria {
+        text: Some(TextQuery {
+            language: "english".into(),
+            query: query.into(),
+        }),
+        ..Default::default()
+    };
+
+    let found = db
+        .search(
+            "Text",
+            &query("boston"),
+            &Page {
+                sort: Sort::relevance(),
+                ..page(10)
+            },
+        )
+        .await
+        .unwrap();
+    assert_eq!(
+        found
+            .iter()
+            .map(|found| found.key.id.clone())
+            .collect::<Vec<_>>(),
+        vec![id(24), id(25)]
+    );
+    assert!(found[0].snippet.as_ref().unwrap().contains("<b>Boston</b>"));
+
+    let after = db
+        .search(
+            "Text",
+            &query("boston"),
+            &Page {
+                sort: Sort::relevance(),
+                after: Some(found[0].key.clone()),
+                ..page(10)
+            },
+        )
+        .await
+        .unwrap();
+    assert_eq!(after.len(), 1);
+    assert_eq!(after[0].key.id, id(25));
+
+    assert_eq!(db.count("Text", &query("boston")).await.unwrap(), 2);
+    assert_eq!(db.count("Text", &query("Boston food")).await.unwrap(), 1);
+    assert_eq!(db.count("Text", &Criteria::default()).await.unwrap(), 4);
+}
+
+async fn search_fuzzy_by_similarity(db: &dyn Backend) {
+    write(db, "Fuzzy", &id(28), "Boston Food Bank").await;
+    write(db, "Fuzzy", &id(29), "Boston").await;
+    write(db, "Fuzzy", &id(30), "Cambridge").await;
+
+    let fuzzy = |threshold| Criteria {
+        fuzzy: Some(FuzzyQuery {
+            query: "Bostn".into(),
+            fields: vec![FuzzyField {
+                field: "name".into(),
+                threshold,
+            }],
+        }),
+        ..Default::default()
+    };
+
+    let found = db
+        .search(
+            "Fuzzy",
+            &fuzzy(0.2),
+            &Page {
+                sort: Sort::similarity(),
+                ..page(10)
+            },
+        )
+        .await
+        .unwrap();
+    assert_eq!(
+        found
+            .iter()
+            .map(|found| found.key.id.clone())
+            .collect::<Vec<_>>(),
+        vec![id(29), id(28)]
+    );
+    assert!(found[0].score.unwrap() > found[1].score.unwrap());
+    assert_eq!(found[0].key.value, json!(found[0].score));
+
+    let after = db
+        .search(
+            "Fuzzy",
+            &fuzzy(0.2),
+            &Page {
+                sort: Sort::similarity(),
+                after: Some(found[0].key.clone()),
+                ..page(10)
+            },
+        )
+        .await
+        .unwrap();
+    assert_eq!(after.len(), 1);
+    assert_eq!(after[0].key.id, id(28));
+
+    assert_eq!(db.count("Fuzzy", &fuzzy(0.2)).await.unwrap(), 2);
+    assert_eq!(db.count("Fuzzy", &fuzzy(0.4)).await.unwrap(), 1);
+}
+
+async fn claims_are_unique(db: &dyn Backend) {
+    let stripe_identifier = |value: &str| {
+        let mut identifier = Identifier::new(IdentifierSystem::Stripe, IdentifierTier::Secondary);
+        identifier.value = value.into();
+        identifier
+    };
+    let stripe = |value: &str| Claim::identifier(&stripe_identifier(value));
+    let find = |value: &'static str| db.find_by_identifier("Ident", "Stripe", value);
+
+    let mut transaction = db.begin().await.unwrap();
+    let duplicate = transaction
+        .claim(
+            "Ident",
+            &id(31),
+            &[stripe("cus_1"), Claim::field("email", &"a@yoda.dev")],
+        )
+        .await
+        .unwrap();
+    assert_eq!(duplicate, None);
+    transaction.commit().await.unwrap();
+
+    assert_eq!(find("cus_1").await.unwrap(), Some(id(31)));
+    let identifiers = [stripe_identifier("cus_1"), stripe_identifier("cus_9")];
+    assert_eq!(
+        db.find_by_identifiers("Ident", &identifiers).await.unwrap(),
+        vec![("Stripe".into(), "cus_1".into(), id(31))]
+    );
+    assert_eq!(
+        db.find_by_identifier("Other", "Stripe", "cus_1")
+            .await
+            .unwrap(),
+        None
+    );
+
+    {
+        let mut transaction = db.begin().await.unwrap();
+        let duplicate = transaction
+            .claim(
+                "Ident",
+                &id(32),
+                &[stripe("cus_3"), Claim::field("email", &"a@yoda.dev")],
+            )
+            .await
+            .unwrap();
+        assert_eq!(
+            duplicate,
+            Some(Duplicate {
+                ty: "Ident".into(),
+                key: "email".into(),
+                value: "a@yoda.dev".into(),
+                id: id(31),
+            })
+        );
+    }
+
+    // Claiming again replaces the entity's claims, freeing the ones it lost
+    let mut transaction = db.begin().await.unwrap();
+    let freed = transaction
+        .claim("Ident", &id(31), &[stripe("cus_2")])
+        .await
+        .unwrap();
+    let taken = transaction
+        .claim(
+            "Ident",
+            &id(32),
+            &[stripe("cus_1"), Claim::field("email", &"a@yoda.dev")],
+        )
+        .await
+        .unwrap();
+    assert_eq!((freed, taken), (None, None));
+    // The transaction sees its own claims before they are committed
+    assert_eq!(
+        transaction
+            .claimant("Ident", &stripe("cus_1"))
+            .await
+            .unwrap(),
+        Some(id(32))
+    );
+    transaction.commit().await.unwrap();
+
+    assert_eq!(find("cus_1").await.unwrap(), Some(id(32)));
+    assert_eq!(find("cus_2").await.unwrap(), Some(id(31)));
+    assert_eq!(find("cus_3").await.unwrap(), None);
+}
+
+async fn purge_redacts_deltas(db: &dyn Backend) {
+    let tombstone = json!({ "lifecycle": { "start": null, "end": "Deleted" } });
+
+    write(db, "Purged", &id(33), "Boston").await;
+    write(db, "Purged", &id(33), "Cambridge").await;
+
+    let mut transaction = db.begin().await.unwrap();
+    transaction
+        .claim("Purged", &id(33), &[Claim::field("name", &"Cambridge")])
+        .await
+        .unwrap();
+    transaction
+        .append("Purged", &id(33), tombstone.clone(), "tester")
+        .await
+        .unwrap();
+    transaction.unproject("Purged", &id(33)).await.unwrap();
+    transaction.commit().await.unwrap();
+
+    assert_eq!(db.count("Purged", &Criteria::default()).await.unwrap(), 0);
+    // Deleting keeps the rest, so the entity can be restored
+    assert!(db
+        .latest_snapshot("Purged", &id(33), None, None)
+        .await
+        .unwrap()
+        .is_some());
+
+    let mut transaction = db.begin().await.unwrap();
+    transaction.purge("Purged", &id(33)).await.unwrap();
+    transaction.commit().await.unwrap();
+
+    let bodies: Vec<_> = db
+        .deltas("Purged", &id(33), Default::default())
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|delta| delta.body)
+        .collect();
+    assert_eq!(bodies, vec![json!({}), json!({}), tombstone]);
+    assert!(db
+        .latest_snapshot("Purged", &id(33), None, None)
+        .await
+        .unwrap()
+        .is_none());
+
+    let mut transaction = db.begin().await.unwrap();
+    let duplicate = transaction
+        .claim("Purged", &id(34), &[Claim::field("name", &"Cambridge")])
+        .await
+        .unwrap();
+    assert_eq!(duplicate, None);
+}
+
+async fn redact_replaces_fields(db: &dyn Backend) {
+    let marker = json!({ "start": null, "end": null, "redacted": true });
+    let untouched = json!({ "name": null, "amount": { "start": null, "end": 1 } });
+
+    write(db, "Redacted", &id(35), "Boston").await;
+    write(db, "Redacted", &id(35), "Cambridge").await;
+    let mut transaction = db.begin().await.unwrap();
+    transaction
+        .append("Redacted", &id(35), untouched.clone(), "tester")
+        .await
+        .unwrap();
+    transaction
+        .redact("Redacted", &id(35), &["name".into()], &marker)
+        .await
+        .unwrap();
+    transaction.commit().await.unwrap();
+
+    let bodies: Vec<_> = db
+        .deltas("Redacted", &id(35), Default::default())
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|delta| delta.body)
+        .collect();
+    let redacted = json!({ "name": marker });
+    assert_eq!(bodies, vec![redacted.clone(), redacted.clone(), untouched]);
+
+    let (snapshot, _) = db
+        .latest_snapshot("Redacted", &id(35), None, None)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(snapshot, redacted);
+
+    let found = db
+        .search("Redacted", &Criteria::default(), &page(10))
+        .await
+        .unwrap();
+    assert_eq!(found[0].body, redacted);
+}
+
+async fn projections_by_id(db: &dyn Backend) {
+    write(db, "Projected", &id(36), "Boston").await;
+    write(db, "Projected", &id(37), "Cambridge").await;
+    write(db, "Elsewhere", &id(38), "Somerville").await;
+
+    let mut found = db
+        .projections("Projected", &[id(37), id(36), id(38), id(99)])
+        .await
+        .unwrap();
+    found.sort_by(|a, b| a.0.cmp(&b.0));
+    assert_eq!(
+        found,
+        vec![(id(36), body("Boston")), (id(37), body("Cambridge"))]
+    );
+
+    let found = db.projections("Projected", &[]).await.unwrap();
+    assert!(found.is_empty());
+}
+
+async fn referrers_by_identifier(db: &dyn Backend) {
+    let owner = reference("User", "Yoda", &id(41));
+    let payment = reference("StripeTransaction", "Stripe", "ch_1");
+    let referral = |field: &str, reference: &Reference| Referral {
+        field: field.into(),
+        reference: reference.clone(),
+    };
+    let referrer = |id: String, field: &str, reference_type| Referrer {
+        ty: "Referring".into(),
+        id,
+        field: field.into(),
+        reference_type,
+    };
+
+    let mut transaction = db.begin().await.unwrap();
+    transaction
+        .refer(
+            "Referring",
+            &id(39),
+            &[referral("owner", &owner), referral("payments", &payment)],
+        )
+        .await
+        .unwrap();
+    transaction
+        .refer("Referring", &id(40), &[referral("owner", &owner)])
+        .await
+        .unwrap();
+    transaction.commit().await.unwrap();
+
+    let identifiers = vec![owner.identifier().clone(), payment.identifier().clone()];
+    assert_eq!(
+        db.referrers(&identifiers, None).await.unwrap(),
+        vec![
+            referrer(id(39), "owner", ReferenceType::User),
+            referrer(id(39), "payments", ReferenceType::StripeTransaction),
+            referrer(id(40), "owner", ReferenceType::User),
+        ]
+    );
+    assert_eq!(
+        db.referrers(&identifiers, Some(ReferenceType::StripeTransaction))
+            .await
+            .unwrap(),
+        vec![referrer(
+            id(39),
+            "payments",
+            ReferenceType::StripeTransaction
+        )]
+    );
+
+    let mut transaction = db.begin().await.unwrap();
+    transaction.refer("Referring", &id(40), &[]).await.unwrap();
+    transaction.unproject("Referring", &id(39)).await.unwrap();
+    transaction.commit().await.unwrap();
+
+    assert!(db.referrers(&identifiers, None).await.unwrap().is_empty());
+}
+
+async fn changes_follow_commits(db: &dyn Backend) {
+    let mut changes = db.changes().await.unwrap();
+
+    {
+        let mut transaction = db.begin().await.unwrap();
+        transaction
+            .append("Changing", &id(42), body("Boston"), "tester")
+            .await
+            .unwrap();
+    }
+    write(db, "Changing", &id(43), "Boston").await;
+    write(db, "Changing", &id(43), "Cambridge").await;
+
+    let changed = |version| Changed {
+        ty: "Changing".into(),
+        id: id(43),
+        version,
+    };
+    assert_eq!(changes.next().await, Some(changed(1)));
+    assert_eq!(changes.next().await, Some(changed(2)));
+    // The discarded delta was never announced
+    assert_eq!(changes.next().now_or_never(), None);
+}
@@ -0,0 +1,601 @@
+use atoms::{
+    delta::{Claim, Dangling, Del, Delta, DeltaConflict, Integrity, Lifecycle, Relation, Store},
+    pagination::{Direction, Page},
+    search::{Condition, Search},
+    Reference,
+};
+use futures::{executor::block_on, StreamExt};
+use serde_json::json;
+use sqlx::types::chrono::Utc;
+
+use super::backend::{by_amount, id, reference, write_amounts};
+use crate::{memory::Memory, Backend, Database, Driver, Found};
+
+struct Everything;
+
+impl Search<serde_json::Value> for Everything {
+    fn ty() -> String {
+        "Amounts".into()
+    }
+    fn conditions(&self) -> Vec<Condition> {
+        vec![]
+    }
+}
+
+fn page(limit: usize, backward: bool) -> Page {
+    Page {
+        sort: by_amount(Direction::Asc),
+        limit,
+        backward,
+        ..Default::default()
+    }
+}
+
+#[test]
+fn search_tells_whether_more_results() {
+    block_on(async {
+        let db = Memory::new();
+        write_amounts(&db, "Amounts", 1).await;
+
+        let ids = |found: Vec<Found<serde_json::Value>>| {
+            found
+                .into_iter()
+                .map(|found| found.key.id)
+                .collect::<Vec<_>>()
+        };
+
+        let (found, more) = Driver::search(&db, Everything, &page(4, false))
+            .await
+            .unwrap();
+        assert_eq!(ids(found), vec![id(1), id(2), id(4), id(5)]);
+        assert!(more);
+
+        let (found, more) = Driver::search(&db, Everything, &page(5, false))
+            .await
+            .unwrap();
+        assert_eq!(found.len(), 5);
+        assert!(!more);
+
+        let (found, more) = Driver::search(&db, Everything, &page(2, true))
+            .await
+            .unwrap();
+        assert_eq!(ids(found), vec![id(5), id(3)]);
+        assert!(more);
+
+        assert_eq!(Driver::count(&db, &Everything).await.unwrap(), 5);
+    })
+}
+
+/// Refers to `Amounts` through a strict and a loose relation
+#[derive(serde::Serialize, serde::Deserialize, Debug)]
+struct Holder {
+    strict: Vec<Reference>,
+    loose: Vec<Reference>,
+}
+
+impl Store for Holder {
+    fn ty() -> String {
+        "Holders".into()
+    }
+    fn identifier(&self) -> String {
+        String::new()
+    }
+    fn claims(&self) -> Vec<Claim> {
+        vec![]
+    }
+    fn lifecycle(&self) -> Option<Lifecycle> {
+        None
+    }
+    fn tombstone(_: Lifecycle) -> Self {
+        Self {
+            strict: vec![],
+            loose: vec![],
+        }
+    }
+    fn relations(&self) -> Vec<Relation> {
+        vec![
+            Relation {
+                field: "strict".into(),
+                ty: "Amounts".into(),
+                integrity: Integrity::Strict,
+                references: self.strict.clone(),
+            },
+            Relation {
+                field: "loose".into(),
+                ty: "Amounts".into(),
+                integrity: Integrity::Warn,
+                references: self.loose.clone(),
+            },
+        ]
+    }
+}
+
+fn dangling(field: &str, reference: Reference) -> Dangling {
+    Dangling {
+        ty: "Holders".into(),
+        id: id(9),
+        field: field.into(),
+        target: "Amounts".into(),
+        reference,
+    }
+}
+
+#[test]
+fn strict_relations_refuse_dangling_references() {
+    block_on(async {
+        let db = Memory::new();
+        write_amounts(&db, "Amounts", 1).await;
+
+        let mut transaction = db.begin().await.unwrap();
+        let claim = Claim {
+            key: "Stripe".into(),
+            value: "ch_1".into(),
+        };
+        transaction
+            .claim("Amounts", &id(2), &[claim])
+            .await
+            .unwrap();
+
+        let holder = Holder {
+            strict: vec![
+                reference("User", "Yoda", &id(1)),
+                reference("User", "Stripe", "ch_1"),
+            ],
+            loose: vec![reference("User", "Yoda", &id(99))],
+        };
+        let found = Driver::check_references(&mut *transaction, &id(9), &holder)
+            .await
+            .unwrap();
+        assert_eq!(found, None);
+
+        let holder = Holder {
+            strict: vec![
+                reference("User", "Stripe", "ch_2"),
+                reference("User", "Yoda", "not-a-uuid"),
+            ],
+            loose: vec![],
+        };
+        let found = Driver::check_references(&mut *transaction, &id(9), &holder)
+            .await
+            .unwrap();
+        assert_eq!(
+            found,
+            Some(dangling("strict", reference("User", "Stripe", "ch_2")))
+        );
+    })
+}
+
+#[test]
+fn scan_lists_dangling_references() {
+    block_on(async {
+        let db = Memory::new();
+        write_amounts(&db, "Amounts", 1).await;
+
+        let holder = json!({
+            "strict": [reference("User", "Yoda", &id(1)), reference("User", "Yoda", &id(98))],
+            "loose": [reference("User", "Yoda", &id(99))],
+        });
+        let mut transaction = db.begin().await.unwrap();
+        transaction
+            .append("Holders", &id(9), holder.clone(), "tester")
+            .await
+            .unwrap();
+        transaction
+            .project("Holders", &id(9), holder, None)
+            .await
+            .unwrap();
+        transaction.commit().await.unwrap();
+
+        assert_eq!(
+            Driver::scan::<Holder>(&db).await.unwrap(),
+            vec![
+                dangling("strict", reference("User", "Yoda", &id(98))),
+                dangling("loose", reference("User", "Yoda", &id(99))),
+            ]
+        );
+    })
+}
+
+/// The entity [`super::backend`] writes: a name each delta sets
+#[derive(Default, Clone, Debug, PartialEq)]
+struct Named {
+    name: Option<String>,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug)]
+struct NamedStore {
+    name: Delta<String>,
+}
+
+impl Store for NamedStore {
+    fn ty() -> String {
+        "Named".into()
+    }
+    fn identifier(&self) -> String {
+        String::new()
+    }
+    fn claims(&self) -> Vec<Claim> {
+        vec![]
+    }
+    fn lifecycle(&self) -> Option<Lifecycle> {
+        None
+    }
+    fn tombstone(_: Lifecycle) -> Self {
+        Self {
+            name: Delta::default(),
+        }
+    }
+}
+
+impl From<NamedStore> for Named {
+    fn from(store: NamedStore) -> Self {
+        Self {
+            name: store.name.end,
+        }
+    }
+}
+
+impl From<Named> for NamedStore {
+    fn from(named: Named) -> Self {
+        Self {
+            name: Delta::init(named.name).unwrap_or_default(),
+        }
+    }
+}
+
+impl Del<NamedStore> for Named {
+    fn ty() -> String {
+        NamedStore::ty()
+    }
+    fn apply(&mut self, del: NamedStore) {
+        if del.name.end.is_some() {
+            self.name = del.name.end;
+        }
+    }
+    fn try_apply(&mut self, del: NamedStore) -> Result<(), DeltaConflict> {
+        self.apply(del);
+        Ok(())
+    }
+}
+
+#[test]
+fn rollup_refuses_entities_not_yet_written() {
+    block_on(async {
+        let db = Memory::new();
+        let before = Utc::now();
+
+        let mut transaction = db.begin().await.unwrap();
+        transaction
+            .append(
+                "Named",
+                &id(1),
+                json!({ "name": { "start": null, "end": "Boston" } }),
+                "tester",
+            )
+            .await
+            .unwrap();
+        transaction.commit().await.unwrap();
+
+        let rollup = |id: String, as_of, version| {
+            let db = &db;
+            async move { Driver::rollup::<Named, NamedStore>(db, &id, as_of, version).await }
+        };
+
+        assert_eq!(
+            rollup(id(1), None, None).await.unwrap(),
+            Named {
+                name: Some("Boston".into())
+            }
+        );
+        for found in [
+            rollup(id(1), Some(before), None).await,
+            rollup(id(1), None, Some(0)).await,
+            rollup(id(2), None, None).await,
+        ] {
+            assert!(matches!(found, Err(sqlx::Error::RowNotFound)));
+        }
+    })
+}
+
+#[test]
+fn changes_follow_an_entity_from_its_new_deltas() {
+    block_on(async {
+        let db = Database::new(Memory::new());
+        let mut changes = Driver::changes::<Named, NamedStore>(db.clone(), Some(id(1)))
+            .await
+            .unwrap();
+        let mut created = Driver::created::<Named, NamedStore>(db.clone())
+            .await
+            .unwrap();
+
+        for (n, name) in [(2, "Boston"), (1, "Boston"), (1, "Cambridge")] {
+            let mut transaction = db.begin().await.unwrap();
+            transaction
+                .append(
+                    "Named",
+                    &id(n),
+                    json!({ "name": { "start": null, "end": name } }),
+                    "tester",
+                )
+                .await
+                .unwrap();
+            transaction.commit().await.unwrap();
+        }
+
+        let first = changes.next().await.unwrap();
+        assert_eq!((first.id, first.delta.version), (id(1), 1));
+        assert_eq!(first.entity.unwrap().name.as_deref(), Some("Boston"));
+
+        let second = changes.next().await.unwrap();
+        assert_eq!((second.id, second.delta.version), (id(1), 2));
+        assert_eq!(second.entity.unwrap().name.as_deref(), Some("Cambridge"));
+        assert_eq!(second.delta.changes[0].before.0, json!("Boston"));
+
+        let ids = [created.next().await.unwrap(), created.next().await.unwrap()]
+            .map(|change| (change.id, change.delta.version));
+        assert_eq!(ids, [(id(2), 1), (id(1), 1)]);
+    })
+}
+
+/// An entity with a `#[unique]` field that is `#[personal_data]`, and one that isn't
+#[derive(Default, Clone)]
+pub(super) struct Person {
+    email: Option<String>,
+    nickname: Option<String>,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug)]
+pub(super) struct PersonStore {
+    email: Option<Delta<String>>,
+    nickname: Option<Delta<String>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    lifecycle: Option<Delta<Lifecycle>>,
+}
+
+impl Store for PersonStore {
+    fn ty() -> String {
+        "Person".into()
+    }
+    fn identifier(&self) -> String {
+        String::new()
+    }
+    fn claims(&self) -> Vec<Claim> {
+        let email = self.email.as_ref().and_then(|delta| delta.end.as_ref());
+        let nickname = self.nickname.as_ref().and_then(|delta| delta.end.as_ref());
+
+        email
+            .map(|value| Claim::field("email", value))
+            .into_iter()
+            .chain(nickname.map(|value| Claim::field("nickname", value)))
+            .collect()
+    }
+    fn lifecycle(&self) -> Option<Lifecycle> {
+        self.lifecycle.as_ref().and_then(|delta| delta.end)
+    }
+    fn tombstone(lifecycle: Lifecycle) -> Self {
+        Self {
+            email: None,
+            nickname: None,
+            lifecycle: Delta::init(Some(lifecycle)),
+        }
+    }
+    fn personal_data() -> Vec<String> {
+        vec!["email".into()]
+    }
+}
+
+impl From<PersonStore> for Person {
+    fn from(store: PersonStore) -> Self {
+        Self {
+            email: store.email.and_then(|delta| delta.end),
+            nickname: store.nickname.and_then(|delta| delta.end),
+        }
+    }
+}
+
+impl From<Person> for PersonStore {
+    fn from(person: Person) -> Self {
+        Self {
+            email: Delta::init(person.email),
+            nickname: Delta::init(person.nickname),
+            lifecycle: None,
+        }
+    }
+}
+
+impl Del<PersonStore> for Person {
+    fn ty() -> String {
+        PersonStore::ty()
+    }
+    fn apply(&mut self, del: PersonStore) {
+        if let Some(email) = del.email.and_then(|delta| delta.end) {
+            self.email = Some(email);
+        }
+        if let Some(nickname) = del.nickname.and_then(|delta| delta.end) {
+            self.nickname = Some(nickname);
+        }
+    }
+    fn try_apply(&mut self, del: PersonStore) -> Result<(), DeltaConflict> {
+        self.apply(del);
+        Ok(())
+    }
+}
+
+/// Erasing a deleted entity gives up its personal claims, keeping the rest for restoring it
+async fn erase_releases_personal_claims(db: &dyn Backend) {
+    let tester = auth::Identity {
+        user_id: "tester".into(),
+        roles: vec![],
+    };
+    let person = || Person {
+        email: Some("leia@yoda.dev".into()),
+        nickname: Some("leia".into()),
+    };
+
+    let mut transaction = db.begin().await.unwrap();
+    let created: PersonStore = person().into();
+    assert_eq!(
+        Driver::claim(&mut *transaction, &id(1), &created)
+            .await
+            .unwrap(),
+        None
+    );
+    Driver::delta(&mut *transaction, &id(1), created, &tester)
+        .await
+        .unwrap();
+    Driver::project::<PersonStore>(&mut *transaction, &id(1), person().into())
+        .await
+        .unwrap();
+    Driver::tombstone::<PersonStore>(&mut *transaction, &id(1), Lifecycle::Deleted, &tester)
+        .await
+        .unwrap();
+    transaction.commit().await.unwrap();
+
+    let mut transaction = db.begin().await.unwrap();
+    Driver::erase::<Person, PersonStore>(&mut *transaction, &id(1), &tester)
+        .await
+        .unwrap();
+    transaction.commit().await.unwrap();
+
+    let mut transaction = db.begin().await.unwrap();
+    let email = Claim::field("email", &"leia@yoda.dev");
+    assert_eq!(transaction.claimant("Person", &email).await.unwrap(), None);
+    let nickname = Claim::field("nickname", &"leia");
+    assert_eq!(
+        transaction.claimant("Person", &nickname).await.unwrap(),
+        Some(id(1))
+    );
+}
+
+#[test]
+fn erasing_deleted_entities_releases_personal_claims() {
+    block_on(async {
+        erase_releases_personal_claims(&Memory::new()).await;
+
+        let db = crate::sqlite::Sqlite::connect("sqlite::memory:")
+            .await
+            .unwrap();
+        erase_releases_personal_claims(&db).await;
+    })
+}
+
+#[test]
+fn changes_after_an_erasure_forget_erased_values() {
+    block_on(async {
+        let db = Database::new(Memory::new());
+        let tester = auth::Identity {
+            user_id: "tester".into(),
+            roles: vec![],
+        };
+        let mut changes = Driver::changes::<Person, PersonStore>(db.clone(), Some(id(1)))
+            .await
+            .unwrap();
+
+        let mut transaction = db.begin().await.unwrap();
+        let created = Person {
+            email: Some("leia@yoda.dev".into()),
+            nickname: Some("leia".into()),
+        };
+        Driver::delta::<PersonStore>(&mut *transaction, &id(1), created.into(), &tester)
+            .await
+            .unwrap();
+        transaction.commit().await.unwrap();
+
+        let created = changes.next().await.unwrap().entity.unwrap();
+        assert_eq!(created.email.as_deref(), Some("leia@yoda.dev"));
+
+        let mut transaction = db.begin().await.unwrap();
+        Driver::erase::<Person, PersonStore>(&mut *transaction, &id(1), &tester)
+            .await
+            .unwrap();
+        transaction.commit().await.unwrap();
+
+        let mut transaction = db.begin().await.unwrap();
+        let renamed = Person {
+            email: None,
+            nickname: Some("general".into()),
+        };
+        Driver::delta::<PersonStore>(&mut *transaction, &id(1), renamed.into(), &tester)
+            .await
+            .unwrap();
+        transaction.commit().await.unwrap();
+
+        // The erasure itself
+        changes.next().await.unwrap();
+
+        let renamed = changes.next().await.unwrap();
+        assert_eq!(renamed.delta.version, 3);
+        let renamed = renamed.entity.unwrap();
+        assert_eq!(renamed.email, None);
+        assert_eq!(renamed.nickname.as_deref(), Some("general"));
+    })
+}
+
+#[test]
+fn erasure_changes_carry_no_erased_values() {
+    block_on(async {
+        let db = Database::new(Memory::new());
+        let tester = auth::Identity {
+            user_id: "tester".into(),
+            roles: vec![],
+        };
+        let mut changes = Driver::changes::<Person, PersonStore>(db.clone(), Some(id(1)))
+            .await
+            .unwrap();
+
+        let mut transaction = db.begin().await.unwrap();
+        let created = Person {
+            email: Some("leia@yoda.dev".into()),
+            nickname: Some("leia".into()),
+        };
+        Driver::delta::<PersonStore>(&mut *transaction, &id(1), created.into(), &tester)
+            .await
+            .unwrap();
+        transaction.commit().await.unwrap();
+
+        changes.next().await.unwrap();
+
+        let mut transaction = db.begin().await.unwrap();
+        Driver::erase::<Person, PersonStore>(&mut *transaction, &id(1), &tester)
+            .await
+            .unwrap();
+        transaction.commit().await.unwrap();
+
+        let erasure = changes.next().await.unwrap();
+        let erased = erasure.entity.unwrap();
+        assert_eq!(erased.email, None);
+        assert_eq!(erased.nickname.as_deref(), Some("leia"));
+
+        let email = &erasure.delta.changes[0];
+        assert_eq!(email.field, "email");
+        assert!(email.redacted);
+        assert_eq!(email.before.0, json!(null));
+        assert_eq!(email.after.0, json!(null));
+    })
+}
+
+#[test]
+fn references_resolve_through_the_loader_in_order() {
+    block_on(async {
+        let db = Database::new(Memory::new());
+
+        let mut transaction = db.begin().await.unwrap();
+        let claim = Claim::identifier(reference("User", "Stripe", "cus_1").identifier());
+        transaction.claim("Named", &id(1), &[claim]).await.unwrap();
+        transaction.commit().await.unwrap();
+
+        let references = [
+            reference("User", "Yoda", &id(2)),
+            reference("User", "Stripe", "cus_1"),
+            reference("User", "Stripe", "cus_9"),
+        ];
+        let loader = crate::Projections::loader(db.clone());
+
+        assert_eq!(
+            Driver::referenced::<NamedStore>(&loader, &references)
+                .await
+                .unwrap(),
+            vec![id(2), id(1)]
+        );
+    })
+}
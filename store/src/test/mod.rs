@@ -0,0 +1,74 @@
+mod backend;
+mod driver;
+mod replay;
+
+use futures::executor::block_on;
+
+#[test]
+fn memory() {
+    block_on(backend::all(&crate::memory::Memory::new()))
+}
+
+#[test]
+fn sqlite() {
+    block_on(async {
+        let db = crate::sqlite::Sqlite::connect("sqlite::memory:")
+            .await
+            .unwrap();
+        backend::all(&db).await
+    })
+}
+
+/// Opt in with `TEST_DATABASE_URL` naming a migrated Postgres database, which is emptied first
+#[test]
+fn postgres() {
+    let url = match std::env::var("TEST_DATABASE_URL") {
+        Ok(url) => url,
+        Err(_) => return,
+    };
+
+    block_on(async {
+        let pool = sqlx::PgPool::connect(&url).await.unwrap();
+        sqlx::query("TRUNCATE delta, snapshot, projection, identifier, reference")
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        backend::all(&crate::sql::Postgres::new(pool)).await
+    })
+}
+
+#[test]
+fn sqlite_missing_indexes() {
+    use crate::Backend;
+    use atoms::search::{Index, Kind};
+
+    block_on(async {
+        let pool = sqlx::sqlite::SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect("sqlite::memory:")
+            .await
+            .unwrap();
+        sqlx::migrate!("../migrations/sqlite")
+            .run(&pool)
+            .await
+            .unwrap();
+        let db = crate::sqlite::Sqlite::new(pool.clone());
+
+        let amount = Index::value("Indexed", "amount", Kind::Number);
+        // SQLite has no trigram indexes, so never misses one
+        let declared = vec![amount.clone(), Index::trigram("Indexed", "name")];
+
+        assert_eq!(
+            db.missing_indexes(&declared).await.unwrap(),
+            vec![amount.clone()]
+        );
+
+        sqlx::query(&crate::sqlite::index_sql(&amount).unwrap())
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        assert!(db.missing_indexes(&declared).await.unwrap().is_empty());
+    })
+}
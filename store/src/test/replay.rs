@@ -0,0 +1,110 @@
+use futures::executor::block_on;
+use serde_json::json;
+
+use super::{
+    backend::id,
+    driver::{Person, PersonStore},
+};
+use crate::{memory::Memory, replay::Replay, Backend};
+
+/// A person created with an email, whose projection says otherwise
+async fn diverged(db: &dyn Backend, n: u8) {
+    let mut transaction = db.begin().await.unwrap();
+    transaction
+        .append(
+            "Person",
+            &id(n),
+            json!({ "email": { "start": null, "end": "leia@yoda.dev" } }),
+            "tester",
+        )
+        .await
+        .unwrap();
+    transaction
+        .project("Person", &id(n), json!({ "email": null }), None)
+        .await
+        .unwrap();
+    transaction.commit().await.unwrap();
+}
+
+async fn projection(db: &dyn Backend, n: u8) -> Option<serde_json::Value> {
+    db.projections("Person", &[id(n)])
+        .await
+        .unwrap()
+        .pop()
+        .map(|(_, body)| body)
+}
+
+#[test]
+fn dry_runs_report_divergences_without_writing() {
+    block_on(async {
+        let db = Memory::new();
+        diverged(&db, 1).await;
+
+        let divergence = Replay::entity::<Person, PersonStore>(&db, &id(1), true)
+            .await
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(divergence.projected, Some(json!({ "email": null })));
+        assert_eq!(
+            divergence.replayed.unwrap()["email"]["end"],
+            "leia@yoda.dev"
+        );
+        assert_eq!(projection(&db, 1).await, Some(json!({ "email": null })));
+    })
+}
+
+#[test]
+fn replays_rewrite_diverged_projections() {
+    block_on(async {
+        let db = Memory::new();
+        diverged(&db, 1).await;
+
+        let divergence = Replay::entity::<Person, PersonStore>(&db, &id(1), false)
+            .await
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(projection(&db, 1).await, divergence.replayed);
+
+        let again = Replay::entity::<Person, PersonStore>(&db, &id(1), false)
+            .await
+            .unwrap();
+        assert!(again.is_none());
+    })
+}
+
+#[test]
+fn replays_unproject_deleted_entities() {
+    block_on(async {
+        let db = Memory::new();
+        diverged(&db, 1).await;
+        diverged(&db, 2).await;
+
+        let mut transaction = db.begin().await.unwrap();
+        transaction
+            .append(
+                "Person",
+                &id(2),
+                json!({ "lifecycle": { "start": null, "end": "Deleted" } }),
+                "tester",
+            )
+            .await
+            .unwrap();
+        transaction.commit().await.unwrap();
+
+        let report = Replay::ty::<Person, PersonStore, _>(&db, None, false, |_| {})
+            .await
+            .unwrap();
+
+        assert_eq!(report.replayed, 2);
+        let deleted = report
+            .divergences
+            .iter()
+            .find(|divergence| divergence.id == id(2))
+            .unwrap();
+        assert_eq!(deleted.replayed, None);
+        assert_eq!(projection(&db, 2).await, None);
+        assert!(projection(&db, 1).await.is_some());
+    })
+}